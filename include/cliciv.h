@@ -0,0 +1,44 @@
+/*
+ * C interface of the cliciv engine, built with the `cliciv-ffi` feature:
+ *
+ *     cargo rustc --release --lib --features cliciv-ffi --crate-type cdylib
+ *
+ * States are opaque. Saves and actions are JSON strings, as on the command line.
+ * Calls that fail return NULL or -1, and cliciv_last_error() tells why.
+ */
+
+#ifndef CLICIV_H
+#define CLICIV_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct CliCivState CliCivState;
+
+/* New game with a random seed. Freed with cliciv_free(). */
+CliCivState *cliciv_create(void);
+
+/* Game read from the JSON of a save. Freed with cliciv_free(). */
+CliCivState *cliciv_load(const char *json);
+
+/* Applies an action, like "Idle" or {"Collect":"Food"}. Returns 0 when it was taken. */
+int cliciv_apply_action(CliCivState *state, const char *action_json);
+
+/* JSON of the game, as it is saved. Freed with cliciv_string_free(). */
+char *cliciv_serialize(const CliCivState *state);
+
+/* Replays the game to check it. Returns 0 when it is valid. */
+int cliciv_check(const CliCivState *state);
+
+/* Why the last failed call on this thread failed, or NULL. Owned by the library. */
+const char *cliciv_last_error(void);
+
+void cliciv_free(CliCivState *state);
+void cliciv_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,91 @@
+use crate::game::state::State;
+use crate::game::actions::Action;
+use crate::game::errors::Error;
+use super::server::Saves;
+use crate::CheckReport;
+
+use std::sync::Mutex;
+use tonic::{Request, Response, Status};
+use tonic::transport::Server;
+
+mod proto {
+    tonic::include_proto!("cliciv");
+}
+
+use proto::game_server::{Game, GameServer};
+use proto::{ActionRequest, CheckReply, SaveRequest, StateReply};
+
+/**
+ * Serves the saves in a directory over gRPC, with the calls of the HTTP server. Calls
+ * are handled one at a time, so a save is never written twice at once.
+ */
+pub fn serve(saves: Saves, port: u16) -> Result<(), Error> {
+    let runtime = tokio::runtime::Runtime::new().map_err(Error::Io)?;
+    let address = ([127, 0, 0, 1], port).into();
+    eprintln!("Serving {} on grpc://127.0.0.1:{}", saves.dir().display(), port);
+
+    runtime.block_on(Server::builder()
+        .add_service(GameServer::new(Service(Mutex::new(saves))))
+        .serve(address))
+        .map_err(|error| Error::Io(std::io::Error::other(error.to_string())))
+}
+
+struct Service(Mutex<Saves>);
+
+#[tonic::async_trait]
+impl Game for Service {
+    async fn get_state(&self, request: Request<SaveRequest>) -> Result<Response<StateReply>, Status> {
+        let saves = self.0.lock().unwrap();
+        let (_, state) = saves.read(&request.get_ref().name).map_err(status)?;
+
+        Ok(Response::new(reply(&state)))
+    }
+
+    async fn check(&self, request: Request<SaveRequest>) -> Result<Response<CheckReply>, Status> {
+        let saves = self.0.lock().unwrap();
+        let (_, state) = saves.read(&request.get_ref().name).map_err(status)?;
+        let report = CheckReport::new(state.check());
+
+        Ok(Response::new(CheckReply {
+            valid: report.valid,
+            code: report.code.map(str::to_string),
+            error: report.error,
+            iteration: report.iteration.map(|iteration| iteration as u64),
+            expected_hash: report.expected_hash.map(|hash| hash.to_hex()),
+            actual_hash: report.actual_hash.map(|hash| hash.to_hex()),
+            action: report.action.map(|action| serde_json::to_string(&action).unwrap())
+        }))
+    }
+
+    async fn apply_action(&self, request: Request<ActionRequest>) -> Result<Response<StateReply>, Status> {
+        let saves = self.0.lock().unwrap();
+        let action: Action = serde_json::from_str(&request.get_ref().action)
+            .map_err(|error| status(Error::ActionParseError(error)))?;
+        let state = saves.apply(&request.get_ref().name, action).map_err(status)?;
+
+        Ok(Response::new(reply(&state)))
+    }
+}
+
+fn reply(state: &State) -> StateReply {
+    StateReply {
+        iterations: state.commitment().iterations as u64,
+        hash: state.hash().to_hex(),
+        state: serde_json::to_string(state).unwrap()
+    }
+}
+
+/**
+ * Same mapping as the HTTP status codes of the HTTP server.
+ */
+fn status(error: Error) -> Status {
+    let message = format!("{}: {}", error.code(), error);
+
+    match error {
+        Error::UnknownArgument(_) => Status::not_found(message),
+        Error::Io(error) if error.kind() == std::io::ErrorKind::NotFound => Status::not_found(message),
+        Error::ActionParseError(_) => Status::invalid_argument(message),
+        Error::Check(_) | Error::Iteration(_) => Status::failed_precondition(message),
+        _ => Status::internal(message)
+    }
+}
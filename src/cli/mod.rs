@@ -0,0 +1,2433 @@
+mod server;
+mod output;
+#[cfg(feature = "grpc")]
+mod grpc;
+
+use crate::{game, CheckReport};
+use game::state::{State, LogEntry, LogDocument, Checkpoint, ReplayStep};
+use game::settings::{Settings, daily_seed};
+use game::actions::Action;
+use game::errors::{CheckError, Error, SyncError};
+use game::signing::{generate_key, parse_signing_key};
+use game::save_file::SaveFile;
+use game::log_file::LogFile;
+use game::database::Database;
+use game::remote::Remote;
+use game::rules::ENGINE_VERSION;
+use game::phrase::parse_seed_phrase;
+use game::merkle::ActionProof;
+use game::run_proof::RunProof;
+use game::challenge::ChallengeResult;
+use game::trade::{TradeOffer, TradeReceipt};
+use game::hashing::Digest;
+use game::quantity::Quantity;
+use game::resources::{Resource, PrimaryResource};
+use game::buildings::Buildings;
+use game::land::Land;
+use game::jobs::{Job, Specialization};
+use game::events::Event;
+use game::simulation::simulate;
+use game::forecast::forecast;
+use game::advisor::advise;
+use game::autoplay::{autoplay, Strategy};
+use game::optimizer::{optimize, Target};
+use game::roi::roi;
+use game::map::{map, legend, MAP_WIDTH};
+use game::luck::luck;
+use game::session::{resume_summary, elapsed};
+use game::timeline::{sessions, utc, SESSION_GAP};
+use game::civclicker::{civclicker_save, civclicker_import};
+use game::scenarios::Scenario;
+use output::OutputFormat;
+
+use std::io::{Read, Write, ErrorKind};
+use std::fs::OpenOptions;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::Serialize;
+use serde_json::{from_reader, from_str, to_string, to_string_pretty};
+use ed25519_dalek::SigningKey;
+
+// Where the signing key is kept unless `CLICIV_KEY_FILE` says otherwise.
+const DEFAULT_KEY_FILE: &str = "cliciv.key";
+
+// Database used by `--backend sqlite` unless `--db` says otherwise.
+const DEFAULT_DATABASE: &str = "cliciv.db";
+
+// Set by `--json-errors`, for scripts that read the errors.
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Runs the command line on its arguments, the name of the program first. Commands that
+ * fail report why and exit the process.
+ */
+pub fn run(mut args: Vec<String>) {
+
+    if let Some(index) = args.iter().position(|arg| arg == "--json-errors") {
+        JSON_ERRORS.store(true, Ordering::Relaxed);
+        args.remove(index);
+    }
+
+    let mut save_file = match save_file(&mut args) {
+        Ok(save_file) => save_file,
+        Err(error) => fail(error)
+    };
+
+    let output = match output_format(&mut args) {
+        Ok(output) => output,
+        Err(error) => fail(error)
+    };
+
+    let state = match args.get(1).map(String::as_str) {
+        Some("create") => match create(&args[2..]) {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("grant") => match grant(&args[2..]) {
+            Ok(action) => advance(load(&mut save_file), vec!{(action, 1)}),
+            Err(error) => fail(error)
+        },
+
+        Some("barter") => match barter(&args[2..]) {
+            Ok(action) => advance(load(&mut save_file), vec!{(action, 1)}),
+            Err(error) => fail(error)
+        },
+
+        Some("sacrifice") => match sacrifice(&args[2..]) {
+            Ok(action) => advance(load(&mut save_file), vec!{(action, 1)}),
+            Err(error) => fail(error)
+        },
+
+        Some("omens") => match read_omens(&args[2..]) {
+            Ok(action) => advance(load(&mut save_file), vec!{(action, 1)}),
+            Err(error) => fail(error)
+        },
+
+        Some("queue") if matches!(args.get(2).map(String::as_str), None | Some("list")) => {
+            if let Err(error) = construction_queue(&peek(&mut save_file), args.get(3..).unwrap_or_default(), output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("queue") => match queue(&args[2..]) {
+            Ok(action) => advance(load(&mut save_file), vec!{(action, 1)}),
+            Err(error) => fail(error)
+        },
+
+        Some("turn") => match turn(&args[2..]) {
+            Ok(action) => advance(load(&mut save_file), vec!{(action, 1)}),
+            Err(error) => fail(error)
+        },
+
+        Some("keygen") => {
+            match keygen() {
+                Ok(public_key) => println!("{}", public_key),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("verify") if args.get(2).is_some_and(|arg| !arg.starts_with("--")) => {
+            if let Err(error) = verify_log(&args[2..]) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("verify") => {
+            let state = load(&mut save_file);
+
+            if let Err(error) = verify(&state, &args[2..]) {
+                fail(error);
+            }
+
+            write(&save_file, &state);
+            return;
+        },
+
+        Some("convert-save") => {
+            let format = save_file.format.unwrap_or_default();
+            let encrypted = save_file.encrypted;
+            let compression = save_file.compression;
+            let state = load(&mut save_file);
+
+            save_file = SaveFile { format: Some(format), encrypted, compression, ..save_file };
+            write(&save_file, &state);
+            return;
+        },
+
+        Some("prove") if args.len() == 2 => {
+            let state = load(&mut save_file);
+
+            match state.check() {
+                Ok(()) => println!("{}", to_string_pretty(&state.run_proof()).unwrap()),
+                Err(error) => fail(Error::Check(error))
+            }
+
+            return;
+        },
+
+        Some("prove") => {
+            match prove(&load(&mut save_file), &args[2..]) {
+                Ok(proof) => println!("{}", to_string_pretty(&proof).unwrap()),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("challenge") if args.get(2).map(String::as_str) == Some("verify") => {
+            match verify_challenge(&args[3..]) {
+                Ok(result) => println!("Daily challenge of {}: {} points in {} iterations, by {}",
+                    result.date, result.score, result.iterations, result.proof.signature.map(|signature| signature.public_key).unwrap_or_default()),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("challenge") => {
+            match submit_challenge(load(&mut save_file), &args[2..]) {
+                Ok(result) => println!("{}", to_string_pretty(&result).unwrap()),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("verify-proof") if args.get(2).is_some_and(|arg| !arg.starts_with("--")) => {
+            match verify_run_proof(&args[2..]) {
+                Ok(proof) => println!("Run of {} iterations and {} actions, population {}: {}",
+                    proof.commitment.iterations, proof.actions, proof.citizens.count(), proof.hash),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("verify-proof") => {
+            match verify_proof(&args[2..]) {
+                Ok(proof) => println!("Action #{} at iteration {}: {:?}", proof.index, proof.iteration, proof.action),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("at") => {
+            match at(&load(&mut save_file), &args[2..]) {
+                Ok(output) => println!("{}", output),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("fork") => {
+            let state = load(&mut save_file);
+
+            match fork(&save_file, &state, &args[2..]) {
+                Ok(path) => eprintln!("Forked into {}", path),
+                Err(error) => fail(error)
+            }
+
+            write(&save_file, &state);
+            return;
+        },
+
+        Some("replay") => {
+            if let Err(error) = replay(&load(&mut save_file), &args[2..]) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("chain") => {
+            if let Err(error) = chain(&load(&mut save_file), &args[2..]) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("status") => {
+            let state = peek(&mut save_file);
+
+            // TOML integers are only 64 bits wide, so the seed is written as text like in `debug dump`.
+            let printed = match output {
+                Some(OutputFormat::Toml) => OutputFormat::Toml.print(&state.editable()),
+                Some(OutputFormat::Porcelain) => {
+                    print!("{}", state.porcelain());
+                    Ok(())
+                },
+                Some(output) => output.print(&state),
+                None => {
+                    print!("{}", state);
+                    Ok(())
+                }
+            };
+
+            if let Err(error) = printed {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("prompt") => {
+            match prompt(&peek(&mut save_file), &args[2..]) {
+                Ok(prompt) => println!("{}", prompt),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("top") => {
+            if let Err(error) = top(&mut save_file, &args[2..]) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("events") => {
+            if let Err(error) = events(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("history") => {
+            if let Err(error) = history(&mut save_file, &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("advise") => {
+            if let Err(error) = advice(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        // Luck is counted replaying the log, which peeking leaves out.
+        Some("stats") if args.get(2).map(String::as_str) == Some("--luck") => {
+            if let Err(error) = statistics(&load(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("stats") => {
+            if let Err(error) = statistics(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("timeline") => {
+            if let Err(error) = timeline(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("map") => {
+            if let Err(error) = land_map(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("roi") => {
+            if let Err(error) = paybacks(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("forecast") => {
+            if let Err(error) = projection(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("can") => match spoken_action(&args[2..]).map(|action| peek(&mut save_file).apply_action(action)) {
+            Ok(Ok(_)) => {
+                println!("yes");
+                return;
+            },
+            Ok(Err(error)) => {
+                let exit_code = error.exit_code();
+                report(&Error::Iteration(error));
+                std::process::exit(exit_code)
+            },
+            Err(error) => fail(error)
+        },
+
+        Some("optimize") => {
+            if let Err(error) = optimization(&peek(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("simulate") => {
+            match simulation(&args[2..]) {
+                Ok(true) => return,
+                Ok(false) => std::process::exit(1),
+                Err(error) => fail(error)
+            }
+        },
+
+        Some("check") if args[2..].iter().any(|arg| arg == "--json") || output.is_some() => {
+            match check_report(&load(&mut save_file), &args[2..]) {
+                Ok(report) => {
+                    if let Err(error) = output.unwrap_or_default().print(&report) {
+                        fail(error);
+                    }
+
+                    if !report.valid {
+                        std::process::exit(1);
+                    }
+                },
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("check") => {
+            let state = load(&mut save_file);
+
+            match check(&save_file, &state, &args[2..]) {
+                Ok(Some(repaired_state)) => repaired_state,
+                Ok(None) => {
+                    write(&save_file, &state);
+                    return;
+                },
+                Err(error) => fail(error)
+            }
+        },
+
+        Some("serve") => {
+            if let Err(error) = server::serve(&args[2..]) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("trade") => match trade(load(&mut save_file), &args[2..]) {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("sync") => {
+            match sync(&mut save_file, &args[2..]) {
+                Ok(state) => write(&save_file, &state),
+                Err(error) => fail(error)
+            }
+
+            return;
+        },
+
+        Some("rollback") => match rollback(&load(&mut save_file), &args[2..]) {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("log") if args.get(2).map(String::as_str) == Some("--filter") => {
+            if let Err(error) = search_log(&load(&mut save_file), &args[2..], output) {
+                fail(error);
+            }
+
+            return;
+        },
+
+        Some("log") => {
+            let state = load(&mut save_file);
+
+            if let Err(error) = export_log(&state, &args[2..], output) {
+                fail(error);
+            }
+
+            write(&save_file, &state);
+            return;
+        },
+
+        Some("export") => {
+            let state = load(&mut save_file);
+
+            if let Err(error) = export(&state, &args[2..]) {
+                fail(error);
+            }
+
+            write(&save_file, &state);
+            return;
+        },
+
+        Some("debug") if args.get(2).map(String::as_str) == Some("dump") => {
+            let state = load(&mut save_file);
+
+            if let Err(error) = debug_dump(&state, &args[3..]) {
+                fail(error);
+            }
+
+            write(&save_file, &state);
+            return;
+        },
+
+        Some("debug") => match debug_load(&args[2..]) {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("import") => match import(&args[2..]) {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("import-log") => match import_log(&args[2..]) {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("migrate") => match migrate() {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("autoplay") => match play(load(&mut save_file), &args[2..]) {
+            Ok(state) => state,
+            Err(error) => fail(error)
+        },
+
+        Some("catch-up") => {
+            let previous_state = load(&mut save_file);
+            let iterations = previous_state.catch_up_iterations(now());
+
+            advance(previous_state, vec!{(Action::Idle, iterations)})
+        },
+
+        Some(action) => match atty::isnt(atty::Stream::Stdin) {
+            true => {
+                let previous_state = load(&mut save_file);
+
+                match from_str(action) {
+                    Ok(action) => advance(previous_state, vec!{(action, 1)}),
+                    Err(error) => {
+                        report(&Error::ActionParseError(error));
+                        previous_state
+                    }
+                }
+            },
+
+            false => fail(Error::NoGame)
+        },
+
+        None => State::rand()
+    };
+
+    write(&save_file, &commit(state));
+}
+
+/**
+ * Reports an error on the standard error. With `--json-errors` it is written as a line
+ * of JSON with the error code and message.
+ */
+fn report(error: &Error) {
+    match JSON_ERRORS.load(Ordering::Relaxed) {
+        true => eprintln!("{}", serde_json::json!({ "code": error.code(), "message": error.to_string() })),
+        false => eprintln!("{}", error)
+    }
+}
+
+/**
+ * Reports an error and exits with the status matching it.
+ */
+fn fail(error: Error) -> ! {
+    report(&error);
+    std::process::exit(error.exit_code())
+}
+
+/**
+ * Stamps the state and signs it when there is a signing key.
+ */
+fn commit(state: State) -> State {
+    match signing_key() {
+        Ok(Some(key)) => state.stamp(now()).sign(&key),
+        Ok(None) => state.stamp(now()),
+        Err(error) => {
+            report(&error);
+            state.stamp(now())
+        }
+    }
+}
+
+fn key_file() -> String {
+    std::env::var("CLICIV_KEY_FILE").unwrap_or_else(|_| DEFAULT_KEY_FILE.to_string())
+}
+
+/**
+ * Reads the key committed states are signed with. Without a key file states are left
+ * unsigned.
+ */
+fn signing_key() -> Result<Option<SigningKey>, Error> {
+    let path = key_file();
+
+    match std::fs::read_to_string(&path) {
+        Ok(secret) => parse_signing_key(&secret).map(Some).ok_or(Error::InvalidKey(path)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(Error::KeyFile(error))
+    }
+}
+
+/**
+ * Generates a new signing key and stores it in the key file, which is never
+ * overwritten. Returns the public key to share with other players.
+ */
+fn keygen() -> Result<String, Error> {
+    let path = key_file();
+    let key = generate_key();
+
+    let mut file = OpenOptions::new().write(true).create_new(true).open(&path)
+        .map_err(|error| match error.kind() {
+            ErrorKind::AlreadyExists => Error::KeyFileExists(path.clone()),
+            _ => Error::KeyFile(error)
+        })?;
+
+    writeln!(file, "{}", hex::encode(key.to_bytes())).map_err(Error::KeyFile)?;
+
+    Ok(hex::encode(key.verifying_key().as_bytes()))
+}
+
+/**
+ * Checks the state and, when `--pubkey <key>` is given, that it was signed with it.
+ */
+fn verify(state: &State, args: &[String]) -> Result<(), Error> {
+    match args.first().map(String::as_str) {
+        Some("--pubkey") => {
+            let public_key = args.get(1).ok_or_else(|| Error::MissingArgument("--pubkey".to_string()))?;
+            state.verify(public_key).map_err(Error::Check)
+        },
+        Some(arg) => Err(Error::UnknownArgument(arg.to_string())),
+        None => state.check().map_err(Error::Check)
+    }
+}
+
+/**
+ * Verifies the exported log at `verify <file>` ends on the hash recorded with it,
+ * without reading any save.
+ */
+fn verify_log(args: &[String]) -> Result<(), Error> {
+    let path = match args {
+        [path] => path,
+        [_, arg, ..] => return Err(Error::UnknownArgument(arg.clone())),
+        [] => return Err(Error::MissingArgument("file".to_string()))
+    };
+    let document = std::fs::read(path).map_err(Error::Io)?;
+    let document: LogDocument = serde_json::from_slice(&document).map_err(Error::InputParseError)?;
+    let hash = document.hash.ok_or_else(|| Error::MissingArgument("hash".to_string()))?;
+
+    crate::verify(document.seed, document.settings, document.log, hash).map(|_| ()).map_err(Error::Check)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+/**
+ * Takes `--output <format>`, the format structured output is printed in, or
+ * `--porcelain`, out of the arguments. Without them, commands print what they always did.
+ */
+fn output_format(args: &mut Vec<String>) -> Result<Option<OutputFormat>, Error> {
+    if let Some(index) = args.iter().position(|arg| arg == "--porcelain") {
+        args.remove(index);
+        return Ok(Some(OutputFormat::Porcelain));
+    }
+
+    match args.iter().position(|arg| arg == "--output") {
+        Some(index) => {
+            let name = args.get(index + 1).ok_or_else(|| Error::MissingArgument("--output".to_string()))?;
+            let output = name.parse().map_err(Error::UnknownOutput)?;
+            args.drain(index..index + 2);
+
+            Ok(Some(output))
+        },
+        None => Ok(None)
+    }
+}
+
+/**
+ * Takes the save options, `--encrypt`, `--format <format>`, `--compress <algorithm>`,
+ * `--log-file <path>` and `--backend sqlite` with `--db <path>`, out of the arguments,
+ * as they can be given to any command.
+ */
+fn save_file(args: &mut Vec<String>) -> Result<SaveFile, Error> {
+    let mut save_file = SaveFile::default();
+
+    // `export --format` names what the game is exported to. Saves are read in any format.
+    let exporting = args.get(1).map(String::as_str) == Some("export");
+
+    if let Some(index) = args.iter().position(|arg| arg == "--format").filter(|_| !exporting) {
+        let name = args.get(index + 1).ok_or_else(|| Error::MissingArgument("--format".to_string()))?;
+        save_file.format = Some(name.parse().map_err(Error::UnknownFormat)?);
+        args.drain(index..index + 2);
+    }
+
+    if let Some(index) = args.iter().position(|arg| arg == "--compress") {
+        let name = args.get(index + 1).ok_or_else(|| Error::MissingArgument("--compress".to_string()))?;
+        save_file.compression = Some(name.parse().map_err(Error::UnknownCompression)?);
+        args.drain(index..index + 2);
+    }
+
+    if let Some(index) = args.iter().position(|arg| arg == "--log-file") {
+        let path = args.get(index + 1).ok_or_else(|| Error::MissingArgument("--log-file".to_string()))?;
+        save_file.log_file = Some(LogFile { path: PathBuf::from(path) });
+        args.drain(index..index + 2);
+    }
+
+    if let Some(index) = args.iter().position(|arg| arg == "--encrypt") {
+        save_file.encrypted = true;
+        args.remove(index);
+    }
+
+    let mut database = Database { path: PathBuf::from(DEFAULT_DATABASE) };
+
+    if let Some(index) = args.iter().position(|arg| arg == "--db") {
+        let path = args.get(index + 1).ok_or_else(|| Error::MissingArgument("--db".to_string()))?;
+        database.path = PathBuf::from(path);
+        args.drain(index..index + 2);
+    }
+
+    if let Some(index) = args.iter().position(|arg| arg == "--backend") {
+        let name = args.get(index + 1).ok_or_else(|| Error::MissingArgument("--backend".to_string()))?;
+        save_file.database = match name.as_str() {
+            "file" => None,
+            "sqlite" => Some(database),
+            _ => return Err(Error::UnknownArgument(name.clone()))
+        };
+        args.drain(index..index + 2);
+    }
+
+    Ok(save_file)
+}
+
+/**
+ * Reads the previous state from the standard input, warning when it was created with
+ * another version of the engine. Players at a terminal coming back to a save after a
+ * while are reminded where they left it.
+ */
+fn load(save_file: &mut SaveFile) -> State {
+    match save_file.read(std::io::stdin()) {
+        Ok(state) => {
+            let rules = state.rules();
+
+            if rules.is_stamped() && rules.engine != ENGINE_VERSION {
+                eprintln!("Save created with cliciv {}, running {}", rules.engine, ENGINE_VERSION);
+            }
+
+            if atty::is(atty::Stream::Stderr) {
+                if let Some(summary) = resume_summary(&state, now()) {
+                    eprintln!("{}", summary);
+                }
+            }
+
+            state
+        },
+        Err(error) => fail(error)
+    }
+}
+
+/**
+ * Reads the previous state from the standard input without its log, for commands that
+ * only look at the latest state and don't write it back.
+ */
+fn peek(save_file: &mut SaveFile) -> State {
+    match save_file.read_without_log(std::io::stdin()) {
+        Ok(state) => state,
+        Err(error) => fail(error)
+    }
+}
+
+/**
+ * Writes the state to the standard output.
+ */
+fn write(save_file: &SaveFile, state: &State) {
+    if let Err(error) = save_file.write(state, std::io::stdout()) {
+        fail(error);
+    }
+}
+
+fn checkpoint_file(state: &State) -> PathBuf {
+    let cache_dir = match std::env::var_os("CLICIV_CACHE_DIR") {
+        Some(cache_dir) => PathBuf::from(cache_dir),
+        None => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".cache").join("cliciv"))
+            .unwrap_or_default()
+    };
+
+    cache_dir.join(format!("{}.json", state.game_id()))
+}
+
+/**
+ * Checks the state replaying only what was not verified by previous checks. The last
+ * verified checkpoint of each game is cached on disk; a missing or broken cache only
+ * means a full check.
+ */
+fn check_cached(state: &State) -> Result<(), CheckError> {
+    let path = checkpoint_file(state);
+    let checkpoint: Option<Checkpoint> = std::fs::read(&path).ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+
+    if let Some(checkpoint) = state.check_from(checkpoint.as_ref())? {
+        let _ = path.parent().map(std::fs::create_dir_all);
+        let _ = std::fs::write(&path, serde_json::to_vec(&checkpoint).unwrap());
+    }
+
+    Ok(())
+}
+
+/**
+ * Checks the previous state and applies the log entries on top of it. On any error the
+ * previous state is kept as is.
+ */
+fn advance(previous_state: State, log: Vec<LogEntry>) -> State {
+    let original_state = previous_state.clone();
+
+    match try_advance(previous_state, log) {
+        Ok(state) => state,
+        Err(error) => {
+            report(&error);
+            original_state
+        }
+    }
+}
+
+/**
+ * Checks the previous state and applies the log entries on top of it. Tampered states
+ * are played without a check, as they can't pass one.
+ */
+fn try_advance(previous_state: State, log: Vec<LogEntry>) -> Result<State, Error> {
+    if !previous_state.tampered() {
+        check_cached(&previous_state).map_err(Error::Check)?;
+    }
+
+    previous_state.apply_log(log).map_err(Error::Iteration)
+}
+
+/**
+ * Creates a new game from the `create` command options. With `--phrase`, the game is
+ * seeded by the given seed phrase instead of a random seed. With `--daily`, it plays
+ * the daily challenge of the current UTC date, which takes no other options.
+ */
+fn create(args: &[String]) -> Result<State, Error> {
+    let mut settings = Settings::default();
+    let mut seed = None;
+    let mut daily = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scenario" => {
+                let name = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+                settings.scenario = Some(name.parse().map_err(Error::UnknownScenario)?);
+            },
+            "--sandbox" => settings.sandbox = true,
+            "--ironman" => settings.ironman = true,
+            "--free-management" => settings.free_management = true,
+            "--fatigue" => settings.fatigue = true,
+            "--construction" => settings.construction = true,
+            "--pity" => settings.pity = true,
+            "--gold-strikes" => settings.gold_strikes = true,
+            "--gems" => settings.gems = true,
+            "--daily" => daily = true,
+            "--soft-cap" => {
+                let soft_cap = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+                settings.soft_cap = Some(soft_cap.parse().ok().filter(|soft_cap| *soft_cap > 0)
+                    .ok_or_else(|| Error::InvalidAmount(soft_cap.clone()))?);
+            },
+            "--phrase" => {
+                let phrase = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+                seed = Some(parse_seed_phrase(phrase).ok_or_else(|| Error::InvalidSeedPhrase(phrase.clone()))?);
+            },
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    if daily {
+        let conflict = [
+            (settings.scenario.is_some(), "--scenario"),
+            (settings.sandbox, "--sandbox"),
+            (settings.ironman, "--ironman"),
+            (settings.free_management, "--free-management"),
+            (settings.soft_cap.is_some(), "--soft-cap"),
+            (settings.fatigue, "--fatigue"),
+            (settings.construction, "--construction"),
+            (settings.pity, "--pity"),
+            (settings.gold_strikes, "--gold-strikes"),
+            (settings.gems, "--gems"),
+            (seed.is_some(), "--phrase")
+        ].iter().find(|(given, _)| *given).map(|(_, option)| option.to_string());
+
+        if let Some(option) = conflict {
+            return Err(Error::ConflictingArguments("--daily".to_string(), option));
+        }
+
+        let date = &utc(now())[..10];
+        return Ok(State::with_settings(daily_seed(date), Settings::daily(date)));
+    }
+
+    match seed {
+        Some(seed) => Ok(State::with_settings(seed, settings)),
+        None => Ok(State::rand_with_settings(settings))
+    }
+}
+
+/**
+ * Starts a sandbox game from the CivClicker save in `import civclicker <file>`. It is
+ * marked as imported in its settings, and its hash chain starts from the import.
+ */
+fn import(args: &[String]) -> Result<State, Error> {
+    let path = match args {
+        [source, path] if source == "civclicker" => path,
+        [source, ..] if source != "civclicker" => return Err(Error::UnknownFormat(source.clone())),
+        [_, _, extra, ..] => return Err(Error::UnknownArgument(extra.clone())),
+        [_] => return Err(Error::MissingArgument("file".to_string())),
+        _ => return Err(Error::MissingArgument("source".to_string()))
+    };
+    let save = std::fs::read(path).map_err(Error::Io)?;
+    let save: serde_json::Value = serde_json::from_slice(&save).map_err(Error::InputParseError)?;
+    let settings = Settings {
+        scenario: Some(Scenario::Imported(Box::new(civclicker_import(&save)?))),
+        sandbox: true,
+        ..Default::default()
+    };
+
+    Ok(State::rand_with_settings(settings))
+}
+
+/**
+ * Rebuilds a save written before hashes were blake3 digests. Its old hashes can't be
+ * verified anymore, so the state is recreated from its seed, settings and log, which
+ * are read as a log document.
+ */
+fn migrate() -> Result<State, Error> {
+    let document: LogDocument = from_reader(std::io::stdin()).map_err(Error::InputParseError)?;
+
+    State::from_log_document(document).map_err(Error::Iteration)
+}
+
+/**
+ * One line summary of the game for shell prompts, like `🌾183 🌲42 ⛏9 👥6/9 i412`:
+ * food, wood, stone, population and iterations. The save is read without its log nor
+ * checked, so it is quick enough to run on every prompt. With `--color`, food turns
+ * red while it is being lost, and resources and population turn yellow when full.
+ */
+fn prompt(state: &State, args: &[String]) -> Result<String, Error> {
+    let color = match args {
+        [] => false,
+        [flag] if flag == "--color" => true,
+        [arg, ..] => return Err(Error::UnknownArgument(arg.clone()))
+    };
+    let paint = |text: String, code: Option<&str>| match (color, code) {
+        (true, Some(code)) => format!("\x1b[{}m{}\x1b[0m", code, text),
+        _ => text
+    };
+    let full = |resource: PrimaryResource| {
+        let resource = Resource::Primary(resource);
+        let amount = state.resources.amount(&resource);
+        let code = match state.resources.capacity(&resource) {
+            Some(capacity) if amount >= capacity => Some("33"),
+            _ => None
+        };
+        paint(amount.whole().to_string(), code)
+    };
+
+    let food = match state.food_left() {
+        Some(_) => paint(state.resources.food.whole().to_string(), Some("31")),
+        None => full(PrimaryResource::Food)
+    };
+    let (citizens, max_population) = state.population();
+    let population = paint(format!("{}/{}", citizens, max_population), (citizens >= max_population && max_population > 0).then_some("33"));
+
+    Ok(format!("🌾{} 🌲{} ⛏{} 👥{} i{}",
+        food, full(PrimaryResource::Wood), full(PrimaryResource::Stone), population, state.iterations()))
+}
+
+/**
+ * Writes the seed, settings and log of the game to `log export <file>`.
+ */
+fn export_log(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    match args {
+        [subcommand, path] if subcommand == "export" => {
+            let document = match output {
+                Some(output) => output.encode(&state.log_document())?,
+                None => to_string(&state.log_document()).map_err(Error::InputParseError)?.into_bytes()
+            };
+            std::fs::write(path, document).map_err(Error::Io)
+        },
+        [subcommand, ..] if subcommand != "export" => Err(Error::UnknownArgument(subcommand.clone())),
+        _ => Err(Error::MissingArgument("file".to_string()))
+    }
+}
+
+/**
+ * Entry of the log matching a filter, as listed with `--output`.
+ */
+#[derive(Serialize)]
+struct LogMatch<'a> {
+    first: usize,
+    last: usize,
+    count: u64,
+    action: &'a Action
+}
+
+/**
+ * Lists the entries of the log matching any of the `--filter` options, each with the
+ * iterations it spans. A filter is a verb, such as `build`, optionally followed by
+ * what it is taken on, such as `assign farmer`. Turns match when any of their actions
+ * does.
+ */
+fn search_log(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    let mut filters = vec!{};
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--filter" => {
+                let filter = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+                let words: Vec<String> = filter.split_whitespace().map(|word| word.to_lowercase().replace(['-', '_'], "")).collect();
+
+                match words.as_slice() {
+                    [verb] | [verb, _] if Action::VERBS.contains(&verb.as_str()) => filters.push((verb.clone(), words.get(1).cloned())),
+                    _ => return Err(Error::UnknownArgument(filter.clone()))
+                }
+            },
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    let matches = |action: &Action| std::iter::once(action).chain(action.steps()).any(|step| {
+        let (verb, object) = step.words();
+        filters.iter().any(|(filter_verb, filter_object)| verb == filter_verb && (filter_object.is_none() || *filter_object == object))
+    });
+    let found: Vec<LogMatch> = state.log_spans().into_iter()
+        .filter(|((action, _), _, _)| matches(action))
+        .map(|((action, count), first, last)| LogMatch { first, last, count: *count, action })
+        .collect();
+
+    if let Some(output) = output {
+        return output.print_list("matches", &found);
+    }
+
+    for found in found.iter() {
+        let action = to_string(found.action).map_err(Error::InputParseError)?;
+
+        match found.first == found.last {
+            true => println!("{}\t{}\t{}", found.first, found.count, action),
+            false => println!("{}-{}\t{}\t{}", found.first, found.last, found.count, action)
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Writes the game to `export --format civclicker <file>` as a save of the browser game
+ * CivClicker, as far as the two games overlap.
+ */
+fn export(state: &State, args: &[String]) -> Result<(), Error> {
+    match args {
+        [option, format, path] if option == "--format" && format == "civclicker" => {
+            let save = to_string_pretty(&civclicker_save(state)).map_err(|error| Error::Output(error.to_string()))?;
+            std::fs::write(path, save).map_err(Error::Io)
+        },
+        [option, ..] if option != "--format" => Err(Error::UnknownArgument(option.clone())),
+        [_, format, ..] if format != "civclicker" => Err(Error::UnknownFormat(format.clone())),
+        [_, _, _, extra, ..] => Err(Error::UnknownArgument(extra.clone())),
+        [_, _] => Err(Error::MissingArgument("file".to_string())),
+        _ => Err(Error::MissingArgument("--format".to_string()))
+    }
+}
+
+/**
+ * Writes the state to `debug dump <file>` as TOML, to be edited by hand.
+ */
+fn debug_dump(state: &State, args: &[String]) -> Result<(), Error> {
+    let path = args.first().ok_or_else(|| Error::MissingArgument("file".to_string()))?;
+    let dump = toml::to_string_pretty(&state.editable()).map_err(|error| Error::SaveEncode(error.to_string()))?;
+
+    std::fs::write(path, dump).map_err(Error::Io)
+}
+
+/**
+ * Reads the state back from `debug load <file>`. It is marked as tampered, so it can
+ * still be played but will never pass a check.
+ */
+fn debug_load(args: &[String]) -> Result<State, Error> {
+    let path = match args {
+        [subcommand, path] if subcommand == "load" => path,
+        [subcommand, ..] if subcommand != "load" => return Err(Error::UnknownArgument(subcommand.clone())),
+        _ => return Err(Error::MissingArgument("file".to_string()))
+    };
+    let dump = std::fs::read_to_string(path).map_err(Error::Io)?;
+    let editable = toml::from_str(&dump).map_err(|error| Error::SaveDecode(error.to_string()))?;
+
+    State::from_editable(editable).map_err(Error::InvalidAmount)
+}
+
+/**
+ * Rebuilds a full save from the log document at `import-log <file>`.
+ */
+fn import_log(args: &[String]) -> Result<State, Error> {
+    let path = args.first().ok_or_else(|| Error::MissingArgument("file".to_string()))?;
+    let document = std::fs::read(path).map_err(Error::Io)?;
+    let document: LogDocument = serde_json::from_slice(&document).map_err(Error::InputParseError)?;
+
+    State::from_log_document(document).map_err(Error::Iteration)
+}
+
+/**
+ * Checks the state. With `--repair`, a state that fails the check is backed up and
+ * rebuilt from the part of its log that is still valid. The backup holds its whole log,
+ * even with a log file.
+ */
+fn check(save_file: &SaveFile, state: &State, args: &[String]) -> Result<Option<State>, Error> {
+    let repair = match args.first().map(String::as_str) {
+        Some("--repair") => true,
+        Some(arg) => return Err(Error::UnknownArgument(arg.to_string())),
+        None => false
+    };
+
+    match state.check() {
+        Ok(()) => Ok(None),
+        Err(error) if repair => {
+            eprintln!("{}", error);
+
+            let backup = format!("cliciv-{}.backup", state.hash());
+            let file = std::fs::File::create(&backup).map_err(Error::Io)?;
+            SaveFile { log_file: None, ..save_file.clone() }.write(state, file)?;
+            eprintln!("Backed up to {}", backup);
+
+            let (repaired_state, dropped_actions) = state.repair().map_err(Error::Iteration)?;
+            eprintln!("Dropped {} actions", dropped_actions);
+
+            Ok(Some(repaired_state))
+        },
+        Err(error) => Err(Error::Check(error))
+    }
+}
+
+/**
+ * Checks the state with `--json`, reporting the outcome instead of writing the save.
+ */
+fn check_report(state: &State, args: &[String]) -> Result<CheckReport, Error> {
+    if let Some(arg) = args.iter().find(|arg| *arg != "--json") {
+        return Err(Error::UnknownArgument(arg.clone()));
+    }
+
+    Ok(CheckReport::new(state.check()))
+}
+
+/**
+ * Describes the state as it was at `--iter <iteration>`, as JSON with `--json`. The
+ * save is left untouched.
+ */
+fn at(state: &State, args: &[String]) -> Result<String, Error> {
+    let mut iteration = None;
+    let mut json = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--iter" => {
+                let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+                iteration = Some(value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?);
+            },
+            "--json" => json = true,
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    let iteration = iteration.ok_or_else(|| Error::MissingArgument("--iter".to_string()))?;
+    let past_state = state.at(iteration).map_err(Error::Rollback)?;
+
+    match json {
+        true => Ok(to_string_pretty(&past_state).unwrap()),
+        false => Ok(past_state.to_string())
+    }
+}
+
+/**
+ * Writes a new save, `<name>.save`, with the game as it was at `--iter <iteration>` so
+ * another strategy can be tried from there. The original save is left untouched.
+ * Ironman games can't be forked, as that would be a way back. The fork keeps its own
+ * log, as a log file or database can only follow one game.
+ */
+fn fork(save_file: &SaveFile, state: &State, args: &[String]) -> Result<String, Error> {
+    let mut iteration = None;
+    let mut name = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+
+        match arg.as_str() {
+            "--iter" => iteration = Some(value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?),
+            "--name" => name = Some(value.clone()),
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    let iteration = iteration.ok_or_else(|| Error::MissingArgument("--iter".to_string()))?;
+    let name = name.ok_or_else(|| Error::MissingArgument("--name".to_string()))?;
+    let forked_state = state.rollback(iteration).map_err(Error::Rollback)?;
+
+    let path = format!("{}.save", name);
+    let file = OpenOptions::new().write(true).create_new(true).open(&path).map_err(Error::Io)?;
+    SaveFile { log_file: None, database: None, ..save_file.clone() }.write(&commit(forked_state), file)?;
+
+    Ok(path)
+}
+
+/**
+ * Narrates the game iteration by iteration, from `--from` to `--to`. With `--speed`,
+ * that many iterations are shown per second.
+ */
+fn replay(state: &State, args: &[String]) -> Result<(), Error> {
+    let mut from = 0;
+    let mut to = usize::MAX;
+    let mut delay = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+        let number: u64 = value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?;
+
+        match arg.as_str() {
+            "--from" => from = number as usize,
+            "--to" => to = number as usize,
+            "--speed" if number > 0 => delay = Some(Duration::from_millis(1000 / number)),
+            "--speed" => return Err(Error::InvalidAmount(value.clone())),
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    state.replay(to, |step: ReplayStep| {
+        if step.iteration < from {
+            return;
+        }
+
+        let deltas: Vec<String> = step.resource_deltas.iter()
+            .map(|(resource, delta)| match *delta > Quantity::ZERO {
+                true => format!("{} +{}", resource.name(), delta),
+                false => format!("{} {}", resource.name(), delta)
+            })
+            .chain(match step.population_delta {
+                0 => None,
+                delta if delta > 0 => Some(format!("population +{}", delta)),
+                delta => Some(format!("population {}", delta))
+            })
+            .collect();
+
+        println!("#{}\t{:?}\t{}", step.iteration, step.action, deltas.join(", "));
+        for event in step.events {
+            println!("\t{:?}", event);
+        }
+
+        if let Some(delay) = delay {
+            std::thread::sleep(delay);
+        }
+    }).map_err(Error::Iteration)
+}
+
+/**
+ * Lists the hash each action led to along with the action, as recomputed by replaying
+ * the game. With `--last`, only that many of the latest actions are listed.
+ */
+fn chain(state: &State, args: &[String]) -> Result<(), Error> {
+    let last = match args {
+        [] => usize::MAX,
+        [flag, value] if flag == "--last" => value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+        [flag] if flag == "--last" => return Err(Error::MissingArgument(flag.clone())),
+        [arg, ..] => return Err(Error::UnknownArgument(arg.clone()))
+    };
+    let mut links = VecDeque::new();
+
+    state.replay(usize::MAX, |step: ReplayStep| {
+        if links.len() == last {
+            links.pop_front();
+        }
+
+        if last > 0 {
+            links.push_back(format!("#{}\t{}\t{:?}", step.iteration, step.hash, step.action));
+        }
+    }).map_err(Error::Iteration)?;
+
+    for link in links {
+        println!("{}", link);
+    }
+
+    Ok(())
+}
+
+/**
+ * Shares the save through the remote given by `--remote <url>`. `sync push` uploads
+ * the save and `sync pull` takes the remote one in its place, which can start a game
+ * on a machine without a save. Saves are only replaced by ones that extend them, so
+ * progress made elsewhere is never lost: pushing over a save that is ahead, or two
+ * saves that took different actions, is refused, as is pushing over a remote save
+ * that changed since it was fetched. The save is written back either way.
+ */
+fn sync(save_file: &mut SaveFile, args: &[String]) -> Result<State, Error> {
+    let (push, url) = match args {
+        [command, flag, url] if flag == "--remote" && (command == "push" || command == "pull") => (command == "push", url),
+        [_, flag] if flag == "--remote" => return Err(Error::MissingArgument(flag.clone())),
+        [command, ..] if command != "push" && command != "pull" => return Err(Error::UnknownArgument(command.clone())),
+        [_, arg, ..] => return Err(Error::UnknownArgument(arg.clone())),
+        _ => return Err(Error::MissingArgument("--remote".to_string()))
+    };
+    let remote = Remote { url: url.clone() };
+    // The remote save always holds the whole game, whatever keeps it locally.
+    let mut remote_file = SaveFile { log_file: None, database: None, ..save_file.clone() };
+
+    let (remote_bytes, version) = remote.fetch()?;
+    let remote_state = match remote_bytes {
+        Some(bytes) => {
+            let state = remote_file.read(&bytes[..])?;
+            state.check().map_err(Error::Check)?;
+            Some(state)
+        },
+        None => None
+    };
+
+    let mut bytes = vec!{};
+    if save_file.database.is_none() {
+        std::io::stdin().read_to_end(&mut bytes).map_err(Error::Io)?;
+    }
+    let local_state = match !push && bytes.is_empty() && save_file.database.is_none() {
+        true => None,
+        false => Some(save_file.read(&bytes[..])?)
+    };
+
+    let conflict = |local: &State, remote: &State| Error::Sync(match local.game_id() == remote.game_id() {
+        true => SyncError::Diverged { local: local.commitment().iterations, remote: remote.commitment().iterations },
+        false => SyncError::OtherGame
+    });
+
+    if let (true, Some(local_state)) = (push, &local_state) {
+        local_state.check().map_err(Error::Check)?;
+
+        match remote_state {
+            Some(remote_state) if remote_state.hash() == local_state.hash() => eprintln!("Remote save is up to date"),
+            Some(remote_state) if remote_state.extends(local_state) => return Err(Error::Sync(SyncError::RemoteAhead {
+                local: local_state.commitment().iterations,
+                remote: remote_state.commitment().iterations
+            })),
+            Some(remote_state) if !local_state.extends(&remote_state) => return Err(conflict(local_state, &remote_state)),
+            _ => {
+                let mut bytes = vec!{};
+                remote_file.write(local_state, &mut bytes)?;
+                remote.store(&bytes, &version)?;
+            }
+        }
+
+        return Ok(local_state.clone());
+    }
+
+    let remote_state = remote_state.ok_or(Error::Sync(SyncError::MissingRemote))?;
+
+    match local_state {
+        Some(local_state) if !remote_state.extends(&local_state) => match local_state.extends(&remote_state) {
+            true => {
+                eprintln!("Local save is ahead of the remote one");
+                Ok(local_state)
+            },
+            false => Err(conflict(&local_state, &remote_state))
+        },
+        _ => Ok(remote_state)
+    }
+}
+
+/**
+ * Plays `--turns` random actions on a throwaway game created from `--seed`, or a random
+ * seed, and prints a summary. Tells whether the game kept its invariants and passed
+ * its checks.
+ */
+fn simulation(args: &[String]) -> Result<bool, Error> {
+    let mut turns = 10000;
+    let mut seed: i128 = rand::random();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+
+        match arg.as_str() {
+            "--turns" => turns = value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+            "--seed" => seed = value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    let simulation = simulate(seed, turns);
+
+    println!("Simulated {} turns with seed {}", turns, seed);
+    println!("{}", simulation.state);
+    println!("Actions taken:");
+
+    for (action, count) in simulation.actions.iter() {
+        println!("\t{}\t{}", count, action);
+    }
+
+    println!("Actions refused:\t{}", simulation.rejected);
+
+    for (iteration, violation) in simulation.violations.iter() {
+        println!("Invariant broken at iteration {}: {}", iteration, violation);
+    }
+
+    match &simulation.check {
+        Ok(()) => println!("Check passed"),
+        Err(error) => println!("Check failed: {}", error)
+    }
+
+    Ok(simulation.passed())
+}
+
+/**
+ * Plays `autoplay --turns <turns>` turns, 100 by default, with the `--strategy`
+ * given, `balanced` by default, and tells what was played on the standard error.
+ */
+fn play(previous_state: State, args: &[String]) -> Result<State, Error> {
+    let mut turns = 100;
+    let mut strategy = Strategy::Balanced;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+
+        match arg.as_str() {
+            "--turns" => turns = value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+            "--strategy" => strategy = value.parse().map_err(Error::UnknownArgument)?,
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    if !previous_state.tampered() {
+        check_cached(&previous_state).map_err(Error::Check)?;
+    }
+
+    let autoplay = autoplay(previous_state, turns, &strategy).map_err(Error::Iteration)?;
+
+    eprintln!("Played {} turns with the {} strategy:", turns, strategy.name());
+    for (action, count) in autoplay.actions.iter() {
+        eprintln!("\t{}\t{}", count, action);
+    }
+    eprintln!("{}", prompt(&autoplay.state, &[])?);
+
+    Ok(autoplay.state)
+}
+
+/**
+ * Prints the actions the advisor recommends, best first, each with why.
+ */
+fn advice(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    if let Some(arg) = args.first() {
+        return Err(Error::UnknownArgument(arg.clone()));
+    }
+
+    let advice = advise(state);
+
+    if let Some(output) = output {
+        return output.print_list("advice", &advice);
+    }
+
+    for (rank, advice) in advice.iter().enumerate() {
+        println!("{}. {}\t{}", rank + 1, to_string(&advice.action).unwrap(), advice.reason);
+    }
+
+    Ok(())
+}
+
+/**
+ * Prints where the game is headed after `forecast --turns <turns>` idle iterations,
+ * 100 by default, without saving anything.
+ */
+fn projection(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    let turns = match args {
+        [] => 100,
+        [flag, value] if flag == "--turns" => value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+        [flag] if flag == "--turns" => return Err(Error::MissingArgument(flag.clone())),
+        [arg, ..] => return Err(Error::UnknownArgument(arg.clone()))
+    };
+    let forecast = forecast(state, turns).map_err(Error::Iteration)?;
+
+    if let Some(output) = output {
+        return output.print(&forecast);
+    }
+
+    println!("In {} turns, at iteration {}:", forecast.turns, forecast.iteration);
+
+    for resource in Resource::all().iter().map(Resource::name) {
+        match forecast.capped.get(resource) {
+            Some(turn) => println!("\t{}\t{}\t(full in {} turns)", resource, forecast.resources[resource], turn),
+            None => println!("\t{}\t{}", resource, forecast.resources[resource])
+        }
+    }
+
+    println!("\tpeople\t{}", forecast.population);
+
+    match forecast.food_runs_out {
+        Some(turn) => println!("Food runs out in {} turns", turn),
+        None => println!("Food lasts")
+    }
+
+    Ok(())
+}
+
+/**
+ * Building in the construction queue as listed with `--output`.
+ */
+#[derive(Serialize)]
+struct Queued {
+    position: usize,
+    building: Buildings,
+    work_left: u64,
+    work: u64,
+    // Iterations until it is finished, at the current work rate.
+    iterations: u64
+}
+
+/**
+ * Prints the buildings under construction in the order they are worked on, and when
+ * each will be finished if the builders stay on them.
+ */
+fn construction_queue(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    if let Some(arg) = args.first() {
+        return Err(Error::UnknownArgument(arg.clone()));
+    }
+
+    let rate = Land::construction_rate(state.citizens().builders);
+    let mut work_ahead = 0;
+    let queue: Vec<Queued> = state.land().construction.iter().enumerate().map(|(index, (building, work_left))| {
+        work_ahead += work_left;
+
+        Queued {
+            position: index + 1,
+            building: building.clone(),
+            work_left: *work_left,
+            work: building.construction_work(),
+            iterations: work_ahead.div_ceil(rate)
+        }
+    }).collect();
+
+    if let Some(output) = output {
+        return output.print_list("queue", &queue);
+    }
+
+    if queue.is_empty() {
+        println!("Nothing is under construction");
+    }
+
+    for queued in queue.iter() {
+        println!("{}. {:?}\t{} of {} work left, done in {} iterations",
+            queued.position, queued.building, queued.work_left, queued.work, queued.iterations);
+    }
+
+    Ok(())
+}
+
+/**
+ * Builds the `queue cancel <position>` and `queue reorder <from> <to>` actions on the
+ * construction queue, counting positions from 1 as listed.
+ */
+fn queue(args: &[String]) -> Result<Action, Error> {
+    let position = |index: usize, name: &str| -> Result<usize, Error> {
+        let arg = args.get(index).ok_or_else(|| Error::MissingArgument(name.to_string()))?;
+
+        arg.parse::<usize>().ok().filter(|position| *position > 0).map(|position| position - 1)
+            .ok_or_else(|| Error::InvalidAmount(arg.clone()))
+    };
+
+    match args.first().map(String::as_str) {
+        Some("cancel") => Ok(Action::CancelConstruction(position(1, "position")?)),
+        Some("reorder") => Ok(Action::ReorderConstruction(position(1, "from")?, position(2, "to")?)),
+        Some(arg) => Err(Error::UnknownArgument(arg.to_string())),
+        None => Err(Error::MissingArgument("command".to_string()))
+    }
+}
+
+/**
+ * Statistics of a game as listed with `--output`.
+ */
+#[derive(Serialize)]
+struct Stats {
+    iterations: usize,
+    actions: u64,
+    sessions: u64,
+    play_time: u64,
+    iterations_per_hour: Option<u64>
+}
+
+/**
+ * Prints how far the game went, and how much real-world time it took to get there,
+ * or with `--luck` how lucky it was finding secondary resources.
+ */
+fn statistics(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    match args.first() {
+        Some(flag) if flag == "--luck" && args.len() == 1 => return luck_report(state, output),
+        Some(arg) => return Err(Error::UnknownArgument(arg.clone())),
+        None => ()
+    }
+
+    let play_time = state.play_time();
+    let stats = Stats {
+        iterations: state.iterations(),
+        actions: state.actions(),
+        sessions: play_time.sessions,
+        play_time: play_time.seconds,
+        iterations_per_hour: (play_time.seconds > 0).then(|| state.iterations() as u64 * 3600 / play_time.seconds)
+    };
+
+    if let Some(output) = output {
+        return output.print(&stats);
+    }
+
+    println!("Iterations\t{}", stats.iterations);
+    println!("Actions\t{}", stats.actions);
+    println!("Sessions\t{}", stats.sessions);
+    println!("Play time\t{}", elapsed(stats.play_time));
+    if let Some(iterations_per_hour) = stats.iterations_per_hour {
+        println!("Iterations per hour\t{}", iterations_per_hour);
+    }
+
+    Ok(())
+}
+
+/**
+ * Prints the secondary resources found while gathering next to the amounts expected
+ * from their chances, replaying the log to count every roll.
+ */
+fn luck_report(state: &State, output: Option<OutputFormat>) -> Result<(), Error> {
+    let luck = luck(state).map_err(Error::Iteration)?;
+
+    if let Some(output) = output {
+        return output.print_list("luck", &luck);
+    }
+
+    for luck in luck.iter() {
+        let sign = if luck.surplus > Quantity::ZERO { "+" } else { "" };
+        println!("{}	{} found	{} expected	in {} draws	({}{})", luck.resource, luck.found, luck.expected, luck.draws, sign, luck.surplus);
+    }
+
+    Ok(())
+}
+
+/**
+ * Prints the real-world sessions the game was played in, split wherever it was left
+ * alone for longer than `--gap` minutes, thirty by default.
+ */
+fn timeline(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    let gap = match args {
+        [] => SESSION_GAP,
+        [flag, value] if flag == "--gap" => value.parse::<u64>().map_err(|_| Error::InvalidAmount(value.clone()))? * 60,
+        [flag] if flag == "--gap" => return Err(Error::MissingArgument(flag.clone())),
+        [arg, ..] => return Err(Error::UnknownArgument(arg.clone()))
+    };
+    let sessions = sessions(state.log_times(), gap);
+
+    if let Some(output) = output {
+        return output.print_list("sessions", &sessions);
+    }
+
+    for session in sessions.iter() {
+        println!("{} to {}\t{} iterations, {} to {}, in {} actions",
+            utc(session.start), utc(session.end), session.iterations(), session.first_iteration, session.last_iteration, session.actions);
+    }
+
+    Ok(())
+}
+
+/**
+ * Draws the land of the game as rows of parcels, `--width` of them per row, along
+ * with a legend of the glyphs.
+ */
+fn land_map(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    let width = match args {
+        [] => MAP_WIDTH,
+        [flag, value] if flag == "--width" => value.parse::<usize>().ok().filter(|width| *width > 0)
+            .ok_or_else(|| Error::InvalidAmount(value.clone()))?,
+        [flag] if flag == "--width" => return Err(Error::MissingArgument(flag.clone())),
+        [arg, ..] => return Err(Error::UnknownArgument(arg.clone()))
+    };
+    let rows = map(state.land(), width);
+
+    if let Some(output) = output {
+        return output.print_list("map", &rows);
+    }
+
+    for row in rows.iter() {
+        println!("{}", row);
+    }
+    println!();
+    for entry in legend(state.land()) {
+        println!("{}", entry);
+    }
+
+    Ok(())
+}
+
+/**
+ * Prints what every building and job brings and costs, with how many iterations of
+ * the current income pay for it.
+ */
+fn paybacks(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    if let Some(arg) = args.first() {
+        return Err(Error::UnknownArgument(arg.clone()));
+    }
+
+    let paybacks = roi(state);
+
+    if let Some(output) = output {
+        return output.print_list("roi", &paybacks);
+    }
+
+    for payback in paybacks.iter() {
+        let costs = payback.costs.iter().map(|(resource, cost)| format!("{} {}", cost, resource)).collect::<Vec<_>>().join(", ");
+        let time = match payback.iterations {
+            Some(iterations) => format!("≈ {} iterations of current income", iterations),
+            None => "(not earned by current income)".to_string()
+        };
+
+        println!("{}: {}, costs {} {}", payback.name, payback.benefits.join(", "), costs, time);
+    }
+
+    Ok(())
+}
+
+/**
+ * Searches job allocations for the target given with `--target`, and prints the plan
+ * reaching the best one found.
+ */
+fn optimization(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    let mut target = None;
+    let mut builds = 0;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+
+        match arg.as_str() {
+            "--target" => target = Some(value.parse::<Target>().map_err(Error::InvalidTarget)?),
+            "--builds" => builds = value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    let target = target.ok_or_else(|| Error::MissingArgument("--target".to_string()))?;
+    let optimization = optimize(state, &target, builds);
+
+    if let Some(output) = output {
+        return output.print(&optimization);
+    }
+
+    println!("To {}: {} against {} idling", optimization.target, optimization.value, optimization.baseline);
+
+    for (action, count) in optimization.plan.iter() {
+        println!("\t{}\t{}", count, to_string(action).unwrap());
+    }
+
+    Ok(())
+}
+
+/**
+ * Event as listed with `--output`.
+ */
+#[derive(Serialize)]
+struct EventRecord<'a> {
+    iteration: usize,
+    event: &'a Event
+}
+
+/**
+ * Lists the latest events kept in the state, along with the iteration each happened
+ * on. With `--since`, only events from that iteration on are listed.
+ */
+fn events(state: &State, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    let since = match args {
+        [] => 0,
+        [flag, value] if flag == "--since" => value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+        [flag] if flag == "--since" => return Err(Error::MissingArgument(flag.clone())),
+        [arg, ..] => return Err(Error::UnknownArgument(arg.clone()))
+    };
+    let events = state.recent_events().iter().filter(|(iteration, _)| *iteration >= since);
+
+    if let Some(output) = output {
+        let events: Vec<EventRecord> = events.map(|(iteration, event)| EventRecord { iteration: *iteration, event }).collect();
+        return output.print_list("events", &events);
+    }
+
+    for (iteration, event) in events {
+        println!("#{}\t{:?}", iteration, event);
+    }
+
+    Ok(())
+}
+
+/**
+ * Repaints a view of the save in `top <file>`, or in the database with `--backend
+ * sqlite`, every second, while a server or another session plays it. Saves that can't
+ * be read, like one being written, are tried again on the next refresh.
+ */
+fn top(save_file: &mut SaveFile, args: &[String]) -> Result<(), Error> {
+    let path = match (args, &save_file.database) {
+        ([], Some(_)) => None,
+        ([path], None) => Some(PathBuf::from(path)),
+        ([], None) => return Err(Error::MissingArgument("file".to_string())),
+        ([.., arg], _) => return Err(Error::UnknownArgument(arg.clone()))
+    };
+    let mut previous: Option<State> = None;
+
+    loop {
+        let state = match &path {
+            Some(path) => std::fs::File::open(path).map_err(Error::Io)
+                .and_then(|file| save_file.read_without_log(file)),
+            None => save_file.read_without_log(std::io::empty())
+        };
+
+        match state {
+            Ok(state) => {
+                print!("\x1b[H\x1b[2J{}", top_view(&state, previous.as_ref()));
+                previous = Some(state);
+            },
+            Err(error) if previous.is_none() => return Err(error),
+            Err(_) => ()
+        }
+
+        std::io::stdout().flush().map_err(Error::Io)?;
+        std::thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/**
+ * Compact view of the game for `top`: resources, with arrows telling how they moved
+ * since the previous refresh, production rates, population and the latest events.
+ */
+fn top_view(state: &State, previous: Option<&State>) -> String {
+    let morale = state.citizens().morale(state.land());
+    let resources = &state.resources;
+    let advanced = previous.map_or(0, |previous| state.iterations().saturating_sub(previous.iterations()));
+    let mut view = format!("iteration {} (+{})\n", state.iterations(), advanced);
+
+    for resource in Resource::all() {
+        let amount = resources.amount(&resource);
+        let trend = match previous.map(|previous| previous.resources.amount(&resource)) {
+            Some(before) if amount > before => "↑",
+            Some(before) if amount < before => "↓",
+            _ => "→"
+        };
+        let rate = match resource {
+            Resource::Primary(PrimaryResource::Food) => Some(resources.food_net_rate(morale)),
+            Resource::Primary(PrimaryResource::Wood) => Some(resources.wood_prod_rate * resources.wood_prod_rate_multiplier * morale),
+            Resource::Primary(PrimaryResource::Stone) => Some(resources.stone_prod_rate * resources.stone_prod_rate_multiplier * morale),
+            _ => None
+        };
+
+        view.push_str(&format!("{:<8}{:>10} {}", resource.name(), amount.to_string(), trend));
+        if let Some(rate) = rate {
+            view.push_str(&format!("  {}/i", rate));
+        }
+        view.push('\n');
+    }
+
+    let (citizens, max_population) = state.population();
+    view.push_str(&format!("{:<8}{:>10}  morale {}\n", "people", format!("{}/{}", citizens, max_population), morale));
+
+    for (iteration, event) in state.recent_events().iter().rev().take(5) {
+        view.push_str(&format!("#{}\t{:?}\n", iteration, event));
+    }
+
+    view
+}
+
+/**
+ * Prints the resources at the end of every iteration from `--from` to `--to`, as one
+ * JSON line each. With `--backend sqlite` they are read from the database instead of
+ * replaying the game.
+ */
+fn history(save_file: &mut SaveFile, args: &[String], output: Option<OutputFormat>) -> Result<(), Error> {
+    let mut from = 0;
+    let mut to = usize::MAX;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+        let iteration = value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?;
+
+        match arg.as_str() {
+            "--from" => from = iteration,
+            "--to" => to = iteration,
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    let history = match &save_file.database {
+        Some(database) => database.history(from, to)?,
+        None => load(save_file).history(from).map_err(Error::Iteration)?
+            .into_iter()
+            .filter(|entry| entry.iteration <= to)
+            .collect()
+    };
+
+    if let Some(output) = output {
+        return output.print_list("history", &history);
+    }
+
+    for entry in history {
+        println!("{}", to_string(&entry).unwrap());
+    }
+
+    Ok(())
+}
+
+/**
+ * Builds the inclusion proof of the action at the position given by `prove <index>`.
+ * Without an index, `prove` writes a proof of the whole run instead.
+ */
+fn prove(state: &State, args: &[String]) -> Result<ActionProof, Error> {
+    let index = args.first().ok_or_else(|| Error::MissingArgument("index".to_string()))?;
+    let index = index.parse().map_err(|_| Error::InvalidAmount(index.clone()))?;
+
+    state.check().map_err(Error::Check)?;
+    state.prove(index).ok_or(Error::InvalidAmount(index.to_string()))
+}
+
+/**
+ * Checks the proof read from the standard input against `--root <log root>`.
+ */
+fn verify_proof(args: &[String]) -> Result<ActionProof, Error> {
+    let root = match args.first().map(String::as_str) {
+        Some("--root") => args.get(1).ok_or_else(|| Error::MissingArgument("--root".to_string()))?,
+        Some(arg) => return Err(Error::UnknownArgument(arg.to_string())),
+        None => return Err(Error::MissingArgument("--root".to_string()))
+    };
+    let root = Digest::from_hex(root).ok_or_else(|| Error::InvalidDigest(root.clone()))?;
+    let proof: ActionProof = from_reader(std::io::stdin()).map_err(Error::InputParseError)?;
+
+    if proof.verify(&root) {
+        Ok(proof)
+    } else {
+        Err(Error::Check(CheckError::InvalidProof))
+    }
+}
+
+/**
+ * Checks the run proof at `verify-proof <file>`, written by `prove` with no index.
+ */
+fn verify_run_proof(args: &[String]) -> Result<RunProof, Error> {
+    let path = match args {
+        [path] => path,
+        [_, arg, ..] => return Err(Error::UnknownArgument(arg.clone())),
+        [] => return Err(Error::MissingArgument("file".to_string()))
+    };
+    let proof = std::fs::read(path).map_err(Error::Io)?;
+    let proof: RunProof = serde_json::from_slice(&proof).map_err(Error::InputParseError)?;
+
+    proof.verify().map_err(Error::Check)?;
+    Ok(proof)
+}
+
+/**
+ * Result of the daily challenge played in the save, for `challenge submit`. The game
+ * is checked first, and signed with the key of the player if it isn't yet.
+ */
+fn submit_challenge(state: State, args: &[String]) -> Result<ChallengeResult, Error> {
+    match args {
+        [subcommand] if subcommand == "submit" => (),
+        [] => return Err(Error::MissingArgument("submit".to_string())),
+        [subcommand] => return Err(Error::UnknownArgument(subcommand.clone())),
+        [_, arg, ..] => return Err(Error::UnknownArgument(arg.clone()))
+    }
+
+    state.check().map_err(Error::Check)?;
+
+    let state = match signing_key()? {
+        Some(key) => state.sign(&key),
+        None => state
+    };
+
+    ChallengeResult::new(&state).map_err(Error::Check)
+}
+
+/**
+ * Verifies the challenge result in `challenge verify <file>`, without any save.
+ */
+fn verify_challenge(args: &[String]) -> Result<ChallengeResult, Error> {
+    let path = match args {
+        [path] => path,
+        [_, arg, ..] => return Err(Error::UnknownArgument(arg.clone())),
+        [] => return Err(Error::MissingArgument("file".to_string()))
+    };
+    let result = std::fs::read(path).map_err(Error::Io)?;
+    let result: ChallengeResult = serde_json::from_slice(&result).map_err(Error::InputParseError)?;
+
+    result.verify().map_err(Error::Check)?;
+    Ok(result)
+}
+
+/**
+ * Restores the state at the iteration given by `rollback --to <iteration>`.
+ */
+fn rollback(state: &State, args: &[String]) -> Result<State, Error> {
+    match args.first().map(String::as_str) {
+        Some("--to") => {
+            let iteration = args.get(1).ok_or_else(|| Error::MissingArgument("--to".to_string()))?;
+            let iteration = iteration.parse().map_err(|_| Error::InvalidAmount(iteration.clone()))?;
+
+            state.rollback(iteration).map_err(Error::Rollback)
+        },
+        Some(arg) => Err(Error::UnknownArgument(arg.to_string())),
+        None => Err(Error::MissingArgument("--to".to_string()))
+    }
+}
+
+/**
+ * Barters with another player through signed files:
+ *
+ * - `trade offer <file> --give <resource> <amount> --want <resource> <amount>` sets
+ *   aside what is given and writes the offer.
+ * - `trade accept <offer file> <receipt file>` makes the exchange and writes the
+ *   receipt for the offering player.
+ * - `trade settle <receipt file>` receives what the offer asked for.
+ *
+ * Each step is logged and needs the signing key. Files are only written once the step
+ * was applied. Trades take the other player at their word, as their save can't be
+ * seen, so only sandbox games can trade.
+ */
+fn trade(state: State, args: &[String]) -> Result<State, Error> {
+    let key = signing_key()?.ok_or_else(|| Error::MissingKey(key_file()))?;
+    check_cached(&state).map_err(Error::Check)?;
+
+    let (action, output) = match args {
+        [subcommand, path, options @ ..] if subcommand == "offer" => {
+            let mut give = None;
+            let mut want = None;
+            let mut options = options.iter();
+
+            while let Some(option) = options.next() {
+                let resource = options.next().ok_or_else(|| Error::MissingArgument(option.clone()))?;
+                let amount = options.next().ok_or_else(|| Error::MissingArgument(option.clone()))?;
+                let side = (
+                    resource.parse().map_err(Error::UnknownResource)?,
+                    Quantity::units(amount.parse().map_err(|_| Error::InvalidAmount(amount.clone()))?)
+                );
+
+                match option.as_str() {
+                    "--give" => give = Some(side),
+                    "--want" => want = Some(side),
+                    _ => return Err(Error::UnknownArgument(option.clone()))
+                }
+            }
+
+            let give = give.ok_or_else(|| Error::MissingArgument("--give".to_string()))?;
+            let want = want.ok_or_else(|| Error::MissingArgument("--want".to_string()))?;
+            let offer = TradeOffer::new(&key, state.game_id(), state.hash(), give, want);
+
+            (Action::OfferTrade(Box::new(offer.clone())), Some((path, to_string_pretty(&offer).unwrap())))
+        },
+        [subcommand, offer_path, receipt_path] if subcommand == "accept" => {
+            let offer = std::fs::read(offer_path).map_err(Error::Io)?;
+            let offer: TradeOffer = serde_json::from_slice(&offer).map_err(Error::InputParseError)?;
+            let receipt = TradeReceipt::new(&key, offer, state.game_id());
+
+            (Action::AcceptTrade(Box::new(receipt.clone())), Some((receipt_path, to_string_pretty(&receipt).unwrap())))
+        },
+        [subcommand, path] if subcommand == "settle" => {
+            let receipt = std::fs::read(path).map_err(Error::Io)?;
+            let receipt: TradeReceipt = serde_json::from_slice(&receipt).map_err(Error::InputParseError)?;
+
+            (Action::SettleTrade(Box::new(receipt)), None)
+        },
+        [subcommand, ..] if !["offer", "accept", "settle"].contains(&subcommand.as_str()) => {
+            return Err(Error::UnknownArgument(subcommand.clone()))
+        },
+        _ => return Err(Error::MissingArgument("file".to_string()))
+    };
+
+    let state = state.apply_action(action).map_err(Error::Iteration)?;
+
+    if let Some((path, contents)) = output {
+        std::fs::write(path, contents).map_err(Error::Io)?;
+    }
+
+    Ok(state)
+}
+
+/**
+ * Builds the sandbox `grant <resource> <amount>` action.
+ */
+fn grant(args: &[String]) -> Result<Action, Error> {
+    let resource = args.first().ok_or_else(|| Error::MissingArgument("resource".to_string()))?;
+    let amount = args.get(1).ok_or_else(|| Error::MissingArgument("amount".to_string()))?;
+
+    Ok(Action::Grant(
+        resource.parse().map_err(Error::UnknownResource)?,
+        amount.parse().map_err(|_| Error::InvalidAmount(amount.clone()))?
+    ))
+}
+
+/**
+ * Builds the `barter <from> <to> <amount>` action, giving away the amount of one
+ * primary resource for a fraction of it in another.
+ */
+fn barter(args: &[String]) -> Result<Action, Error> {
+    let primary = |name: &String| match name.parse().map_err(Error::UnknownResource)? {
+        Resource::Primary(resource) => Ok(resource),
+        _ => Err(Error::UnknownResource(name.clone()))
+    };
+    let from = args.first().ok_or_else(|| Error::MissingArgument("from".to_string()))?;
+    let to = args.get(1).ok_or_else(|| Error::MissingArgument("to".to_string()))?;
+    let amount = args.get(2).ok_or_else(|| Error::MissingArgument("amount".to_string()))?;
+
+    Ok(Action::Barter {
+        from: primary(from)?,
+        to: primary(to)?,
+        amount: amount.parse().map_err(|_| Error::InvalidAmount(amount.clone()))?
+    })
+}
+
+/**
+ * Builds the `sacrifice <resource> <amount>` action, offering food, gold, corpses or gems
+ * for piety.
+ */
+fn sacrifice(args: &[String]) -> Result<Action, Error> {
+    let resource = args.first().ok_or_else(|| Error::MissingArgument("resource".to_string()))?;
+    let amount = args.get(1).ok_or_else(|| Error::MissingArgument("amount".to_string()))?;
+
+    Ok(Action::Sacrifice(
+        resource.parse().map_err(Error::UnknownResource)?,
+        amount.parse().map_err(|_| Error::InvalidAmount(amount.clone()))?
+    ))
+}
+
+/**
+ * Builds the `omens <iterations>` action, spending the piety of clerics on foreseeing
+ * the events of the next iterations.
+ */
+fn read_omens(args: &[String]) -> Result<Action, Error> {
+    let iterations = args.first().ok_or_else(|| Error::MissingArgument("iterations".to_string()))?;
+
+    Ok(Action::ReadOmens(iterations.parse().map_err(|_| Error::InvalidAmount(iterations.clone()))?))
+}
+
+/**
+ * Builds a `turn <action>...` action out of several JSON actions, all taken within a
+ * single iteration.
+ */
+fn turn(args: &[String]) -> Result<Action, Error> {
+    if args.is_empty() {
+        return Err(Error::MissingArgument("action".to_string()));
+    }
+
+    let actions = args.iter()
+        .map(|arg| from_str(arg).map_err(Error::ActionParseError))
+        .collect::<Result<Vec<Action>, Error>>()?;
+
+    Ok(Action::Turn(actions))
+}
+
+/**
+ * Action written out in words, such as `build barn`, `collect food` or `assign farmer`.
+ * Names are matched ignoring case, dashes and underscores, and a single argument is
+ * also read as an action in JSON.
+ */
+fn spoken_action(args: &[String]) -> Result<Action, Error> {
+    fn named<T: std::fmt::Debug>(all: Vec<T>, name: &str) -> Result<T, Error> {
+        let wanted = name.to_lowercase().replace(['-', '_'], "");
+        all.into_iter().find(|value| format!("{:?}", value).to_lowercase() == wanted)
+            .ok_or_else(|| Error::UnknownArgument(name.to_string()))
+    }
+
+    match args.first().map(String::as_str) {
+        Some("barter") => return barter(&args[1..]),
+        Some("sacrifice") => return sacrifice(&args[1..]),
+        _ => {}
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] => Err(Error::MissingArgument("action".to_string())),
+        ["idle"] => Ok(Action::Idle),
+        ["recruit"] => Ok(Action::RecruitCitizen),
+        ["ennoble"] => Ok(Action::Ennoble),
+        ["collect", resource] => match resource.parse().map_err(Error::UnknownResource)? {
+            Resource::Primary(resource) => Ok(Action::Collect(resource)),
+            _ => Err(Error::UnknownResource(resource.to_string()))
+        },
+        ["assign", job] => Ok(Action::AssignJob(named(Job::all(), job)?)),
+        ["discharge", job] => Ok(Action::DischargeJob(named(Job::all(), job)?)),
+        ["train", specialization] => Ok(Action::Train(named(Specialization::all(), specialization)?)),
+        ["build", building] => Ok(Action::Build(named(Buildings::all(), building)?)),
+        [action] => from_str(action).map_err(Error::ActionParseError),
+        [arg, ..] => Err(Error::UnknownArgument(arg.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::game::state::State;
+    use super::game::actions::Action;
+    use super::game::resources::*;
+    use super::game::quantity::Quantity;
+    use super::game::buildings::*;
+    use super::game::jobs::*;
+    use super::game::errors::{Error, IterationError};
+    use super::game::settings::{Settings, daily_seed};
+    use super::game::errors::RollbackError;
+    use super::game::save_file::SaveFile;
+    use super::output::OutputFormat;
+    use super::server::Saves;
+
+    #[test]
+    fn fork_writes_the_game_at_an_iteration() {
+        let name = std::env::temp_dir().join(format!("cliciv-{}-fork", std::process::id())).display().to_string();
+        let args = |name: &str| vec!{"--iter".to_string(), "4".to_string(), "--name".to_string(), name.to_string()};
+        let log = vec!{(Action::Collect(PrimaryResource::Food), 10)};
+        let state = State::new(43932030939219715774207308070970463251).apply_log(log.clone()).unwrap();
+
+        let path = super::fork(&SaveFile::default(), &state, &args(&name)).unwrap();
+        let forked = SaveFile::default().read(std::fs::File::open(&path).unwrap()).unwrap();
+        let refused = super::fork(&SaveFile::default(), &state, &args(&name));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(path, format!("{}.save", name));
+        assert_eq!(forked.iterations(), 4);
+        assert_eq!(forked.hash(), state.rollback(4).unwrap().hash());
+        assert!(forked.check().is_ok());
+        assert!(matches!(refused, Err(Error::Io(error)) if error.kind() == std::io::ErrorKind::AlreadyExists));
+
+        let ironman = State::with_settings(43932030939219715774207308070970463251, Settings { ironman: true, ..Default::default() })
+            .apply_log(log).unwrap();
+        assert!(matches!(super::fork(&SaveFile::default(), &ironman, &args(&name)), Err(Error::Rollback(RollbackError::Ironman))));
+    }
+
+    #[test]
+    fn served_saves_are_exposed_as_metrics() {
+        let dir = std::env::temp_dir().join(format!("cliciv-{}-metrics", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        SaveFile::default().write(&state, std::fs::File::create(dir.join("town.save")).unwrap()).unwrap();
+
+        let saves = Saves::new(dir.clone());
+        saves.apply("town", Action::Collect(PrimaryResource::Food)).unwrap();
+        let metrics = saves.metrics().unwrap();
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(files, 1);
+
+        assert!(metrics.contains("# TYPE cliciv_resource gauge\n"));
+        assert!(metrics.contains("cliciv_iterations{save=\"town\"} 11\n"));
+        assert!(metrics.contains("cliciv_resource_capacity{save=\"town\",resource=\"food\"} 200.00\n"));
+        assert!(metrics.contains("cliciv_rate{save=\"town\",rate=\"water_production\"} "));
+        assert!(metrics.contains("# TYPE cliciv_events_total counter\n"));
+    }
+
+    #[test]
+    fn prompt_summarizes_the_game_in_one_line() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 12), (Action::Collect(PrimaryResource::Wood), 3)}).unwrap();
+        let prompt = super::prompt(&state, &[]).unwrap();
+
+        assert_eq!(prompt, format!("🌾{} 🌲{} ⛏0 👥0/0 i15", state.resources.food.whole(), state.resources.wood.whole()));
+        assert!(!prompt.contains('\x1b'));
+        assert!(super::prompt(&state, &["--color".to_string()]).is_ok());
+        assert!(super::prompt(&state, &["--colour".to_string()]).is_err());
+    }
+
+    #[test]
+    fn top_shows_how_resources_moved() {
+        let previous = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Wood), 5)}).unwrap();
+        let state = previous.clone().apply_log(vec!{(Action::Collect(PrimaryResource::Food), 3)}).unwrap();
+        let view = super::top_view(&state, Some(&previous));
+
+        assert!(view.starts_with("iteration 8 (+3)\n"));
+        assert!(view.lines().any(|line| line.starts_with("food") && line.contains('↑')));
+        assert!(view.lines().any(|line| line.starts_with("wood") && line.contains('→')));
+        assert!(!super::top_view(&state, None).contains('↑'));
+    }
+
+    #[test]
+    fn bartering_trades_primary_resources_at_a_loss() {
+        let words = |words: &[&str]| super::spoken_action(&words.iter().map(|word| word.to_string()).collect::<Vec<_>>());
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_plan(vec!{(Action::Collect(PrimaryResource::Stone), 10)}).unwrap();
+        let barter = words(&["barter", "stone", "food", "9"]).unwrap();
+
+        assert_eq!(barter, Action::Barter { from: PrimaryResource::Stone, to: PrimaryResource::Food, amount: 9 });
+        assert_eq!(barter.words(), ("barter", Some("stone".to_string())));
+        assert!(matches!(words(&["barter", "stone", "gold", "9"]), Err(Error::UnknownResource(_))));
+
+        let bartered = state.clone().apply_action(barter).unwrap();
+        assert_eq!(bartered.resources.amount(&Resource::Primary(PrimaryResource::Stone)), Quantity::units(1));
+        assert_eq!(bartered.resources.amount(&Resource::Primary(PrimaryResource::Food)), Quantity::units(9 / BARTER_RATIO as i64));
+
+        for (from, to, amount) in [(PrimaryResource::Stone, PrimaryResource::Stone, 9), (PrimaryResource::Stone, PrimaryResource::Food, 2)] {
+            match state.clone().apply_action(Action::Barter { from, to, amount }) {
+                Err(error) => assert_eq!(error.code(), "invalid_trade"),
+                Ok(_) => panic!("bartered for nothing")
+            }
+        }
+        match state.apply_action(Action::Barter { from: PrimaryResource::Wood, to: PrimaryResource::Food, amount: 9 }) {
+            Err(error) => assert_eq!(error.exit_code(), 10),
+            Ok(_) => panic!("bartered wood that was never collected")
+        }
+    }
+
+    #[test]
+    fn construction_queue_can_be_reordered_and_cancelled() {
+        let state = State::with_settings(-141872649309347578469772012024767025949, Settings { construction: true, ..Default::default() })
+            .apply_plan(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 60),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::Build(Buildings::Tent), 1),
+            }).unwrap();
+        assert_eq!(state.land().construction, vec!{(Buildings::WoodenHut, 19), (Buildings::Tent, 4)});
+
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let reorder = super::queue(&args(&["reorder", "2", "1"])).unwrap();
+        assert_eq!(reorder, Action::ReorderConstruction(1, 0));
+        assert!(matches!(super::queue(&args(&["cancel", "0"])), Err(Error::InvalidAmount(_))));
+
+        let reordered = state.clone().apply_action(reorder).unwrap();
+        assert_eq!(reordered.iterations(), state.iterations() + 1);
+        assert_eq!(reordered.land().construction, vec!{(Buildings::Tent, 3), (Buildings::WoodenHut, 19)});
+
+        let wood = |state: &State| state.resources.amount(&Resource::Primary(PrimaryResource::Wood));
+        let cancelled = reordered.clone().apply_action(Action::CancelConstruction(1)).unwrap();
+        assert_eq!(cancelled.land().construction, vec!{(Buildings::Tent, 2)});
+        assert_eq!(cancelled.land().free_land(), cancelled.land().total_land - 1);
+        assert_eq!(wood(&cancelled), wood(&reordered) + Quantity::hundredths(2000 * 19 / 21));
+
+        match cancelled.apply_action(Action::CancelConstruction(1)) {
+            Err(error) => assert_eq!(error.code(), "not_under_construction"),
+            Ok(_) => panic!("cancelled a building never started")
+        }
+    }
+
+    #[test]
+    fn spoken_actions_are_validated_like_any_other() {
+        let words = |words: &[&str]| super::spoken_action(&words.iter().map(|word| word.to_string()).collect::<Vec<_>>());
+        let state = State::new(43932030939219715774207308070970463251);
+
+        assert_eq!(words(&["build", "wooden-hut"]).unwrap(), Action::Build(Buildings::WoodenHut));
+        assert_eq!(words(&["assign", "Farmer"]).unwrap(), Action::AssignJob(Job::Farmer));
+        assert_eq!(words(&["\"Idle\""]).unwrap(), Action::Idle);
+        assert!(matches!(words(&["build", "castle"]), Err(Error::UnknownArgument(_))));
+        assert!(matches!(words(&["collect", "gold"]), Err(Error::UnknownResource(_))));
+
+        match state.clone().apply_action(words(&["build", "barn"]).unwrap()) {
+            Err(error) => assert_eq!(error.exit_code(), 10),
+            Ok(_) => panic!("built a barn out of nothing")
+        }
+        assert!(state.apply_action(words(&["collect", "food"]).unwrap()).is_ok());
+    }
+
+    #[test]
+    fn play_time_adds_up_within_sessions() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .stamp(1_000_000)
+            .stamp(1_000_300)
+            .stamp(1_000_900)
+            .stamp(1_100_000)
+            .stamp(1_100_060);
+
+        assert_eq!(state.play_time().seconds, 960);
+        assert_eq!(state.play_time().sessions, 2);
+        assert_eq!(state.clone().stamp(1_100_000).play_time().sessions, 3);
+        assert_eq!(super::elapsed(state.play_time().seconds), "16 minutes");
+    }
+
+    #[test]
+    fn daily_challenges_share_a_seed_and_a_turn_budget() {
+        assert_eq!(daily_seed("2026-10-16"), daily_seed("2026-10-16"));
+        assert_ne!(daily_seed("2026-10-16"), daily_seed("2026-10-17"));
+
+        let state = State::with_settings(daily_seed("2026-10-16"), Settings::daily("2026-10-16"))
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10), (Action::Idle, 490)}).unwrap();
+
+        assert!(matches!(state.clone().apply_action(Action::Idle), Err(IterationError::TurnBudgetSpent(500))));
+        assert!(matches!(state.rollback(1), Err(RollbackError::Ironman)));
+        assert!(matches!(State::with_settings(daily_seed("2026-10-16"), Settings::daily("2026-10-16"))
+            .apply_action(Action::Grant(Resource::Primary(PrimaryResource::Food), 1)), Err(IterationError::SandboxOnly)));
+        assert_ne!(Settings::daily("2026-10-16").hash(), Settings { ironman: true, ..Default::default() }.hash());
+        assert!(matches!(super::create(&["--daily".to_string(), "--sandbox".to_string()]), Err(Error::ConflictingArguments(..))));
+    }
+
+    #[test]
+    fn reports_are_printed_in_every_output_format() {
+        let report = crate::CheckReport::new(Ok(()));
+
+        for name in ["json", "yaml", "toml", "msgpack"] {
+            let output: OutputFormat = name.parse().unwrap();
+            let decoded: serde_json::Value = match output {
+                OutputFormat::Json => serde_json::from_slice(&output.encode(&report).unwrap()).unwrap(),
+                OutputFormat::Yaml => serde_yaml::from_slice(&output.encode(&report).unwrap()).unwrap(),
+                OutputFormat::Toml => toml::from_str(&String::from_utf8(output.encode(&report).unwrap()).unwrap()).unwrap(),
+                OutputFormat::MessagePack => rmp_serde::from_slice(&output.encode(&report).unwrap()).unwrap(),
+                OutputFormat::Porcelain => continue
+            };
+
+            assert_eq!(decoded["valid"], serde_json::Value::Bool(true));
+        }
+
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn porcelain_output_is_versioned_key_value_lines() {
+        let state = State::rand();
+        let porcelain = state.porcelain();
+        let mut lines = porcelain.lines();
+
+        assert_eq!(lines.next(), Some("porcelain=1"));
+
+        let fields: Vec<(&str, &str)> = lines.map(|line| line.split_once('=').unwrap()).collect();
+        let mut keys: Vec<&str> = fields.iter().map(|field| field.0).collect();
+        keys.sort_unstable();
+
+        assert_eq!(keys, fields.iter().map(|field| field.0).collect::<Vec<_>>());
+        assert!(fields.contains(&("hash", state.hash().to_string().as_str())));
+        assert!(fields.contains(&("resources.water", "100.00")));
+
+        let report = OutputFormat::Porcelain.encode(&crate::CheckReport::new(Ok(()))).unwrap();
+
+        assert!(String::from_utf8(report).unwrap().contains("\nvalid=true\n"));
+    }
+}
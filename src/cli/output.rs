@@ -0,0 +1,68 @@
+use crate::game::errors::Error;
+use crate::game::porcelain::porcelain;
+
+use std::io::Write;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use serde::Serialize;
+
+/**
+ * Format structured output is printed in. JSON is what `--json` prints, the others are
+ * chosen with the global `--output` option to fit other pipelines. `--porcelain` stands
+ * for `--output porcelain`, the versioned `key=value` lines scripts can rely on.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum OutputFormat {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+    MessagePack,
+    Porcelain
+}
+
+impl OutputFormat {
+    pub(crate) fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Json => serde_json::to_vec_pretty(value).map(|mut json| { json.push(b'\n'); json })
+                .map_err(|error| Error::Output(error.to_string())),
+            Self::Yaml => serde_yaml::to_string(value).map(String::into_bytes)
+                .map_err(|error| Error::Output(error.to_string())),
+            Self::Toml => toml::to_string_pretty(value).map(String::into_bytes)
+                .map_err(|error| Error::Output(error.to_string())),
+            Self::MessagePack => rmp_serde::to_vec_named(value)
+                .map_err(|error| Error::Output(error.to_string())),
+            Self::Porcelain => porcelain(value).map(String::into_bytes)
+                .map_err(|error| Error::Output(error.to_string())),
+        }
+    }
+
+    /**
+     * Writes the value to the standard output.
+     */
+    pub(crate) fn print<T: Serialize>(&self, value: &T) -> Result<(), Error> {
+        std::io::stdout().write_all(&self.encode(value)?).map_err(Error::Io)
+    }
+
+    /**
+     * Writes a list under the given key, as TOML documents can only be tables.
+     */
+    pub(crate) fn print_list<T: Serialize>(&self, key: &str, items: &[T]) -> Result<(), Error> {
+        self.print(&BTreeMap::from([(key, items)]))
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "json" => Ok(Self::Json),
+            "yaml" => Ok(Self::Yaml),
+            "toml" => Ok(Self::Toml),
+            "msgpack" => Ok(Self::MessagePack),
+            "porcelain" => Ok(Self::Porcelain),
+            _ => Err(name.to_string()),
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use crate::game::state::State;
+use crate::game::actions::Action;
+use crate::game::errors::Error;
+use crate::game::save_file::SaveFile;
+use crate::game::events::Event;
+use crate::game::hashing::Digest;
+use crate::game::resources::Resource;
+use crate::game::webhook::{Notification, Webhook};
+use crate::game::desktop::Desktop;
+use super::{commit, try_advance};
+use crate::CheckReport;
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde::Serialize;
+use serde_json::{to_string, to_string_pretty};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tungstenite::{Message, WebSocket};
+
+// Port the server listens on unless `--port` says otherwise.
+const DEFAULT_PORT: u16 = 8080;
+
+const JSON: &str = "application/json";
+// Prometheus text exposition format.
+const METRICS: &str = "text/plain; version=0.0.4";
+
+// Longest a WebSocket client may take to shake hands or to take an update, after which
+// it is dropped, so a stalled client can't hold up the others.
+const WS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/**
+ * Serves the saves in a directory over HTTP, as `<name>.save` files, so web frontends
+ * and bots can play remotely:
+ *
+ * - `GET /saves/<name>` returns the state.
+ * - `GET /saves/<name>/check` returns the check report.
+ * - `POST /saves/<name>/actions` applies the action in the body and returns the state.
+ * - `GET /metrics` returns gauges for every save, and counters for the events caused
+ *   by the actions applied since the server started, for Prometheus to scrape.
+ *
+ * Actions go through the same checks as on the command line. Requests are handled one
+ * at a time, so a save is never written twice at once. Only local connections are
+ * accepted. With `--grpc`, in builds with the `grpc` feature, the same calls are
+ * served over gRPC instead, as described by `proto/cliciv.proto`.
+ *
+ * With `--ws-port <port>`, every applied action is also pushed as an update to the
+ * WebSocket clients connected to that port, so dashboards and bots can follow games
+ * as they are played.
+ *
+ * With `--webhook <url>`, given as many times as needed, notable events in the saves,
+ * like full storage or food about to run out, are posted to the URL as they happen.
+ * With `--notify <kinds>`, the kinds listed, or `all`, are also shown as desktop
+ * notifications: `storage-full`, `building-built` and `starvation-imminent`.
+ */
+pub fn serve(args: &[String]) -> Result<(), Error> {
+    let mut port = DEFAULT_PORT;
+    let mut saves = PathBuf::from(".");
+    let mut ws_port = None;
+    let mut webhooks = vec!{};
+    let mut desktop = None;
+    let mut grpc = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--grpc" {
+            match cfg!(feature = "grpc") {
+                true => grpc = true,
+                false => return Err(Error::UnknownArgument(arg.clone()))
+            }
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| Error::MissingArgument(arg.clone()))?;
+
+        match arg.as_str() {
+            "--port" => port = value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?,
+            "--saves" => saves = PathBuf::from(value),
+            "--ws-port" => ws_port = Some(value.parse().map_err(|_| Error::InvalidAmount(value.clone()))?),
+            "--webhook" => webhooks.push(Webhook { url: value.clone() }),
+            "--notify" => desktop = Some(Desktop::parse(value)?),
+            _ => return Err(Error::UnknownArgument(arg.clone()))
+        }
+    }
+
+    let saves = Saves::new(saves).with_webhooks(webhooks).with_desktop(desktop);
+
+    if let Some(ws_port) = ws_port {
+        saves.listen(ws_port)?;
+    }
+
+    if grpc {
+        #[cfg(feature = "grpc")]
+        return super::grpc::serve(saves, port);
+    }
+
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|error| Error::Io(std::io::Error::other(error.to_string())))?;
+    eprintln!("Serving {} on http://127.0.0.1:{}", saves.dir().display(), port);
+
+    for mut request in server.incoming_requests() {
+        let (status, body, content_type) = match handle(&saves, &mut request) {
+            Ok((body, content_type)) => (200, body, content_type),
+            Err(error) => (status(&error), to_string_pretty(&serde_json::json!({ "code": error.code(), "error": error.to_string() })).unwrap(), JSON)
+        };
+        let header = Header::from_bytes("Content-Type", content_type).unwrap();
+
+        if let Err(error) = request.respond(Response::from_string(body).with_status_code(status).with_header(header)) {
+            eprintln!("{}", error);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle(saves: &Saves, request: &mut Request) -> Result<(String, &'static str), Error> {
+    let segments: Vec<&str> = request.url().trim_matches('/').split('/').collect();
+
+    match (request.method(), segments.as_slice()) {
+        (Method::Get, ["metrics"]) => Ok((saves.metrics()?, METRICS)),
+        (Method::Get, ["saves", name]) => {
+            let (_, state) = saves.read(name)?;
+            Ok((to_string_pretty(&state).unwrap(), JSON))
+        },
+        (Method::Get, ["saves", name, "check"]) => {
+            let (_, state) = saves.read(name)?;
+            Ok((to_string_pretty(&CheckReport::new(state.check())).unwrap(), JSON))
+        },
+        (Method::Post, ["saves", name, "actions"]) => {
+            let name = name.to_string();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).map_err(Error::Io)?;
+            let action: Action = serde_json::from_str(&body).map_err(Error::ActionParseError)?;
+
+            Ok((to_string_pretty(&saves.apply(&name, action)?).unwrap(), JSON))
+        },
+        _ => Err(Error::UnknownArgument(request.url().to_string()))
+    }
+}
+
+/**
+ * Pushed to WebSocket clients after an action is applied to a save, with the events
+ * it caused.
+ */
+#[derive(Serialize)]
+struct Update<'a> {
+    save: &'a str,
+    hash: Digest,
+    events: Vec<Event>,
+    state: &'a State
+}
+
+/**
+ * Directory of served saves, along with the WebSocket clients following them and how
+ * many of each event the actions applied to each save caused.
+ */
+pub(crate) struct Saves {
+    dir: PathBuf,
+    subscribers: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+    events: Mutex<BTreeMap<(String, &'static str), u64>>,
+    webhooks: Vec<Webhook>,
+    desktop: Option<Desktop>
+}
+
+impl Saves {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir, subscribers: Default::default(), events: Default::default(), webhooks: vec!{}, desktop: None }
+    }
+
+    pub(crate) fn with_webhooks(self, webhooks: Vec<Webhook>) -> Self {
+        Self { webhooks, ..self }
+    }
+
+    pub(crate) fn with_desktop(self, desktop: Option<Desktop>) -> Self {
+        Self { desktop, ..self }
+    }
+
+    pub(crate) fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /**
+     * Path of a save, refusing names that could reach outside the saves directory.
+     */
+    fn path(&self, name: &str) -> Result<PathBuf, Error> {
+        match !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            true => Ok(self.dir.join(format!("{}.save", name))),
+            false => Err(Error::UnknownArgument(name.to_string()))
+        }
+    }
+
+    pub(crate) fn read(&self, name: &str) -> Result<(SaveFile, State), Error> {
+        let file = File::open(self.path(name)?).map_err(Error::Io)?;
+        let mut save_file = SaveFile::default();
+        let state = save_file.read(file)?;
+
+        Ok((save_file, state))
+    }
+
+    /**
+     * Applies an action to a save, writes it back in the format it was read in and
+     * pushes the update to the WebSocket clients.
+     */
+    pub(crate) fn apply(&self, name: &str, action: Action) -> Result<State, Error> {
+        let (save_file, previous_state) = self.read(name)?;
+        let iteration = previous_state.commitment().iterations;
+        let state = commit(try_advance(previous_state.clone(), vec!{(action, 1)})?);
+        self.write(name, &save_file, &state)?;
+        let events = state.events_since(iteration);
+        self.notify(name, &previous_state, &state);
+
+        let mut counters = self.events.lock().unwrap();
+        for event in &events {
+            *counters.entry((name.to_string(), event.name())).or_default() += 1;
+        }
+        drop(counters);
+
+        self.broadcast(&to_string(&Update {
+            save: name,
+            hash: state.hash(),
+            events,
+            state: &state
+        }).unwrap());
+
+        Ok(state)
+    }
+
+    /**
+     * Writes a save to a temporary file next to it, then moves it in place, so the
+     * save is never left half written.
+     */
+    fn write(&self, name: &str, save_file: &SaveFile, state: &State) -> Result<(), Error> {
+        let path = self.path(name)?;
+        let temporary = path.with_extension("save.tmp");
+        let written = File::create(&temporary).map_err(Error::Io)
+            .and_then(|file| save_file.write(state, file))
+            .and_then(|_| std::fs::rename(&temporary, &path).map_err(Error::Io));
+
+        if written.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+
+        written
+    }
+
+    /**
+     * Metrics of every save in the directory, in the Prometheus text format. Saves are
+     * read without their log, and the ones that can't be read are left out.
+     */
+    pub(crate) fn metrics(&self) -> Result<String, Error> {
+        let mut states = vec!{};
+
+        for entry in std::fs::read_dir(&self.dir).map_err(Error::Io)?.flatten() {
+            let path = entry.path();
+            let name = match (path.extension().and_then(OsStr::to_str), path.file_stem().and_then(OsStr::to_str)) {
+                (Some("save"), Some(name)) if self.path(name).is_ok() => name.to_string(),
+                _ => continue
+            };
+            let state = File::open(&path).map_err(Error::Io)
+                .and_then(|file| SaveFile::default().read_without_log(file));
+
+            if let Ok(state) = state {
+                states.push((name, state));
+            }
+        }
+
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(metrics(&states, &self.events.lock().unwrap()))
+    }
+
+    /**
+     * Posts the notable events between two states of a save to the webhooks and shows
+     * the wanted ones on the desktop, in the background so a slow or broken webhook
+     * doesn't hold the request up.
+     */
+    fn notify(&self, name: &str, previous_state: &State, state: &State) {
+        let notifications = Notification::between(previous_state, state);
+
+        if (self.webhooks.is_empty() && self.desktop.is_none()) || notifications.is_empty() {
+            return;
+        }
+
+        let (webhooks, desktop) = (self.webhooks.clone(), self.desktop.clone());
+        let (name, iteration) = (name.to_string(), state.iterations());
+        std::thread::spawn(move || for notification in &notifications {
+            for webhook in &webhooks {
+                if let Err(error) = webhook.notify(&name, iteration, notification) {
+                    eprintln!("{}", error);
+                }
+            }
+
+            if let Some(desktop) = desktop.as_ref().filter(|desktop| desktop.wants(notification)) {
+                if let Err(error) = desktop.show(&name, notification) {
+                    eprintln!("{}", error);
+                }
+            }
+        });
+    }
+
+    /**
+     * Accepts WebSocket clients on the given port in the background, each shaking hands
+     * on its own thread so a slow one doesn't keep the rest waiting.
+     */
+    fn listen(&self, port: u16) -> Result<(), Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).map_err(Error::Io)?;
+        let subscribers = self.subscribers.clone();
+        eprintln!("Pushing updates on ws://127.0.0.1:{}", port);
+
+        std::thread::spawn(move || for stream in listener.incoming().flatten() {
+            let subscribers = subscribers.clone();
+
+            std::thread::spawn(move || {
+                let accepted = stream.set_read_timeout(Some(WS_TIMEOUT))
+                    .and_then(|_| stream.set_write_timeout(Some(WS_TIMEOUT)))
+                    .map_err(|error| error.to_string())
+                    .and_then(|_| tungstenite::accept(stream).map_err(|error| error.to_string()));
+
+                match accepted {
+                    Ok(socket) => subscribers.lock().unwrap().push(socket),
+                    Err(error) => eprintln!("{}", error)
+                }
+            });
+        });
+
+        Ok(())
+    }
+
+    /**
+     * Sends a message to every client, dropping the ones that went away or took longer
+     * than the timeout. The clients are taken out of the list while sending, so new
+     * ones can still join.
+     */
+    fn broadcast(&self, message: &str) {
+        let mut sockets = std::mem::take(&mut *self.subscribers.lock().unwrap());
+        sockets.retain_mut(|socket| socket.send(Message::Text(message.to_string())).is_ok());
+
+        self.subscribers.lock().unwrap().extend(sockets);
+    }
+}
+
+fn metrics(states: &[(String, State)], events: &BTreeMap<(String, &'static str), u64>) -> String {
+    let mut metrics = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        metrics.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+        for (labels, value) in samples {
+            metrics.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+        }
+    };
+
+    metric("cliciv_iterations", "gauge", "Iterations the game has run for.", states.iter()
+        .map(|(save, state)| (format!("save=\"{}\"", save), state.iterations().to_string()))
+        .collect());
+    metric("cliciv_population", "gauge", "Citizens in the civilization.", states.iter()
+        .map(|(save, state)| (format!("save=\"{}\"", save), state.population().0.to_string()))
+        .collect());
+    metric("cliciv_max_population", "gauge", "Citizens the civilization can house.", states.iter()
+        .map(|(save, state)| (format!("save=\"{}\"", save), state.population().1.to_string()))
+        .collect());
+    metric("cliciv_resource", "gauge", "Stored amount of a resource.", states.iter()
+        .flat_map(|(save, state)| Resource::all().into_iter().map(move |resource| {
+            (format!("save=\"{}\",resource=\"{}\"", save, resource.name()), state.resources.amount(&resource).to_string())
+        }))
+        .collect());
+    metric("cliciv_resource_capacity", "gauge", "Most of a resource that can be stored.", states.iter()
+        .flat_map(|(save, state)| Resource::all().into_iter().filter_map(move |resource| {
+            let capacity = state.resources.capacity(&resource)?;
+            Some((format!("save=\"{}\",resource=\"{}\"", save, resource.name()), capacity.to_string()))
+        }))
+        .collect());
+    metric("cliciv_rate", "gauge", "Production and consumption per iteration.", states.iter()
+        .flat_map(|(save, state)| {
+            let resources = &state.resources;
+            [
+                ("food_production", resources.food_prod_rate),
+                ("food_consumption", resources.food_cons_rate),
+                ("wood_production", resources.wood_prod_rate),
+                ("stone_production", resources.stone_prod_rate),
+                ("water_production", resources.water_prod_rate),
+                ("water_consumption", resources.water_cons_rate)
+            ].map(|(rate, value)| (format!("save=\"{}\",rate=\"{}\"", save, rate), value.to_string()))
+        })
+        .collect());
+    metric("cliciv_events_total", "counter", "Events caused by the actions applied since the server started.", events.iter()
+        .map(|((save, event), count)| (format!("save=\"{}\",event=\"{}\"", save, event), count.to_string()))
+        .collect());
+
+    metrics
+}
+
+fn status(error: &Error) -> u16 {
+    match error {
+        Error::UnknownArgument(_) => 404,
+        Error::Io(error) if error.kind() == std::io::ErrorKind::NotFound => 404,
+        Error::ActionParseError(_) => 400,
+        Error::Check(_) | Error::Iteration(_) => 422,
+        _ => 500
+    }
+}
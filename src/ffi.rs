@@ -0,0 +1,185 @@
+use super::{State, Action, Error};
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+/*
+ * C ABI of the engine, described by `include/cliciv.h`. States are opaque pointers,
+ * and everything else goes in and out as JSON strings, the way saves and actions are
+ * written on the command line. Calls that fail return a null pointer or -1, and leave
+ * the reason in `cliciv_last_error`.
+ */
+
+thread_local! {
+    // Message of the last call that failed on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn fail<T>(error: Error, failed: T) -> T {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = CString::new(error.to_string()).ok());
+    failed
+}
+
+/**
+ * # Safety
+ *
+ * The text must be null or a valid, null terminated C string.
+ */
+unsafe fn parse<T: serde::de::DeserializeOwned>(text: *const c_char, error: fn(serde_json::Error) -> Error) -> Result<T, Error> {
+    if text.is_null() {
+        return Err(Error::MissingArgument("json".to_string()));
+    }
+
+    serde_json::from_slice(CStr::from_ptr(text).to_bytes()).map_err(error)
+}
+
+/**
+ * Creates a new game with a random seed.
+ */
+#[no_mangle]
+pub extern "C" fn cliciv_create() -> *mut State {
+    Box::into_raw(Box::new(State::rand()))
+}
+
+/**
+ * Reads a game from the JSON of a save.
+ *
+ * # Safety
+ *
+ * The JSON must be null or a valid, null terminated C string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn cliciv_load(json: *const c_char) -> *mut State {
+    match parse(json, Error::InputParseError) {
+        Ok(state) => Box::into_raw(Box::new(state)),
+        Err(error) => fail(error, ptr::null_mut())
+    }
+}
+
+/**
+ * Applies the action in the JSON to the game. It is left as it was when the action
+ * can't be taken.
+ *
+ * # Safety
+ *
+ * The state must come from this library and not be freed, and the JSON must be null
+ * or a valid, null terminated C string.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn cliciv_apply_action(state: *mut State, json: *const c_char) -> c_int {
+    let state = match state.as_mut() {
+        Some(state) => state,
+        None => return fail(Error::NoGame, -1)
+    };
+    let action: Action = match parse(json, Error::ActionParseError) {
+        Ok(action) => action,
+        Err(error) => return fail(error, -1)
+    };
+
+    match state.clone().apply_action(action) {
+        Ok(next_state) => {
+            *state = next_state;
+            0
+        },
+        Err(error) => fail(Error::Iteration(error), -1)
+    }
+}
+
+/**
+ * JSON of the game, as it is saved. The string is freed with `cliciv_string_free`.
+ *
+ * # Safety
+ *
+ * The state must come from this library and not be freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn cliciv_serialize(state: *const State) -> *mut c_char {
+    let state = match state.as_ref() {
+        Some(state) => state,
+        None => return fail(Error::NoGame, ptr::null_mut())
+    };
+
+    match serde_json::to_string(state).map(CString::new) {
+        Ok(Ok(json)) => json.into_raw(),
+        Ok(Err(error)) => fail(Error::SaveEncode(error.to_string()), ptr::null_mut()),
+        Err(error) => fail(Error::SaveEncode(error.to_string()), ptr::null_mut())
+    }
+}
+
+/**
+ * Replays the game to make sure it wasn't tampered with. Returns 0 when it is valid.
+ *
+ * # Safety
+ *
+ * The state must come from this library and not be freed.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn cliciv_check(state: *const State) -> c_int {
+    match state.as_ref().map(State::check) {
+        Some(Ok(())) => 0,
+        Some(Err(error)) => fail(Error::Check(error), -1),
+        None => fail(Error::NoGame, -1)
+    }
+}
+
+/**
+ * Why the last call that failed on this thread did, or null. The string belongs to the
+ * library and lasts until the next call that fails.
+ */
+#[no_mangle]
+pub extern "C" fn cliciv_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ptr::null(), |error| error.as_ptr()))
+}
+
+/**
+ * # Safety
+ *
+ * The state must be null or come from this library, and not be used afterwards.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn cliciv_free(state: *mut State) {
+    if !state.is_null() {
+        drop(Box::from_raw(state));
+    }
+}
+
+/**
+ * # Safety
+ *
+ * The string must be null or come from this library, and not be used afterwards.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn cliciv_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_is_driven_through_the_c_abi() {
+        unsafe {
+            let state = cliciv_create();
+            let collect = CString::new(r#"{"Collect":"Food"}"#).unwrap();
+            let invalid = CString::new("nope").unwrap();
+
+            assert_eq!(cliciv_apply_action(state, collect.as_ptr()), 0);
+            assert_eq!(cliciv_apply_action(state, invalid.as_ptr()), -1);
+            assert!(CStr::from_ptr(cliciv_last_error()).to_str().unwrap().starts_with("invalid action"));
+            assert_eq!(cliciv_check(state), 0);
+
+            let json = cliciv_serialize(state);
+            let loaded = cliciv_load(json);
+            assert_eq!((*loaded).hash(), (*state).hash());
+
+            cliciv_string_free(json);
+            cliciv_free(loaded);
+            cliciv_free(state);
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use super::resources::PrimaryResource;
+use super::resources::{Resource, PrimaryResource};
 use super::buildings::Buildings;
-use super::jobs::Job;
+use super::jobs::{Job, Specialization};
+use super::trade::{TradeOffer, TradeReceipt};
 
 use serde::{Serialize, Deserialize};
 
@@ -16,7 +17,116 @@ pub enum Action {
     RecruitCitizen,
     AssignJob(Job),
     DischargeJob(Job),
+    Train(Specialization),
+    PrioritizeJobs(Vec<Job>),
+    Ennoble,
 
     // Land management:
     Build(Buildings),
-}
\ No newline at end of file
+    // Buildings under construction, by their position in the queue:
+    CancelConstruction(usize),
+    ReorderConstruction(usize, usize),
+
+    // Sandbox:
+    Grant(Resource, u64),
+
+    // Bartering primary resources, without gold, at a loss:
+    Barter { from: PrimaryResource, to: PrimaryResource, amount: u64 },
+
+    // Offering resources to the gods for piety:
+    Sacrifice(Resource, u64),
+
+    // Clerics reading what the next iterations hold:
+    ReadOmens(u64),
+
+    // Trading with other players:
+    OfferTrade(Box<TradeOffer>),
+    AcceptTrade(Box<TradeReceipt>),
+    SettleTrade(Box<TradeReceipt>),
+
+    // Several actions taken within a single iteration:
+    Turn(Vec<Action>),
+}
+
+impl Action {
+    /**
+     * Administrative actions only reorganize the workforce. They can be made free
+     * by the game settings, in which case no iteration elapses when taking them.
+     */
+    pub fn is_administrative(&self) -> bool {
+        match self {
+            Self::AssignJob(_) | Self::DischargeJob(_) | Self::PrioritizeJobs(_) | Self::ReorderConstruction(..) => true,
+            Self::Turn(actions) => actions.iter().all(|action| action.is_administrative()),
+            _ => false
+        }
+    }
+
+    /**
+     * Every verb `words` names actions with.
+     */
+    pub const VERBS: [&'static str; 19] = [
+        "idle", "collect", "recruit", "assign", "discharge", "train", "prioritize",
+        "ennoble", "build", "cancel", "reorder", "grant", "barter", "sacrifice", "read",
+        "offer", "accept", "settle", "turn"
+    ];
+
+    /**
+     * Verb naming the action in words, along with what it is taken on, if anything,
+     * in lower case.
+     */
+    pub fn words(&self) -> (&'static str, Option<String>) {
+        let object = |value: &dyn std::fmt::Debug| Some(format!("{:?}", value).to_lowercase());
+
+        match self {
+            Self::Idle => ("idle", None),
+            Self::Collect(resource) => ("collect", object(resource)),
+            Self::RecruitCitizen => ("recruit", None),
+            Self::AssignJob(job) => ("assign", object(job)),
+            Self::DischargeJob(job) => ("discharge", object(job)),
+            Self::Train(specialization) => ("train", object(specialization)),
+            Self::PrioritizeJobs(_) => ("prioritize", None),
+            Self::Ennoble => ("ennoble", None),
+            Self::Build(building) => ("build", object(building)),
+            Self::CancelConstruction(_) => ("cancel", None),
+            Self::ReorderConstruction(..) => ("reorder", None),
+            Self::Grant(resource, _) => ("grant", Some(resource.name().to_string())),
+            Self::Barter { from, .. } => ("barter", object(from)),
+            Self::Sacrifice(resource, _) => ("sacrifice", Some(resource.name().to_string())),
+            Self::ReadOmens(_) => ("read", Some("omens".to_string())),
+            Self::OfferTrade(_) => ("offer", None),
+            Self::AcceptTrade(_) => ("accept", None),
+            Self::SettleTrade(_) => ("settle", None),
+            Self::Turn(_) => ("turn", None),
+        }
+    }
+
+    /**
+     * Whether the action can be taken. Turns can't be empty nor hold other turns, and
+     * gather at most once, or a single iteration could gather without limit.
+     */
+    pub fn is_well_formed(&self) -> bool {
+        match self {
+            Self::Turn(actions) => !actions.is_empty()
+                && !actions.iter().any(|action| matches!(action, Self::Turn(_)))
+                && actions.iter().filter(|action| matches!(action, Self::Collect(_))).count() <= 1,
+            _ => true
+        }
+    }
+
+    /**
+     * Actions to apply, in order, during the iteration.
+     */
+    pub fn steps(&self) -> Vec<&Action> {
+        match self {
+            Self::Turn(actions) => actions.iter().collect(),
+            action => vec!{action}
+        }
+    }
+
+    /**
+     * Trades rest on the word of the other player, see `TradeOffer`.
+     */
+    pub fn is_trade(&self) -> bool {
+        matches!(self, Self::OfferTrade(_) | Self::AcceptTrade(_) | Self::SettleTrade(_))
+    }
+}
@@ -0,0 +1,144 @@
+use super::state::State;
+use super::actions::Action;
+use super::buildings::Buildings;
+use super::jobs::Job;
+use super::resources::{Resource, PrimaryResource};
+use super::quantity::Quantity;
+use super::errors::IterationError;
+
+use serde::Serialize;
+
+/**
+ * Action recommended by the advisor, with why. Higher scores are more urgent.
+ */
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct Advice {
+    pub action: Action,
+    pub reason: String,
+    pub score: u32
+}
+
+/**
+ * Recommends the next actions from a few rules of thumb: feed the people first and
+ * keep the water flowing, put idle citizens to work, make room for full stores and for more citizens, and grow
+ * the population while there is food to spare. Only actions the game would take are
+ * recommended, best first, with gathering by hand what isn't full as the last resort.
+ * Buildings that can't be paid for yet are recommended as gathering what they lack.
+ */
+pub fn advise(state: &State) -> Vec<Advice> {
+    let citizens = state.citizens();
+    let resources = &state.resources;
+    let morale = citizens.morale(state.land());
+    let food_rate = resources.food_net_rate(morale);
+    let (population, max_population) = state.population();
+    let mut advice = vec!{};
+
+    if food_rate < Quantity::ZERO {
+        let reason = match state.food_left() {
+            Some(turns) => format!("food is lost at {}/i and runs out in {} turns", food_rate, turns),
+            None => format!("food is lost at {}/i", food_rate)
+        };
+        advice.push((Action::AssignJob(Job::Farmer), reason.clone(), 100));
+        advice.push((Action::Collect(PrimaryResource::Food), reason, 75));
+    }
+
+    if resources.water_cons_rate > resources.water_prod_rate {
+        let reason = format!("water is drunk at {}/i but only {}/i flows in, which slows farms down", resources.water_cons_rate, resources.water_prod_rate);
+        advice.push((Action::Build(Buildings::Well), reason, 80));
+    }
+
+    if citizens.idle > 0 {
+        let (job, resource) = match resources.wood <= resources.stone {
+            true => (Job::Woodcutter, "wood"),
+            false => (Job::Miner, "stone")
+        };
+        advice.push((Action::AssignJob(job), format!("{} citizens are idle and {} is the scarcest", citizens.idle, resource), 60));
+    }
+
+    for resource in [PrimaryResource::Food, PrimaryResource::Wood, PrimaryResource::Stone].iter().cloned().map(Resource::Primary) {
+        let full = resources.capacity(&resource).is_some_and(|capacity| resources.amount(&resource) >= capacity);
+
+        if full {
+            for building in Buildings::all().into_iter().filter(|building| building.resource_storage_increase().iter().any(|(stored, _)| *stored == resource)) {
+                advice.push((Action::Build(building), format!("{} storage is full", resource.name()), 70));
+            }
+        }
+    }
+
+    if population >= max_population {
+        for building in Buildings::all().into_iter().rev().filter(|building| building.population_capacity_increase() > 0) {
+            let reason = format!("the population is at its limit and a {:?} houses {} more", building, building.population_capacity_increase());
+            advice.push((Action::Build(building), reason, 50));
+        }
+    } else if food_rate >= Quantity::ZERO {
+        advice.push((Action::RecruitCitizen, format!("{} of {} homes are taken and food is not being lost", population, max_population), 40));
+    }
+
+    for primary_resource in [PrimaryResource::Food, PrimaryResource::Wood, PrimaryResource::Stone] {
+        let resource = Resource::Primary(primary_resource.clone());
+        let room = resources.capacity(&resource).is_none_or(|capacity| resources.amount(&resource) < capacity);
+
+        if room {
+            advice.push((Action::Collect(primary_resource), format!("{} can always be gathered by hand", resource.name()), 10));
+        }
+    }
+
+    // The cheapest of the buildings recommended for the same reason is worked towards by
+    // gathering what it lacks, when it can't be paid for yet.
+    let mut targets: Vec<(Buildings, u32)> = vec!{};
+
+    for (action, _, score) in &advice {
+        if let Action::Build(building) = action {
+            match targets.iter_mut().find(|(_, target_score)| target_score == score) {
+                Some(target) if total_cost(building) < total_cost(&target.0) => target.0 = building.clone(),
+                Some(_) => (),
+                None => targets.push((building.clone(), *score))
+            }
+        }
+    }
+
+    for (building, score) in targets {
+        if let Err(IterationError::NotEnaughtResource(Resource::Primary(resource))) = state.clone().apply_action(Action::Build(building.clone())) {
+            let reason = format!("{} is short for a {:?}", Resource::Primary(resource.clone()).name(), building);
+            advice.push((Action::Collect(resource), reason, score - 1));
+        }
+    }
+
+    let mut ranked: Vec<Advice> = vec!{};
+    advice.sort_by_key(|(_, _, score)| std::cmp::Reverse(*score));
+
+    for (action, reason, score) in advice {
+        if !ranked.iter().any(|advice| advice.action == action) && state.clone().apply_action(action.clone()).is_ok() {
+            ranked.push(Advice { action, reason, score });
+        }
+    }
+
+    ranked
+}
+
+fn total_cost(building: &Buildings) -> Quantity {
+    building.costs().into_iter().fold(Quantity::ZERO, |total, (_, cost)| total + cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::buildings::Buildings;
+    use crate::game::jobs::Job;
+    use crate::game::settings::Settings;
+    use super::advise;
+
+    #[test]
+    fn advisor_feeds_the_people_first() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{(Action::Build(Buildings::Tent), 2), (Action::RecruitCitizen, 2)}).unwrap();
+        let advice = advise(&state);
+
+        assert_eq!(advice[0].action, Action::AssignJob(Job::Farmer));
+        assert!(advice.windows(2).all(|pair| pair[0].score >= pair[1].score));
+        assert!(advice.iter().all(|advice| state.clone().apply_action(advice.action.clone()).is_ok()));
+        assert_eq!(advise(&State::new(43932030939219715774207308070970463251)).len(), 3);
+    }
+}
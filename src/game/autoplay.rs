@@ -0,0 +1,111 @@
+use super::state::State;
+use super::actions::Action;
+use super::buildings::Buildings;
+use super::jobs::Job;
+use super::resources::PrimaryResource;
+use super::advisor::{advise, Advice};
+use super::errors::IterationError;
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/**
+ * How the autoplayer picks among the advisor's recommendations. Balanced games follow
+ * it as is, food first games favor farms and food stores, and expansion games favor
+ * housing and recruits.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Strategy {
+    Balanced,
+    FoodFirst,
+    Expansion,
+}
+
+impl Strategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Balanced => "balanced",
+            Self::FoodFirst => "food-first",
+            Self::Expansion => "expansion",
+        }
+    }
+
+    fn score(&self, advice: &Advice) -> u32 {
+        let favored = match (self, &advice.action) {
+            (Self::FoodFirst, Action::AssignJob(Job::Farmer)) => true,
+            (Self::FoodFirst, Action::Collect(PrimaryResource::Food)) => true,
+            (Self::FoodFirst, Action::Build(Buildings::Barn | Buildings::Granary)) => true,
+            (Self::Expansion, Action::Build(building)) => building.population_capacity_increase() > 0,
+            (Self::Expansion, Action::RecruitCitizen) => true,
+            _ => false
+        };
+
+        match favored {
+            true => advice.score + 25,
+            false => advice.score
+        }
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "balanced" => Ok(Self::Balanced),
+            "food-first" => Ok(Self::FoodFirst),
+            "expansion" => Ok(Self::Expansion),
+            _ => Err(name.to_string()),
+        }
+    }
+}
+
+/**
+ * Outcome of an autoplayed stretch of the game.
+ */
+pub struct Autoplay {
+    pub state: State,
+    // How many times each action was taken, by its debug name.
+    pub actions: BTreeMap<String, u64>
+}
+
+/**
+ * Plays the given number of turns, taking the recommendation the strategy likes best
+ * on each one. Ties go to the advisor's own order.
+ */
+pub fn autoplay(state: State, turns: u64, strategy: &Strategy) -> Result<Autoplay, IterationError> {
+    let mut autoplay = Autoplay { state, actions: BTreeMap::new() };
+
+    for _ in 0..turns {
+        let advice = advise(&autoplay.state);
+        let action = advice.iter().rev()
+            .max_by_key(|advice| strategy.score(advice))
+            .map_or(Action::Idle, |advice| advice.action.clone());
+
+        *autoplay.actions.entry(format!("{:?}", action)).or_default() += 1;
+        autoplay.state = autoplay.state.apply_action(action)?;
+    }
+
+    Ok(autoplay)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use super::{autoplay, Strategy};
+
+    #[test]
+    fn autoplay_grows_a_valid_game() {
+        for strategy in ["balanced", "food-first", "expansion"] {
+            let strategy: Strategy = strategy.parse().unwrap();
+            let autoplay = autoplay(State::new(43932030939219715774207308070970463251), 300, &strategy).unwrap();
+
+            assert_eq!(autoplay.state.iterations(), 300);
+            assert_eq!(autoplay.actions.values().sum::<u64>(), 300);
+            assert!(autoplay.state.population().0 > 0);
+            assert!(autoplay.state.check().is_ok());
+        }
+
+        assert!("greedy".parse::<Strategy>().is_err());
+    }
+}
@@ -1,4 +1,5 @@
-use super::resources::{Resource, PrimaryResource, SecondaryResource};
+use super::resources::{Resource, PrimaryResource, SecondaryResource, TertiaryResource, SpecialResource};
+use super::quantity::Quantity;
 
 use serde::{Serialize, Deserialize};
 
@@ -6,52 +7,179 @@ use serde::{Serialize, Deserialize};
 pub enum Buildings {
     Tent,
     WoodenHut,
+    House,
+    Mansion,
+    School,
+    Well,
     Barn,
+    Granary,
     WoodStockpile,
-    StoneStockpile
+    StoneStockpile,
+    Storehouse,
+    Vault
 }
 
 impl Buildings {
-    pub fn costs(&self) -> Vec<(Resource, f64)> {
+    pub fn all() -> Vec<Self> {
+        vec!{
+            Self::Tent,
+            Self::WoodenHut,
+            Self::House,
+            Self::Mansion,
+            Self::School,
+            Self::Well,
+            Self::Barn,
+            Self::Granary,
+            Self::WoodStockpile,
+            Self::StoneStockpile,
+            Self::Storehouse,
+            Self::Vault,
+        }
+    }
+
+    pub fn costs(&self) -> Vec<(Resource, Quantity)> {
         match self {
             Self::Tent => vec!{
-                (Resource::Primary(PrimaryResource::Wood), 2.0),
-                (Resource::Secondary(SecondaryResource::Skins), 2.0)
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(2)),
+                (Resource::Secondary(SecondaryResource::Skins), Quantity::units(2))
             },
 
             Self::WoodenHut => vec!{
-                (Resource::Primary(PrimaryResource::Wood), 20.0),
-                (Resource::Secondary(SecondaryResource::Skins), 1.0)
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(20)),
+                (Resource::Secondary(SecondaryResource::Skins), Quantity::ONE)
+            },
+
+            Self::House => vec!{
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(30)),
+                (Resource::Primary(PrimaryResource::Stone), Quantity::units(70)),
+                (Resource::Tertiary(TertiaryResource::Metal), Quantity::units(2))
+            },
+
+            Self::Mansion => vec!{
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(200)),
+                (Resource::Primary(PrimaryResource::Stone), Quantity::units(200)),
+                (Resource::Tertiary(TertiaryResource::Leather), Quantity::units(20)),
+                (Resource::Tertiary(TertiaryResource::Metal), Quantity::units(10))
+            },
+
+            Self::School => vec!{
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(60)),
+                (Resource::Primary(PrimaryResource::Stone), Quantity::units(40))
+            },
+
+            Self::Well => vec!{
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(20)),
+                (Resource::Primary(PrimaryResource::Stone), Quantity::units(30))
             },
 
             Self::Barn => vec!{
-                (Resource::Primary(PrimaryResource::Wood), 100.0)
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(100))
+            },
+
+            Self::Granary => vec!{
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(80)),
+                (Resource::Primary(PrimaryResource::Stone), Quantity::units(60))
             },
 
             Self::WoodStockpile => vec!{
-                (Resource::Primary(PrimaryResource::Wood), 100.0)
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(100))
             },
 
             Self::StoneStockpile => vec!{
-                (Resource::Primary(PrimaryResource::Wood), 100.0)
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(100))
+            },
+
+            Self::Storehouse => vec!{
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(60)),
+                (Resource::Primary(PrimaryResource::Stone), Quantity::units(30))
+            },
+
+            Self::Vault => vec!{
+                (Resource::Primary(PrimaryResource::Wood), Quantity::units(40)),
+                (Resource::Primary(PrimaryResource::Stone), Quantity::units(120))
             }
         }
     }
 
+    /**
+     * Work it takes builders to put the building up when construction takes time,
+     * a unit for every unit of its costs.
+     */
+    pub fn construction_work(&self) -> u64 {
+        self.costs().iter().map(|(_, cost)| cost.whole() as u64).sum()
+    }
+
     pub fn population_capacity_increase(&self) -> u64 {
         match self {
             Self::Tent => 1,
             Self::WoodenHut => 3,
+            Self::House => 6,
+            Self::Mansion => 20,
+            _ => 0
+        }
+    }
+
+    /**
+     * How comfortable a building is to live in, from 0.5 for a tent up to 2 for a mansion.
+     */
+    pub fn housing_quality(&self) -> Quantity {
+        match self {
+            Self::Tent => Quantity::hundredths(50),
+            Self::WoodenHut => Quantity::ONE,
+            Self::House => Quantity::hundredths(150),
+            Self::Mansion => Quantity::units(2),
+            _ => Quantity::ZERO
+        }
+    }
+
+    pub fn training_capacity_increase(&self) -> u64 {
+        match self {
+            Self::School => 2,
             _ => 0
         }
     }
 
-    pub fn primary_resource_storage_increase(&self) -> Option<(PrimaryResource, f64)> {
+    pub fn water_production_increase(&self) -> Quantity {
+        match self {
+            Self::Well => Quantity::units(2),
+            _ => Quantity::ZERO
+        }
+    }
+
+    pub fn water_storage_increase(&self) -> Quantity {
         match self {
-            Self::Barn => Some((PrimaryResource::Food, 100.0)),
-            Self::WoodStockpile => Some((PrimaryResource::Wood, 100.0)),
-            Self::StoneStockpile => Some((PrimaryResource::Stone, 100.0)),
-            _ => None
+            Self::Well => Quantity::units(50),
+            _ => Quantity::ZERO
+        }
+    }
+
+    /**
+     * Factor applied to the food spoilage rate once the building is built.
+     */
+    pub fn food_spoilage_multiplier(&self) -> Quantity {
+        match self {
+            Self::Granary => Quantity::hundredths(75),
+            _ => Quantity::ONE
+        }
+    }
+
+    pub fn resource_storage_increase(&self) -> Vec<(Resource, Quantity)> {
+        match self {
+            Self::Barn => vec!{(Resource::Primary(PrimaryResource::Food), Quantity::units(100))},
+            Self::Granary => vec!{(Resource::Primary(PrimaryResource::Food), Quantity::units(50))},
+            Self::WoodStockpile => vec!{(Resource::Primary(PrimaryResource::Wood), Quantity::units(100))},
+            Self::StoneStockpile => vec!{(Resource::Primary(PrimaryResource::Stone), Quantity::units(100))},
+            Self::Storehouse => vec!{
+                (Resource::Secondary(SecondaryResource::Skins), Quantity::units(100)),
+                (Resource::Secondary(SecondaryResource::Herbs), Quantity::units(100)),
+                (Resource::Secondary(SecondaryResource::Ore), Quantity::units(100))
+            },
+            Self::Vault => vec!{
+                (Resource::Tertiary(TertiaryResource::Leather), Quantity::units(50)),
+                (Resource::Tertiary(TertiaryResource::Metal), Quantity::units(50)),
+                (Resource::Special(SpecialResource::Gold), Quantity::units(100))
+            },
+            _ => vec!{}
         }
     }
 }
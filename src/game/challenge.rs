@@ -0,0 +1,125 @@
+use super::state::{State, LogDocument};
+use super::resources::{Resources, Resource};
+use super::citizens::Citizens;
+use super::settings::{Settings, daily_seed};
+use super::run_proof::RunProof;
+use super::errors::CheckError;
+use super::quantity::Quantity;
+
+use serde::{Serialize, Deserialize};
+
+// Points for every citizen, on top of a point for every unit of every resource.
+const CITIZEN_POINTS: u64 = 100;
+
+/**
+ * Result of a daily challenge as submitted to a leaderboard. The score can be read
+ * at a glance, but it is recomputed when verifying by replaying the game, whose log
+ * comes along, from the seed and settings of the challenge of the date. The proof
+ * carries the signature of the player.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct ChallengeResult {
+    pub date: String,
+    pub score: u64,
+    pub iterations: usize,
+    pub proof: RunProof,
+    pub game: LogDocument
+}
+
+impl ChallengeResult {
+    /**
+     * Result of a daily challenge game, which must be signed by its player.
+     */
+    pub fn new(state: &State) -> Result<Self, CheckError> {
+        let date = state.settings().daily.clone().ok_or(CheckError::NotChallenge)?;
+        let proof = state.run_proof();
+
+        if proof.signature.is_none() {
+            return Err(CheckError::MissingSignature);
+        }
+
+        Ok(Self {
+            date,
+            score: score(&proof.resources, &proof.citizens),
+            iterations: proof.commitment.iterations,
+            proof,
+            game: state.log_document()
+        })
+    }
+
+    /**
+     * Checks the proof and its signature, then replays the log from the seed and with
+     * the settings of the challenge of the date: it must end on the signed hash, after
+     * the claimed iterations and with the claimed score.
+     */
+    pub fn verify(&self) -> Result<(), CheckError> {
+        if self.proof.signature.is_none() {
+            return Err(CheckError::MissingSignature);
+        }
+
+        self.proof.verify()?;
+
+        let settings = Settings::daily(&self.date);
+        if self.game.seed != daily_seed(&self.date) || self.game.settings.hash() != settings.hash() {
+            return Err(CheckError::NotChallenge);
+        }
+
+        let state = crate::verify(self.game.seed, settings, self.game.log.clone(), self.proof.hash)?;
+        if state.iterations() != self.iterations {
+            return Err(CheckError::NotChallenge);
+        }
+
+        match score(&state.resources, state.citizens()) {
+            actual if actual == self.score => Ok(()),
+            actual => Err(CheckError::ScoreMismatch { claimed: self.score, actual })
+        }
+    }
+}
+
+/**
+ * Points a challenge run is ranked by: a hundred per citizen and one per whole unit of
+ * every resource held.
+ */
+pub fn score(resources: &Resources, citizens: &Citizens) -> u64 {
+    let units: i64 = Resource::all().iter()
+        .map(|resource| Quantity::max(resources.amount(resource), Quantity::ZERO).whole())
+        .sum();
+
+    citizens.count() * CITIZEN_POINTS + units as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::quantity::Quantity;
+    use crate::game::errors::CheckError;
+    use crate::game::settings::{Settings, daily_seed};
+    use crate::game::signing::generate_key;
+    use super::{ChallengeResult, score};
+
+    #[test]
+    fn challenge_results_verify_against_the_day() {
+        let key = generate_key();
+        let state = State::with_settings(daily_seed("2026-10-16"), Settings::daily("2026-10-16"))
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 30)}).unwrap();
+        let mut result = ChallengeResult::new(&state.clone().sign(&key)).unwrap();
+
+        assert!(result.verify().is_ok());
+        assert_eq!(result.score, score(&state.resources, state.citizens()));
+        assert!(matches!(ChallengeResult::new(&state), Err(CheckError::MissingSignature)));
+        assert!(matches!(ChallengeResult::new(&State::new(43932030939219715774207308070970463251).sign(&key)), Err(CheckError::NotChallenge)));
+
+        result.score += 1;
+        assert!(matches!(result.verify(), Err(CheckError::ScoreMismatch { .. })));
+        result.score -= 1;
+        result.date = "2026-10-17".to_string();
+        assert!(matches!(result.verify(), Err(CheckError::NotChallenge)));
+
+        // A signed proof of a state the log doesn't lead to is refused.
+        let mut forged = state.clone();
+        forged.resources.food += Quantity::units(1000);
+        assert!(matches!(ChallengeResult::new(&forged.sign(&key)).unwrap().verify(), Err(CheckError::HashMismatch { .. })));
+    }
+}
@@ -1,38 +1,209 @@
 use super::actions::Action;
 use super::errors::IterationError;
 use super::state::Context;
-use super::jobs::Job;
+use super::jobs::{Job, CitizenClass, Specialization};
+use super::land::Land;
+use super::buildings::Buildings;
+use super::events::Event;
+use super::quantity::Quantity;
+use super::hashing::{Digest, StableHasher};
 
 use serde::{Serialize, Deserialize};
-use std::collections::hash_map::DefaultHasher;
+use rand::distributions::{Distribution, Bernoulli};
 use std::hash::Hasher;
 
+type Trainee = (Specialization, u64);
+
+// Iterations a job can be worked without rest before its output starts dropping.
+const FATIGUE_ONSET: u64 = 100;
+
+// Iterations worked past the onset for every hundredth of output lost, up to half.
+const FATIGUE_PACE: u64 = 10;
+const MAX_FATIGUE: u64 = 50;
+
+// Iterations of work a job recovers from for every iteration it is left to rest.
+const REST_RECOVERY: u64 = 4;
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct Citizens {
     pub idle: u64,
     pub farmers: u64,
     pub woodcutters: u64,
     pub miners: u64,
-    pub max_population: u64
+    pub idle_scholars: u64,
+    pub idle_blacksmiths: u64,
+    pub idle_clerics: u64,
+    pub scholars: u64,
+    pub blacksmiths: u64,
+    pub clerics: u64,
+    pub nobles: u64,
+    pub trainees: Vec<Trainee>,
+    pub job_priorities: Vec<Job>,
+    pub max_trainees: u64,
+    pub max_population: u64,
+    // Iterations each laborer job has been worked without rest, if any.
+    #[serde(default)]
+    pub fatigue: Vec<(Job, u64)>,
+    #[serde(default)]
+    pub builders: u64,
+    #[serde(default)]
+    pub surveyors: u64
 }
 
 impl Citizens {
     pub fn count(&self) -> u64 {
-        self.idle + self.farmers + self.woodcutters + self.miners
+        self.laborers() + self.specialists() + self.nobles + self.trainees.len() as u64
+    }
+
+    pub fn laborers(&self) -> u64 {
+        self.idle + self.farmers + self.woodcutters + self.miners + self.builders + self.surveyors
+    }
+
+    pub fn specialists(&self) -> u64 {
+        self.idle_specialists() + self.scholars + self.blacksmiths + self.clerics
+    }
+
+    pub fn idle_specialists(&self) -> u64 {
+        self.idle_scholars + self.idle_blacksmiths + self.idle_clerics
+    }
+
+    /**
+     * Production multiplier bonus granted by nobles while their upkeep is paid.
+     */
+    pub fn governance_bonus(&self) -> Quantity {
+        Quantity::hundredths(5) * self.nobles
+    }
+
+    /**
+     * Morale scales every primary production rate. Huts are neutral, tents lower it
+     * and better housing raises it.
+     */
+    pub fn morale(&self, land: &Land) -> Quantity {
+        Quantity::hundredths(80) + Quantity::hundredths(20) * land.housing_quality()
+    }
+
+    /**
+     * Chance of a citizen being born on each iteration. Only housing better than huts
+     * makes the population grow on its own.
+     */
+    pub fn birth_rate(&self, land: &Land) -> Quantity {
+        Quantity::max(land.housing_quality() - Quantity::ONE, Quantity::ZERO) * Quantity::hundredths(5)
+    }
+
+    /**
+     * Rolls for a newborn citizen while there is room for one.
+     */
+    pub fn grow(self, ctx: &mut Context) -> Result<Self, IterationError> {
+        if ctx.birth_rate <= Quantity::ZERO || self.count() >= self.max_population {
+            return Ok(self);
+        }
+
+        if Bernoulli::from_ratio(ctx.birth_rate.as_hundredths() as u32, 100).unwrap().sample(&mut ctx.rng) {
+            ctx.events.push(Event::CitizenBorn);
+            Ok(Self { idle: self.idle + 1, ..self })
+        } else {
+            Ok(self)
+        }
+    }
+
+    /**
+     * Laborer job that gives up a worker first when food runs short: the one with the
+     * lowest priority among those with workers. Jobs without a declared priority go
+     * first. Nothing is returned until priorities are declared.
+     */
+    pub fn least_prioritized_job(&self) -> Option<Job> {
+        if self.job_priorities.is_empty() {
+            return None;
+        }
+
+        let candidates = [
+            (Job::Woodcutter, self.woodcutters),
+            (Job::Miner, self.miners)
+        ];
+
+        candidates.iter()
+            .filter(|(_, workers)| *workers > 0)
+            .max_by_key(|(job, _)| self.job_priorities.iter()
+                .position(|prioritized_job| prioritized_job == job)
+                .unwrap_or(usize::MAX))
+            .map(|(job, _)| job.clone())
+    }
+
+    /**
+     * Share of the output of a job lost to fatigue, growing once the job has been
+     * worked for long without rest.
+     */
+    pub fn fatigue(&self, job: &Job) -> Quantity {
+        let stretch = self.fatigue.iter().find(|(tired, _)| tired == job).map_or(0, |(_, stretch)| *stretch);
+
+        Quantity::hundredths((stretch.saturating_sub(FATIGUE_ONSET) / FATIGUE_PACE) as i64)
+    }
+
+    /**
+     * Laborer jobs tire while worked and rest while left without workers, several
+     * times faster than they tire. Only jobs still tired are kept.
+     */
+    fn tire(&self) -> Vec<(Job, u64)> {
+        let longest = FATIGUE_ONSET + MAX_FATIGUE * FATIGUE_PACE;
+        let jobs = [(Job::Farmer, self.farmers), (Job::Woodcutter, self.woodcutters), (Job::Miner, self.miners)];
+
+        jobs.iter().filter_map(|(job, workers)| {
+            let stretch = self.fatigue.iter().find(|(tired, _)| tired == job).map_or(0, |(_, stretch)| *stretch);
+            let stretch = match workers {
+                0 => stretch.saturating_sub(REST_RECOVERY),
+                _ => u64::min(stretch + 1, longest)
+            };
+
+            Some((job.clone(), stretch)).filter(|(_, stretch)| *stretch > 0)
+        }).collect()
+    }
+
+    /**
+     * Progresses school training, turning trainees that finished into idle specialists,
+     * and tires the workers when fatigue is played with.
+     */
+    pub fn work(self, ctx: &Context) -> Result<Self, IterationError> {
+        let fatigue = if ctx.fatigue { self.tire() } else { self.fatigue.clone() };
+        let mut citizens = Self { trainees: vec!{}, fatigue, ..self };
+
+        for (specialization, remaining) in self.trainees {
+            if remaining > 1 {
+                citizens.trainees.push((specialization, remaining - 1));
+                continue;
+            }
+
+            citizens = match specialization {
+                Specialization::Scholar => Self { idle_scholars: citizens.idle_scholars + 1, ..citizens },
+                Specialization::Blacksmith => Self { idle_blacksmiths: citizens.idle_blacksmiths + 1, ..citizens },
+                Specialization::Cleric => Self { idle_clerics: citizens.idle_clerics + 1, ..citizens },
+            };
+        }
+
+        Ok(citizens)
     }
 
     pub fn apply_action(self, action: &Action, ctx: &mut Context) -> Result<Self, IterationError> {
         match action {
             Action::RecruitCitizen => if self.count() < self.max_population {
+                ctx.events.push(Event::CitizenRecruited);
                 Ok(Self { idle: self.idle + 1, ..self })
             } else {
                 Err(IterationError::PopulationLimitReached)
             },
-            Action::AssignJob(job) => if self.idle > 0 {
+            Action::AssignJob(job) => if job.get_class() == CitizenClass::Specialist {
+                match job {
+                    Job::Scholar if self.idle_scholars > 0 => Ok(Self { idle_scholars: self.idle_scholars - 1, scholars: self.scholars + 1, ..self }),
+                    Job::Blacksmith if self.idle_blacksmiths > 0 => Ok(Self { idle_blacksmiths: self.idle_blacksmiths - 1, blacksmiths: self.blacksmiths + 1, ..self }),
+                    Job::Cleric if self.idle_clerics > 0 => Ok(Self { idle_clerics: self.idle_clerics - 1, clerics: self.clerics + 1, ..self }),
+                    job => Err(IterationError::NotEnaughtTrainedSpecialists(job.get_specialization().unwrap()))
+                }
+            } else if self.idle > 0 {
                 match job {
                     Job::Farmer if self.idle > 0 => Ok(Self { idle: self.idle - 1, farmers: self.farmers + 1, ..self }),
                     Job::Woodcutter if self.idle > 0 => Ok(Self { idle: self.idle - 1, woodcutters: self.woodcutters + 1, ..self }),
                     Job::Miner if self.idle > 0 => Ok(Self { idle: self.idle - 1, miners: self.miners + 1, ..self }),
+                    Job::Builder if self.idle > 0 => Ok(Self { idle: self.idle - 1, builders: self.builders + 1, ..self }),
+                    Job::Surveyor if self.idle > 0 => Ok(Self { idle: self.idle - 1, surveyors: self.surveyors + 1, ..self }),
                     _ => Ok(self)
                 }
             } else {
@@ -42,20 +213,138 @@ impl Citizens {
                 Job::Farmer if self.farmers > 0 => Ok(Self { idle: self.idle + 1, farmers: self.farmers - 1, ..self }),
                 Job::Woodcutter if self.woodcutters > 0 => Ok(Self { idle: self.idle + 1, woodcutters: self.woodcutters - 1, ..self }),
                 Job::Miner if self.miners > 0 => Ok(Self { idle: self.idle + 1, miners: self.miners - 1, ..self }),
+                Job::Builder if self.builders > 0 => Ok(Self { idle: self.idle + 1, builders: self.builders - 1, ..self }),
+                Job::Surveyor if self.surveyors > 0 => Ok(Self { idle: self.idle + 1, surveyors: self.surveyors - 1, ..self }),
+                Job::Scholar if self.scholars > 0 => Ok(Self { idle_scholars: self.idle_scholars + 1, scholars: self.scholars - 1, ..self }),
+                Job::Blacksmith if self.blacksmiths > 0 => Ok(Self { idle_blacksmiths: self.idle_blacksmiths + 1, blacksmiths: self.blacksmiths - 1, ..self }),
+                Job::Cleric if self.clerics > 0 => Ok(Self { idle_clerics: self.idle_clerics + 1, clerics: self.clerics - 1, ..self }),
                 job => Err(IterationError::NotEnaughtWorkersInJob(job.clone()))
             },
-            Action::Build(building) => Ok(Self { max_population: self.max_population + building.population_capacity_increase(), ..self }),
+            Action::Train(specialization) => if self.idle == 0 {
+                Err(IterationError::NotEnaughtIdleWorkers)
+            } else if self.trainees.len() as u64 >= self.max_trainees {
+                Err(IterationError::TrainingCapacityReached)
+            } else {
+                let mut trainees = self.trainees;
+                trainees.push((specialization.clone(), specialization.training_iterations()));
+                Ok(Self { idle: self.idle - 1, trainees, ..self })
+            },
+            Action::PrioritizeJobs(jobs) => Ok(Self { job_priorities: jobs.clone(), ..self }),
+            Action::Ennoble => if self.idle_scholars > 0 {
+                Ok(Self { idle_scholars: self.idle_scholars - 1, nobles: self.nobles + 1, ..self })
+            } else if self.idle_clerics > 0 {
+                Ok(Self { idle_clerics: self.idle_clerics - 1, nobles: self.nobles + 1, ..self })
+            } else if self.idle_blacksmiths > 0 {
+                Ok(Self { idle_blacksmiths: self.idle_blacksmiths - 1, nobles: self.nobles + 1, ..self })
+            } else {
+                Err(IterationError::NotEnaughtIdleSpecialists)
+            },
+            Action::Build(_) if ctx.construction => Ok(self),
+            Action::Build(building) => Ok(self.house(building)),
+            Action::ReadOmens(_) if self.clerics == 0 => Err(IterationError::NotEnaughtWorkersInJob(Job::Cleric)),
             _ => Ok(self)
         }
     }
 
-    pub fn hash(&self) -> u64 {
-        let mut hasher = DefaultHasher::default();
+    /**
+     * Room a finished building makes for citizens and trainees.
+     */
+    pub fn house(self, building: &Buildings) -> Self {
+        Self {
+            max_population: self.max_population + building.population_capacity_increase(),
+            max_trainees: self.max_trainees + building.training_capacity_increase(),
+            ..self
+        }
+    }
+
+    pub fn hash(&self) -> Digest {
+        let mut hasher = StableHasher::default();
         hasher.write_u64(self.idle);
         hasher.write_u64(self.farmers);
         hasher.write_u64(self.woodcutters);
         hasher.write_u64(self.miners);
+        hasher.write_u64(self.idle_scholars);
+        hasher.write_u64(self.idle_blacksmiths);
+        hasher.write_u64(self.idle_clerics);
+        hasher.write_u64(self.scholars);
+        hasher.write_u64(self.blacksmiths);
+        hasher.write_u64(self.clerics);
+        hasher.write_u64(self.nobles);
+        hasher.write_usize(self.trainees.len());
+        for (specialization, remaining) in self.trainees.iter() {
+            hasher.write_u8(specialization.clone() as u8);
+            hasher.write_u64(*remaining);
+        }
+        hasher.write_usize(self.job_priorities.len());
+        for job in self.job_priorities.iter() {
+            hasher.write_u8(job.clone() as u8);
+        }
+        hasher.write_u64(self.max_trainees);
         hasher.write_u64(self.max_population);
-        hasher.finish()
+        hasher.write_usize(self.fatigue.len());
+        for (job, stretch) in self.fatigue.iter() {
+            hasher.write_u8(job.clone() as u8);
+            hasher.write_u64(*stretch);
+        }
+        hasher.write_u64(self.builders);
+        hasher.write_u64(self.surveyors);
+        hasher.digest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::{Resource, PrimaryResource};
+    use crate::game::quantity::Quantity;
+    use crate::game::buildings::Buildings;
+    use crate::game::jobs::Job;
+    use crate::game::settings::Settings;
+
+    #[test]
+    fn soft_cap_makes_crowded_jobs_produce_less() {
+        let wood = |state: &State| state.resources.amount(&Resource::Primary(PrimaryResource::Wood));
+        let plan = vec!{
+            (Action::Collect(PrimaryResource::Food), 200),
+            (Action::Collect(PrimaryResource::Wood), 60),
+            (Action::Build(Buildings::WoodenHut), 3),
+            (Action::RecruitCitizen, 4),
+            (Action::AssignJob(Job::Woodcutter), 4),
+            (Action::Idle, 10),
+        };
+        let flat = State::new(-141872649309347578469772012024767025949);
+        let capped = State::with_settings(-141872649309347578469772012024767025949, Settings { soft_cap: Some(2), ..Default::default() });
+
+        assert_eq!(Job::Woodcutter.crowding_loss(1, 2), Quantity::ZERO);
+        assert_eq!(Job::Woodcutter.crowding_loss(3, 2), Quantity::hundredths(42));
+        assert_ne!(flat.settings().hash(), capped.settings().hash());
+
+        let (flat, capped) = (flat.apply_plan(plan.clone()).unwrap(), capped.apply_plan(plan).unwrap());
+        assert!(wood(&capped) > Quantity::ZERO);
+        assert!(wood(&capped) < wood(&flat));
+    }
+
+    #[test]
+    fn long_stretches_tire_workers_until_they_rest() {
+        let plan = vec!{
+            (Action::Collect(PrimaryResource::Food), 200),
+            (Action::Collect(PrimaryResource::Wood), 60),
+            (Action::Build(Buildings::WoodenHut), 3),
+            (Action::RecruitCitizen, 1),
+            (Action::AssignJob(Job::Woodcutter), 1),
+            (Action::Idle, 300),
+        };
+        let rested = State::new(-141872649309347578469772012024767025949).apply_plan(plan.clone()).unwrap();
+        let tired = State::with_settings(-141872649309347578469772012024767025949, Settings { fatigue: true, ..Default::default() })
+            .apply_plan(plan).unwrap();
+
+        assert!(rested.citizens().fatigue.is_empty());
+        assert_eq!(tired.citizens().fatigue(&Job::Woodcutter), Quantity::hundredths(20));
+        assert!(tired.resources.wood < rested.resources.wood);
+
+        let recovered = tired.apply_plan(vec!{(Action::DischargeJob(Job::Woodcutter), 1), (Action::Idle, 200)}).unwrap();
+        assert_eq!(recovered.citizens().fatigue(&Job::Woodcutter), Quantity::ZERO);
+        assert!(recovered.citizens().fatigue.is_empty());
     }
 }
@@ -0,0 +1,167 @@
+use super::state::{State, LogEntry};
+use super::resources::Resource;
+use super::buildings::Buildings;
+use super::jobs::Job;
+use super::actions::Action;
+use super::scenarios::Import;
+use super::errors::Error;
+
+use serde_json::{json, Map, Value};
+
+// CivClicker release whose save schema the export follows.
+const CIVCLICKER_VERSION: (u64, u64, u64, &str) = (1, 1, 59, "alpha");
+
+// CivClicker buildings with a counterpart, by their id in its saves.
+const BUILDINGS: [(&str, Buildings); 7] = [
+    ("tent", Buildings::Tent),
+    ("hut", Buildings::WoodenHut),
+    ("house", Buildings::House),
+    ("mansion", Buildings::Mansion),
+    ("barn", Buildings::Barn),
+    ("woodstock", Buildings::WoodStockpile),
+    ("stonestock", Buildings::StoneStockpile)
+];
+
+// CivClicker jobs with a counterpart among laborers.
+const JOBS: [(&str, Job); 3] = [
+    ("farmer", Job::Farmer),
+    ("woodcutter", Job::Woodcutter),
+    ("miner", Job::Miner)
+];
+
+/**
+ * Game as a CivClicker save, the JSON the browser game keeps under the `civ` key of
+ * its local storage. Only what exists in both games is carried over: resources, land,
+ * the buildings CivClicker also has and the workers in its jobs. Schools, wells,
+ * granaries, storehouses, vaults, scholars and nobles have no counterpart and are
+ * left out, and specialists in training are counted as unemployed.
+ */
+pub fn civclicker_save(state: &State) -> Value {
+    let citizens = state.citizens();
+    let land = state.land();
+    let mut civ = Map::new();
+
+    civ.insert("civName".to_string(), json!(format!("cliciv {}", &state.game_id().to_string()[..8])));
+
+    for resource in Resource::all() {
+        let owned = state.resources.amount(&resource).whole().max(0);
+        civ.insert(resource.name().to_string(), json!({ "owned": owned }));
+    }
+
+    let owned = [
+        ("land", land.total_land),
+        ("freeLand", land.free_land()),
+        ("tent", land.tents),
+        ("hut", land.wooden_huts),
+        ("house", land.houses),
+        ("mansion", land.mansions),
+        ("barn", land.barns),
+        ("woodstock", land.wood_stockpiles),
+        ("stonestock", land.stone_stockpiles),
+        ("unemployed", citizens.idle + citizens.idle_specialists() + citizens.trainees.len() as u64),
+        ("farmer", citizens.farmers),
+        ("woodcutter", citizens.woodcutters),
+        ("miner", citizens.miners),
+        ("blacksmith", citizens.blacksmiths),
+        ("cleric", citizens.clerics)
+    ];
+
+    for (id, owned) in owned {
+        civ.insert(id.to_string(), json!({ "owned": owned }));
+    }
+
+    let (major, minor, sub, modifier) = CIVCLICKER_VERSION;
+
+    json!({
+        "versionData": { "major": major, "minor": minor, "sub": sub, "mod": modifier },
+        "saveDate": state.saved_at() * 1000,
+        "curCiv": civ
+    })
+}
+
+/**
+ * Start of a game matching a CivClicker save: its buildings are built, its population
+ * recruited into the laborer jobs both games share and its resources granted. Every
+ * other citizen, soldiers and specialists included, starts idle. The land is the one
+ * the game is created with, as CivClicker land can't be bought.
+ */
+pub fn civclicker_import(save: &Value) -> Result<Import, Error> {
+    let civ = save.get("curCiv").and_then(Value::as_object)
+        .ok_or_else(|| Error::SaveDecode("not a CivClicker save".to_string()))?;
+    let owned = |id: &str| civ.get(id).and_then(|entry| entry.get("owned")).and_then(Value::as_f64)
+        .map_or(0, |owned| owned.max(0.0) as u64);
+
+    let mut setup: Vec<LogEntry> = vec!{};
+
+    for (id, building) in BUILDINGS {
+        setup.push((Action::Build(building), owned(id)));
+    }
+
+    let population = ["unemployed", "farmer", "woodcutter", "miner", "tanner", "blacksmith", "healer", "cleric", "labourer", "soldier", "cavalry"]
+        .iter().map(|id| owned(id)).sum();
+    setup.push((Action::RecruitCitizen, population));
+
+    for (id, job) in JOBS {
+        setup.push((Action::AssignJob(job), owned(id)));
+    }
+
+    for resource in Resource::all() {
+        setup.push((Action::Grant(resource.clone(), owned(resource.name())), 1));
+    }
+
+    setup.retain(|(action, count)| *count > 0 && !matches!(action, Action::Grant(_, 0)));
+
+    Ok(Import { source: "civclicker".to_string(), setup })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::quantity::Quantity;
+    use crate::game::scenarios::Scenario;
+    use crate::game::settings::Settings;
+    use super::{civclicker_save, civclicker_import};
+
+    #[test]
+    fn games_export_to_civclicker_saves() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 30), (Action::Collect(PrimaryResource::Wood), 12)}).unwrap();
+        let save = civclicker_save(&state);
+
+        assert_eq!(save["versionData"]["major"], 1);
+        assert_eq!(save["curCiv"]["food"]["owned"], state.resources.food.whole());
+        assert_eq!(save["curCiv"]["wood"]["owned"], state.resources.wood.whole());
+        assert_eq!(save["curCiv"]["land"]["owned"], state.land().total_land);
+        assert_eq!(save["curCiv"]["unemployed"]["owned"], state.citizens().idle);
+        assert!(save["curCiv"].get("school").is_none());
+    }
+
+    #[test]
+    fn civclicker_saves_are_imported_as_sandbox_games() {
+        let save = serde_json::json!({ "curCiv": {
+            "food": { "owned": 150 }, "skins": { "owned": 5 },
+            "tent": { "owned": 9 }, "barn": { "owned": 1 },
+            "unemployed": { "owned": 3 }, "farmer": { "owned": 5 }, "cleric": { "owned": 1 }
+        }});
+        let settings = Settings {
+            scenario: Some(Scenario::Imported(Box::new(civclicker_import(&save).unwrap()))),
+            sandbox: true,
+            ..Default::default()
+        };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings);
+
+        assert_eq!(state.resources.food, Quantity::units(150));
+        assert_eq!(state.resources.wood, Quantity::ZERO);
+        assert_eq!(state.land().tents, 9);
+        assert_eq!(state.citizens().farmers, 5);
+        assert_eq!(state.population().0, 9);
+        assert_eq!(state.commitment().iterations, 0);
+
+        let state = state.apply_log(vec!{(Action::Collect(PrimaryResource::Food), 5)}).unwrap();
+
+        assert!(state.check().is_ok());
+        assert!(civclicker_import(&serde_json::json!({})).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use super::state::{State, LogEntry, HistoryEntry};
+use super::errors::Error;
+use super::hashing::Digest;
+
+use std::path::PathBuf;
+use rusqlite::{params, Connection, OptionalExtension};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS state (id INTEGER PRIMARY KEY CHECK (id = 0), game TEXT NOT NULL, body TEXT NOT NULL);
+    CREATE TABLE IF NOT EXISTS log (position INTEGER PRIMARY KEY, action TEXT NOT NULL, count INTEGER NOT NULL);
+    CREATE TABLE IF NOT EXISTS history (iteration INTEGER PRIMARY KEY, hash TEXT NOT NULL, resources TEXT NOT NULL);
+";
+
+/**
+ * Game kept in an SQLite database instead of a save piped through the standard input
+ * and output. Besides the state and its log, the database holds the resources at the
+ * end of every iteration, so the history of the game is read without replaying it.
+ * Like a log file, it is only added to while the log of the state extends its own.
+ */
+#[derive(Clone, Debug)]
+pub struct Database {
+    pub path: PathBuf
+}
+
+impl Database {
+    fn open(&self) -> Result<Connection, Error> {
+        let connection = Connection::open(&self.path).map_err(database_error)?;
+        connection.execute_batch(SCHEMA).map_err(database_error)?;
+
+        Ok(connection)
+    }
+
+    pub fn load(&self) -> Result<State, Error> {
+        let state = self.load_without_log()?;
+
+        Ok(state.with_log(read_log(&self.open()?)?))
+    }
+
+    /**
+     * Latest state, leaving the log in the database.
+     */
+    pub fn load_without_log(&self) -> Result<State, Error> {
+        let connection = self.open()?;
+        let body: String = connection.query_row("SELECT body FROM state", [], |row| row.get(0))
+            .optional().map_err(database_error)?
+            .ok_or(Error::NoGame)?;
+
+        serde_json::from_str(&body).map_err(Error::InputParseError)
+    }
+
+    pub fn store(&self, state: &State) -> Result<(), Error> {
+        let mut connection = self.open()?;
+        let transaction = connection.transaction().map_err(database_error)?;
+
+        let game: Option<String> = transaction.query_row("SELECT game FROM state", [], |row| row.get(0))
+            .optional().map_err(database_error)?;
+        let log = read_log(&transaction)?;
+        let extends = game.and_then(|game| Digest::from_hex(&game)) == Some(state.game_id())
+            && State::split_log(state.log(), State::count_actions(&log)).0 == log;
+
+        let (missing, position, from) = match extends {
+            true => {
+                let position: i64 = transaction.query_row("SELECT COALESCE(MAX(position) + 1, 0) FROM log", [], |row| row.get(0))
+                    .map_err(database_error)?;
+                let from: i64 = transaction.query_row("SELECT COALESCE(MAX(iteration), 0) FROM history", [], |row| row.get(0))
+                    .map_err(database_error)?;
+                (State::split_log(state.log(), State::count_actions(&log)).1, position, from as usize)
+            },
+            false => {
+                transaction.execute_batch("DELETE FROM log; DELETE FROM history;").map_err(database_error)?;
+                (state.log().to_vec(), 0, 0)
+            }
+        };
+
+        for (offset, (action, count)) in missing.iter().enumerate() {
+            let action = serde_json::to_string(action).map_err(Error::InputParseError)?;
+            transaction.execute("INSERT INTO log (position, action, count) VALUES (?1, ?2, ?3)",
+                params![position + offset as i64, action, *count as i64]).map_err(database_error)?;
+        }
+
+        for entry in state.history(from).map_err(Error::Iteration)? {
+            let resources = serde_json::to_string(&entry.resources).map_err(Error::InputParseError)?;
+            transaction.execute("INSERT OR REPLACE INTO history (iteration, hash, resources) VALUES (?1, ?2, ?3)",
+                params![entry.iteration as i64, entry.hash.to_hex(), resources]).map_err(database_error)?;
+        }
+
+        let body = serde_json::to_string(&state.without_log()).map_err(Error::InputParseError)?;
+        transaction.execute("INSERT OR REPLACE INTO state (id, game, body) VALUES (0, ?1, ?2)",
+            params![state.game_id().to_hex(), body]).map_err(database_error)?;
+
+        transaction.commit().map_err(database_error)
+    }
+
+    /**
+     * Recorded iterations within the given range.
+     */
+    pub fn history(&self, from: usize, to: usize) -> Result<Vec<HistoryEntry>, Error> {
+        let connection = self.open()?;
+        let mut statement = connection.prepare("SELECT iteration, hash, resources FROM history WHERE iteration BETWEEN ?1 AND ?2 ORDER BY iteration")
+            .map_err(database_error)?;
+        let rows = statement.query_map(params![from as i64, to.min(i64::MAX as usize) as i64], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        }).map_err(database_error)?;
+
+        let mut history = vec!{};
+
+        for row in rows {
+            let (iteration, hash, resources) = row.map_err(database_error)?;
+            history.push(HistoryEntry {
+                iteration: iteration as usize,
+                hash: Digest::from_hex(&hash).ok_or(Error::InvalidDigest(hash))?,
+                resources: serde_json::from_str(&resources).map_err(Error::InputParseError)?
+            });
+        }
+
+        Ok(history)
+    }
+}
+
+/**
+ * Log in the database, with consecutive entries of the same action merged.
+ */
+fn read_log(connection: &Connection) -> Result<Vec<LogEntry>, Error> {
+    let mut statement = connection.prepare("SELECT action, count FROM log ORDER BY position").map_err(database_error)?;
+    let rows = statement.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(database_error)?;
+
+    let mut log: Vec<LogEntry> = vec!{};
+
+    for row in rows {
+        let (action, count) = row.map_err(database_error)?;
+        let action = serde_json::from_str(&action).map_err(Error::InputParseError)?;
+
+        match log.last_mut() {
+            Some(last_entry) if last_entry.0 == action => last_entry.1 += count as u64,
+            _ => log.push((action, count as u64))
+        }
+    }
+
+    Ok(log)
+}
+
+fn database_error(error: rusqlite::Error) -> Error {
+    Error::Database(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use super::Database;
+
+    #[test]
+    fn database_keeps_history_of_every_iteration() {
+        let path = std::env::temp_dir().join(format!("cliciv-{}.db", std::process::id()));
+        let database = Database { path: path.clone() };
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        database.store(&state).unwrap();
+
+        let state = state.apply_log(vec!{(Action::Collect(PrimaryResource::Wood), 5)}).unwrap();
+        database.store(&state).unwrap();
+        let loaded = database.load().unwrap();
+        let history = database.history(0, usize::MAX).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.log(), state.log());
+        assert!(loaded.check().is_ok());
+        assert_eq!(history.len(), 16);
+        assert_eq!(history.last().unwrap().hash, state.hash());
+        assert_eq!(serde_json::to_string(&history).unwrap(), serde_json::to_string(&state.history(0).unwrap()).unwrap());
+    }
+}
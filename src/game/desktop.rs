@@ -0,0 +1,66 @@
+use super::errors::Error;
+use super::webhook::Notification;
+
+use std::collections::BTreeSet;
+
+/**
+ * Shows notifications on the desktop, through the notification service of the system
+ * (D-Bus on Linux and the BSDs, the notification center on macOS). Only the kinds of
+ * notification it was given are shown.
+ */
+#[derive(Clone, Debug)]
+pub struct Desktop {
+    pub kinds: BTreeSet<&'static str>
+}
+
+impl Desktop {
+    /**
+     * Desktop notifications for a comma separated list of kinds, or `all` of them.
+     */
+    pub fn parse(list: &str) -> Result<Self, Error> {
+        let kinds = match list {
+            "all" => Notification::kinds().iter().copied().collect(),
+            _ => list.split(',')
+                .map(|name| Notification::kinds().iter().copied().find(|kind| *kind == name)
+                    .ok_or_else(|| Error::UnknownArgument(name.to_string())))
+                .collect::<Result<_, _>>()?
+        };
+
+        Ok(Self { kinds })
+    }
+
+    pub fn wants(&self, notification: &Notification) -> bool {
+        self.kinds.contains(notification.kind())
+    }
+
+    pub fn show(&self, save: &str, notification: &Notification) -> Result<(), Error> {
+        let title = format!("cliciv: {}", save);
+        let body = notification.to_string();
+
+        notify_rust::Notification::new()
+            .summary(&title)
+            .body(&body)
+            .show()
+            .map(|_| ())
+            .map_err(|error| Error::Io(std::io::Error::other(error.to_string())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::resources::{Resource, PrimaryResource};
+    use crate::game::webhook::Notification;
+    use super::Desktop;
+
+    #[test]
+    fn desktop_notifications_are_enabled_by_kind() {
+        let storage_full = Notification::StorageFull(Resource::Primary(PrimaryResource::Food));
+        let starvation = Notification::StarvationImminent { iterations_left: 3 };
+        let desktop = Desktop::parse("starvation-imminent").unwrap();
+
+        assert!(desktop.wants(&starvation));
+        assert!(!desktop.wants(&storage_full));
+        assert!(Desktop::parse("all").unwrap().wants(&storage_full));
+        assert!(Desktop::parse("storage-full,raiders").is_err());
+    }
+}
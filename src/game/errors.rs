@@ -1,25 +1,336 @@
 use super::resources::Resource;
-use super::jobs::Job;
+use super::jobs::{Job, Specialization};
+use super::actions::Action;
+use super::hashing::Digest;
+use super::rules::Rules;
+use super::invariants::Violation;
+use super::state::Difference;
 
-#[derive(Debug)]
+use thiserror::Error as ThisError;
+
+#[allow(dead_code, clippy::enum_variant_names)]
+#[derive(Debug, ThisError)]
 pub enum Error {
-    InputParseError(serde_json::Error),
-    ActionParseError(serde_json::Error),
-    Check(CheckError),
-    Iteration(IterationError)
+    #[error("invalid input: {0}")]
+    InputParseError(#[source] serde_json::Error),
+    #[error("invalid action: {0}")]
+    ActionParseError(#[source] serde_json::Error),
+    #[error("check failed: {0}")]
+    Check(#[source] CheckError),
+    #[error("rollback failed: {0}")]
+    Rollback(#[source] RollbackError),
+    #[error("action failed: {0}")]
+    Iteration(#[source] IterationError),
+    #[error("no game found, run `cliciv create` first")]
+    NoGame,
+    #[error("unknown argument {0}")]
+    UnknownArgument(String),
+    #[error("unknown scenario {0}")]
+    UnknownScenario(String),
+    #[error("unknown resource {0}")]
+    UnknownResource(String),
+    #[error("invalid amount {0}")]
+    InvalidAmount(String),
+    #[error("invalid target {0}, expected something like `maximize stone in 200 turns`")]
+    InvalidTarget(String),
+    #[error("missing argument {0}")]
+    MissingArgument(String),
+    #[error("{0} can't be used along with {1}")]
+    ConflictingArguments(String, String),
+    #[error("can't read the key file: {0}")]
+    KeyFile(#[source] std::io::Error),
+    #[error("key file {0} already exists")]
+    KeyFileExists(String),
+    #[error("invalid key in {0}")]
+    InvalidKey(String),
+    #[error("{0}")]
+    Io(#[source] std::io::Error),
+    #[error("the save couldn't be encrypted")]
+    Encryption,
+    #[error("the save couldn't be decrypted, check the passphrase")]
+    Decryption,
+    #[error("unknown save format {0}")]
+    UnknownFormat(String),
+    #[error("unknown compression {0}")]
+    UnknownCompression(String),
+    #[error("invalid digest {0}")]
+    InvalidDigest(String),
+    #[error("the save couldn't be encoded: {0}")]
+    SaveEncode(String),
+    #[error("the save couldn't be decoded: {0}")]
+    SaveDecode(String),
+    #[error("the log file {0} doesn't hold the log of this game")]
+    MissingLogFile(String),
+    #[error("invalid seed phrase {0}")]
+    InvalidSeedPhrase(String),
+    #[error("no key in {0}")]
+    MissingKey(String),
+    #[error("the output couldn't be encoded: {0}")]
+    Output(String),
+    #[error("unknown output format {0}")]
+    UnknownOutput(String),
+    #[error("database error: {0}")]
+    Database(String),
+    #[error("remote error: {0}")]
+    Remote(String),
+    #[error("sync refused: {0}")]
+    Sync(#[source] SyncError)
+}
+
+impl Error {
+    /**
+     * Stable identifier of the error, for scripts. Wrapped errors give their own code.
+     */
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InputParseError(_) => "invalid_input",
+            Self::ActionParseError(_) => "invalid_action",
+            Self::Check(error) => error.code(),
+            Self::Rollback(error) => error.code(),
+            Self::Iteration(error) => error.code(),
+            Self::NoGame => "no_game",
+            Self::UnknownArgument(_) => "unknown_argument",
+            Self::UnknownScenario(_) => "unknown_scenario",
+            Self::UnknownResource(_) => "unknown_resource",
+            Self::InvalidAmount(_) => "invalid_amount",
+            Self::InvalidTarget(_) => "invalid_target",
+            Self::MissingArgument(_) => "missing_argument",
+            Self::ConflictingArguments(..) => "conflicting_arguments",
+            Self::KeyFile(_) => "key_file",
+            Self::KeyFileExists(_) => "key_file_exists",
+            Self::InvalidKey(_) => "invalid_key",
+            Self::Io(_) => "io",
+            Self::Encryption => "encryption",
+            Self::Decryption => "decryption",
+            Self::UnknownFormat(_) => "unknown_format",
+            Self::UnknownCompression(_) => "unknown_compression",
+            Self::InvalidDigest(_) => "invalid_digest",
+            Self::SaveEncode(_) => "save_encode",
+            Self::SaveDecode(_) => "save_decode",
+            Self::MissingLogFile(_) => "missing_log_file",
+            Self::InvalidSeedPhrase(_) => "invalid_seed_phrase",
+            Self::MissingKey(_) => "missing_key",
+            Self::Output(_) => "output",
+            Self::UnknownOutput(_) => "unknown_output",
+            Self::Database(_) => "database",
+            Self::Remote(_) => "remote",
+            Self::Sync(error) => error.code(),
+        }
+    }
+
+    /**
+     * Status the CLI exits with: 2 for a mistyped command, 3 when there is no save to
+     * read or it can't be read or written, 1 for the rest.
+     */
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::UnknownArgument(_) | Self::UnknownScenario(_) | Self::UnknownResource(_)
+                | Self::InvalidAmount(_) | Self::InvalidTarget(_) | Self::MissingArgument(_) | Self::ConflictingArguments(..)
+                | Self::UnknownOutput(_) => 2,
+            Self::NoGame | Self::InputParseError(_) | Self::Io(_) | Self::Decryption
+                | Self::UnknownFormat(_) | Self::SaveDecode(_) | Self::Database(_) => 3,
+            _ => 1
+        }
+    }
 }
 
-#[derive(Debug)]
+#[allow(dead_code)]
+#[derive(Debug, ThisError)]
 pub enum IterationError {
+    #[error("not enough {}", .0.name())]
     NotEnaughtResource(Resource),
+    #[error("not enough free land")]
     NotEnaughtFreeLand,
+    #[error("not enough idle workers")]
     NotEnaughtIdleWorkers,
+    #[error("not enough idle specialists")]
+    NotEnaughtIdleSpecialists,
+    #[error("not enough trained {0:?} specialists")]
+    NotEnaughtTrainedSpecialists(Specialization),
+    #[error("training capacity reached")]
+    TrainingCapacityReached,
+    #[error("not enough workers in the {0:?} job")]
     NotEnaughtWorkersInJob(Job),
+    #[error("population limit reached")]
     PopulationLimitReached,
+    #[error("only allowed in sandbox games")]
+    SandboxOnly,
+    #[error("invalid trade")]
+    InvalidTrade,
+    #[error("the budget of {0} turns is spent")]
+    TurnBudgetSpent(usize),
+    #[error("{} can't be sacrificed", .0.name())]
+    InvalidSacrifice(Resource),
+    #[error("nothing is under construction at position {}", .0 + 1)]
+    NotUnderConstruction(usize),
+    #[error("a turn takes at least one action, no turns and at most one collect")]
+    InvalidTurn,
+}
+
+impl IterationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotEnaughtResource(_) => "not_enough_resource",
+            Self::NotEnaughtFreeLand => "not_enough_free_land",
+            Self::NotEnaughtIdleWorkers => "not_enough_idle_workers",
+            Self::NotEnaughtIdleSpecialists => "not_enough_idle_specialists",
+            Self::NotEnaughtTrainedSpecialists(_) => "not_enough_trained_specialists",
+            Self::TrainingCapacityReached => "training_capacity_reached",
+            Self::NotEnaughtWorkersInJob(_) => "not_enough_workers_in_job",
+            Self::PopulationLimitReached => "population_limit_reached",
+            Self::SandboxOnly => "sandbox_only",
+            Self::InvalidTrade => "invalid_trade",
+            Self::TurnBudgetSpent(_) => "turn_budget_spent",
+            Self::InvalidSacrifice(_) => "invalid_sacrifice",
+            Self::NotUnderConstruction(_) => "not_under_construction",
+            Self::InvalidTurn => "invalid_turn",
+        }
+    }
+
+    /**
+     * Status `cliciv can` exits with when it refuses an action, a different one for
+     * every error so scripts can tell them apart.
+     */
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::NotEnaughtResource(_) => 10,
+            Self::NotEnaughtFreeLand => 11,
+            Self::NotEnaughtIdleWorkers => 12,
+            Self::NotEnaughtIdleSpecialists => 13,
+            Self::NotEnaughtTrainedSpecialists(_) => 14,
+            Self::TrainingCapacityReached => 15,
+            Self::NotEnaughtWorkersInJob(_) => 16,
+            Self::PopulationLimitReached => 17,
+            Self::SandboxOnly => 18,
+            Self::InvalidTrade => 19,
+            Self::TurnBudgetSpent(_) => 20,
+            Self::InvalidSacrifice(_) => 21,
+            Self::NotUnderConstruction(_) => 22,
+            Self::InvalidTurn => 23,
+        }
+    }
 }
 
-#[derive(Debug)]
+#[allow(dead_code)]
+#[derive(Debug, ThisError)]
 pub enum CheckError {
-    HashMismatch,
-    InvalidStateRecreation(IterationError)
+    #[error("hash mismatch at iteration {iteration}: expected {expected}, got {actual}{}", .differences.iter().map(|difference| format!("\niteration {}: {}", iteration, difference)).collect::<String>())]
+    HashMismatch { iteration: usize, expected: Digest, actual: Digest, differences: Vec<Difference> },
+    #[error("{action:?} at iteration {iteration} can't be replayed: {error}")]
+    InvalidStateRecreation { iteration: usize, action: Action, #[source] error: IterationError },
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("the save is not signed")]
+    MissingSignature,
+    #[error("the save is signed by another key")]
+    UnexpectedSigner,
+    #[error("invalid public key")]
+    InvalidPublicKey,
+    #[error("invalid proof")]
+    InvalidProof,
+    #[error("the save was edited by hand")]
+    Tampered,
+    #[error("the snapshot at iteration {0} doesn't match the log")]
+    SnapshotMismatch(usize),
+    #[error("the save was created under other rules, by cliciv {}", .0.engine)]
+    RulesMismatch(Rules),
+    #[error("the state breaks the rules of the game: {}", .0.iter().map(Violation::to_string).collect::<Vec<_>>().join("; "))]
+    InvariantsViolated(Vec<Violation>),
+    #[error("the run is not the daily challenge it claims to be")]
+    NotChallenge,
+    #[error("the claimed score {claimed} doesn't match the run, which scores {actual}")]
+    ScoreMismatch { claimed: u64, actual: u64 }
+}
+
+impl CheckError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::HashMismatch { .. } => "hash_mismatch",
+            Self::InvalidStateRecreation { .. } => "invalid_state_recreation",
+            Self::InvalidSignature => "invalid_signature",
+            Self::MissingSignature => "missing_signature",
+            Self::UnexpectedSigner => "unexpected_signer",
+            Self::InvalidPublicKey => "invalid_public_key",
+            Self::InvalidProof => "invalid_proof",
+            Self::Tampered => "tampered",
+            Self::SnapshotMismatch(_) => "snapshot_mismatch",
+            Self::RulesMismatch(_) => "rules_mismatch",
+            Self::InvariantsViolated(_) => "invariants_violated",
+            Self::NotChallenge => "not_challenge",
+            Self::ScoreMismatch { .. } => "score_mismatch",
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ThisError)]
+pub enum RollbackError {
+    #[error("ironman games can't be rolled back")]
+    Ironman,
+    #[error("iteration {0} hasn't been reached yet")]
+    FutureIteration(usize),
+    #[error("{0}")]
+    Iteration(#[source] IterationError)
+}
+
+impl RollbackError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Ironman => "ironman",
+            Self::FutureIteration(_) => "future_iteration",
+            Self::Iteration(error) => error.code(),
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ThisError)]
+pub enum SyncError {
+    #[error("there is no save at the remote")]
+    MissingRemote,
+    #[error("the remote save belongs to another game")]
+    OtherGame,
+    #[error("the remote save is ahead, at iteration {remote} against {local}: pull first")]
+    RemoteAhead { local: usize, remote: usize },
+    #[error("the saves took different actions, and are at iterations {local} locally and {remote} on the remote")]
+    Diverged { local: usize, remote: usize },
+    #[error("the remote save changed while syncing: try again")]
+    RemoteChanged
+}
+
+impl SyncError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MissingRemote => "missing_remote",
+            Self::OtherGame => "other_game",
+            Self::RemoteAhead { .. } => "remote_ahead",
+            Self::Diverged { .. } => "diverged",
+            Self::RemoteChanged => "remote_changed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::buildings::Buildings;
+    use super::{Error, CheckError};
+
+    #[test]
+    fn errors_have_messages_and_stable_codes() {
+        let error = match State::new(1).apply_action(Action::Build(Buildings::WoodenHut)) {
+            Err(error) => error,
+            Ok(_) => panic!("built a hut without wood")
+        };
+
+        assert!(error.to_string().contains("wood"));
+        assert_eq!(error.code(), "not_enough_resource");
+
+        let error = Error::Check(CheckError::Tampered);
+
+        assert_eq!(error.to_string(), "check failed: the save was edited by hand");
+        assert_eq!(error.code(), "tampered");
+        assert!(std::error::Error::source(&error).is_some());
+    }
 }
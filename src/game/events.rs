@@ -0,0 +1,61 @@
+use super::jobs::Job;
+use super::resources::Resource;
+use super::buildings::Buildings;
+
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    // A worker was moved from the first job into the second one to fight a shortage.
+    WorkerReassigned(Job, Job),
+    // A resource filled its storage, so further production is lost.
+    ResourceCapped(Resource),
+    CitizenRecruited,
+    CitizenBorn,
+    BuildingBuilt(Buildings),
+    // Miners struck gold.
+    GoldStruck,
+}
+
+impl Event {
+    /**
+     * Kind of the event, without what it happened to.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::WorkerReassigned(..) => "worker_reassigned",
+            Self::ResourceCapped(_) => "resource_capped",
+            Self::CitizenRecruited => "citizen_recruited",
+            Self::CitizenBorn => "citizen_born",
+            Self::BuildingBuilt(_) => "building_built",
+            Self::GoldStruck => "gold_struck",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::{Resource, PrimaryResource};
+    use crate::game::buildings::Buildings;
+    use super::Event;
+
+    #[test]
+    fn events_are_recorded() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 250),
+                (Action::Collect(PrimaryResource::Wood), 200),
+                (Action::Collect(PrimaryResource::Stone), 200),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::RecruitCitizen, 1),
+            }).unwrap();
+        let events: Vec<Event> = state.recent_events().iter().map(|(_, event)| event.clone()).collect();
+
+        assert!(events.contains(&Event::ResourceCapped(Resource::Primary(PrimaryResource::Food))));
+        assert!(events.contains(&Event::BuildingBuilt(Buildings::WoodenHut)));
+        assert_eq!(events.last(), Some(&Event::CitizenRecruited));
+        assert_eq!(state.events_since(650), vec!{Event::BuildingBuilt(Buildings::WoodenHut), Event::CitizenRecruited});
+    }
+}
@@ -0,0 +1,93 @@
+use super::state::State;
+use super::actions::Action;
+use super::resources::{Resource, PrimaryResource};
+use super::quantity::Quantity;
+use super::errors::IterationError;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/**
+ * Where a game is headed if it is left idle. Turns are counted from the state the
+ * forecast was made on.
+ */
+#[derive(Serialize)]
+pub struct Forecast {
+    pub turns: u64,
+    pub iteration: usize,
+    // Resources after the last turn, by name.
+    pub resources: BTreeMap<&'static str, Quantity>,
+    // Turn on which each resource first fills its storage, if it does.
+    pub capped: BTreeMap<&'static str, u64>,
+    // Turn on which the food store first runs out, if it does.
+    pub food_runs_out: Option<u64>,
+    pub population: u64
+}
+
+/**
+ * Idles a copy of the game for the given number of turns, keeping track of when each
+ * storage fills and when food runs out.
+ */
+pub fn forecast(state: &State, turns: u64) -> Result<Forecast, IterationError> {
+    let mut state = state.clone();
+    let mut capped = BTreeMap::new();
+    let mut food_runs_out = None;
+    let food = Resource::Primary(PrimaryResource::Food);
+
+    for turn in 1..=turns {
+        let food_before = state.resources.amount(&food);
+        state = state.apply_action(Action::Idle)?;
+
+        for resource in Resource::all() {
+            match state.resources.capacity(&resource) {
+                Some(capacity) if state.resources.amount(&resource) >= capacity => {
+                    capped.entry(resource.name()).or_insert(turn);
+                },
+                _ => ()
+            }
+        }
+
+        let food_after = state.resources.amount(&food);
+        if food_runs_out.is_none() && food_after < food_before && food_after <= Quantity::ZERO {
+            food_runs_out = Some(turn);
+        }
+    }
+
+    Ok(Forecast {
+        turns,
+        iteration: state.iterations(),
+        resources: Resource::all().iter().map(|resource| (resource.name(), state.resources.amount(resource))).collect(),
+        capped,
+        food_runs_out,
+        population: state.population().0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::buildings::Buildings;
+    use crate::game::settings::Settings;
+    use super::forecast;
+
+    #[test]
+    fn forecast_projects_idle_turns_without_changing_the_game() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 5),
+                (Action::Build(Buildings::Tent), 1),
+                (Action::RecruitCitizen, 1)
+            }).unwrap();
+        let forecast = forecast(&state, 30).unwrap();
+        let idled = state.clone().apply_log(vec!{(Action::Idle, 30)}).unwrap();
+
+        assert_eq!(forecast.iteration, state.iterations() + 30);
+        assert_eq!(forecast.resources["food"], idled.resources.food);
+        assert_eq!(forecast.food_runs_out, Some(5));
+        assert!(forecast.capped.is_empty());
+        assert_eq!(state.iterations(), 7);
+    }
+}
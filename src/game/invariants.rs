@@ -0,0 +1,67 @@
+use super::resources::Resource;
+use super::quantity::Quantity;
+
+use thiserror::Error as ThisError;
+
+/**
+ * Rule tying parts of the state together that a state built through actions always
+ * keeps, broken by a state. Matching hashes only prove the state is the one the engine
+ * computes, so a broken invariant points at a bug in the engine or a forged save.
+ */
+#[derive(Clone, Debug, Eq, PartialEq, ThisError)]
+pub enum Violation {
+    #[error("{citizens} citizens live where there is room for {max_population}")]
+    Overpopulated { citizens: u64, max_population: u64 },
+    #[error("buildings take {land_use} land out of {total_land}")]
+    LandOverused { land_use: u64, total_land: u64 },
+    #[error("{amount} {} stored out of a capacity of {capacity}", .resource.name())]
+    OverCapacity { resource: Resource, amount: Quantity, capacity: Quantity },
+    #[error("{amount} {} in store", .0.name(), amount = .1)]
+    NegativeResource(Resource, Quantity),
+    #[error("citizens eat {actual} food per iteration instead of {expected}")]
+    FoodConsumption { expected: Quantity, actual: Quantity },
+}
+
+impl Violation {
+    /**
+     * Whether both break the same invariant, whatever the amounts involved.
+     */
+    pub fn is_same_invariant(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::OverCapacity { resource, .. }, Self::OverCapacity { resource: other_resource, .. }) => resource == other_resource,
+            (Self::NegativeResource(resource, _), Self::NegativeResource(other_resource, _)) => resource == other_resource,
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::{Resource, PrimaryResource, SecondaryResource};
+    use crate::game::buildings::Buildings;
+    use crate::game::settings::Settings;
+    use super::Violation;
+
+    #[test]
+    fn states_keep_their_invariants() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(1, settings)
+            .apply_log(vec!{
+                (Action::Grant(Resource::Primary(PrimaryResource::Wood), 20), 1),
+                (Action::Grant(Resource::Secondary(SecondaryResource::Skins), 2), 1),
+                (Action::Build(Buildings::Tent), 1),
+                (Action::Grant(Resource::Primary(PrimaryResource::Food), 20), 1),
+                (Action::RecruitCitizen, 1)
+            }).unwrap();
+
+        assert!(state.violations().is_empty());
+
+        let mut value = serde_json::to_value(&state).unwrap();
+        value["citizens"]["max_population"] = 0.into();
+        let state: State = serde_json::from_value(value).unwrap();
+
+        assert_eq!(state.violations(), vec!{Violation::Overpopulated { citizens: 1, max_population: 0 }});
+    }
+}
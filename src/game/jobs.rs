@@ -1,28 +1,162 @@
-use super::resources::{Resource, PrimaryResource};
+use super::resources::{Resource, PrimaryResource, SecondaryResource, TertiaryResource, SpecialResource};
+use super::quantity::Quantity;
 
 use serde::{Serialize, Deserialize};
 
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum CitizenClass {
+    Laborer,
+    Specialist,
+    Noble,
+}
+
+impl CitizenClass {
+    pub fn all() -> Vec<Self> {
+        vec!{Self::Laborer, Self::Specialist, Self::Noble}
+    }
+
+    /**
+     * Resources consumed by each citizen of this class on every iteration.
+     */
+    pub fn upkeep(&self) -> Vec<(Resource, Quantity)> {
+        match self {
+            Self::Noble => vec!{
+                (Resource::Special(SpecialResource::Gold), Quantity::hundredths(10)),
+                (Resource::Tertiary(TertiaryResource::Leather), Quantity::hundredths(5))
+            },
+            _ => vec!{}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum Specialization {
+    Scholar,
+    Blacksmith,
+    Cleric,
+}
+
+impl Specialization {
+    pub fn all() -> Vec<Self> {
+        vec!{Self::Scholar, Self::Blacksmith, Self::Cleric}
+    }
+
+    /**
+     * Number of iterations a laborer spends at school before becoming a specialist.
+     */
+    pub fn training_iterations(&self) -> u64 {
+        match self {
+            Self::Scholar => 20,
+            Self::Blacksmith => 10,
+            Self::Cleric => 15
+        }
+    }
+
+    pub fn training_costs(&self) -> Vec<(Resource, Quantity)> {
+        match self {
+            Self::Scholar => vec!{
+                (Resource::Primary(PrimaryResource::Food), Quantity::units(20)),
+                (Resource::Secondary(SecondaryResource::Herbs), Quantity::units(5))
+            },
+            Self::Blacksmith => vec!{
+                (Resource::Primary(PrimaryResource::Food), Quantity::units(20)),
+                (Resource::Secondary(SecondaryResource::Ore), Quantity::units(5))
+            },
+            Self::Cleric => vec!{
+                (Resource::Primary(PrimaryResource::Food), Quantity::units(20)),
+                (Resource::Secondary(SecondaryResource::Herbs), Quantity::units(5))
+            }
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub enum Job {
     Farmer,
     Woodcutter,
     Miner,
+    Scholar,
+    Blacksmith,
+    Cleric,
+    Builder,
+    Surveyor,
 }
 
 impl Job {
-    pub fn get_production_rate(&self) -> f64 {
+    pub fn all() -> Vec<Self> {
+        vec!{Self::Farmer, Self::Woodcutter, Self::Miner, Self::Scholar, Self::Blacksmith, Self::Cleric, Self::Builder, Self::Surveyor}
+    }
+
+    pub fn get_production_rate(&self) -> Quantity {
         match self {
-            Job::Farmer => 1.2,
-            Job::Woodcutter => 0.5,
-            Job::Miner => 0.2
+            Job::Farmer => Quantity::hundredths(120),
+            Job::Woodcutter => Quantity::hundredths(50),
+            Job::Miner => Quantity::hundredths(20),
+            Job::Scholar => Quantity::hundredths(2),
+            Job::Blacksmith => Quantity::hundredths(10),
+            Job::Cleric => Quantity::hundredths(10),
+            // Construction work, in units of building costs.
+            Job::Builder => Quantity::units(10),
+            // Survey effort, each new parcel of land taking more than the one before.
+            Job::Surveyor => Quantity::hundredths(50)
         }
     }
 
-    pub fn get_resource_production(&self) -> Resource {
+    /**
+     * Production lost to crowding when the workers share the job under a soft cap:
+     * the n-th worker only produces cap / (cap + n - 1) of the rate, so the first one
+     * produces it in full and the one right past the cap, half of it.
+     */
+    pub fn crowding_loss(&self, workers: u64, soft_cap: u64) -> Quantity {
+        let (rate, soft_cap) = (self.get_production_rate().as_hundredths(), u64::max(soft_cap, 1) as i64);
+        let produced: i64 = (0..workers as i64).map(|n| rate * soft_cap / (soft_cap + n)).sum();
+
+        Quantity::hundredths(rate * workers as i64 - produced)
+    }
+
+    /**
+     * Water drawn by the job on top of what the worker drinks.
+     */
+    pub fn get_water_consumption(&self) -> Quantity {
         match self {
-            Self::Farmer => Resource::Primary(PrimaryResource::Food),
-            Self::Woodcutter => Resource::Primary(PrimaryResource::Wood),
-            Self::Miner => Resource::Primary(PrimaryResource::Stone)
+            Job::Farmer => Quantity::hundredths(50),
+            _ => Quantity::ZERO
         }
     }
-}
\ No newline at end of file
+
+    pub fn get_class(&self) -> CitizenClass {
+        match self {
+            Job::Farmer | Job::Woodcutter | Job::Miner | Job::Builder | Job::Surveyor => CitizenClass::Laborer,
+            Job::Scholar | Job::Blacksmith | Job::Cleric => CitizenClass::Specialist
+        }
+    }
+
+    /**
+     * Training a citizen needs before being able to take this job, if any.
+     */
+    pub fn get_specialization(&self) -> Option<Specialization> {
+        match self {
+            Job::Scholar => Some(Specialization::Scholar),
+            Job::Blacksmith => Some(Specialization::Blacksmith),
+            Job::Cleric => Some(Specialization::Cleric),
+            _ => None
+        }
+    }
+
+    /**
+     * Resource whose production rate is raised by this job. Scholars don't produce
+     * anything, they raise every primary production multiplier instead, builders put
+     * up buildings and surveyors find more land.
+     */
+    pub fn get_resource_production(&self) -> Option<Resource> {
+        match self {
+            Self::Farmer => Some(Resource::Primary(PrimaryResource::Food)),
+            Self::Woodcutter => Some(Resource::Primary(PrimaryResource::Wood)),
+            Self::Miner => Some(Resource::Primary(PrimaryResource::Stone)),
+            Self::Scholar => None,
+            Self::Blacksmith => Some(Resource::Tertiary(TertiaryResource::Metal)),
+            Self::Cleric => Some(Resource::Tertiary(TertiaryResource::Piety)),
+            Self::Builder | Self::Surveyor => None
+        }
+    }
+}
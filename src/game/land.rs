@@ -2,22 +2,56 @@ use super::buildings::Buildings;
 use super::errors::IterationError;
 use super::state::Context;
 use super::actions::Action;
+use super::quantity::Quantity;
+use super::events::Event;
+use super::jobs::Job;
+use super::resources::Resource;
+use super::rules::LAND_EXPANSION;
+use super::hashing::{Digest, StableHasher};
 
 use serde::{Serialize, Deserialize};
-use std::collections::hash_map::DefaultHasher;
+use rand::Rng;
+use rand_chacha::ChaChaRng;
 use std::hash::Hasher;
 
+// Work put into construction on every iteration even without builders, so that a
+// settlement with no one to assign yet can still house its first citizens.
+const SETTLERS_WORK: u64 = 1;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Land {
     pub total_land: u64,
     pub tents: u64,
     pub wooden_huts: u64,
+    pub houses: u64,
+    pub mansions: u64,
+    pub schools: u64,
+    pub wells: u64,
     pub barns: u64,
+    pub granaries: u64,
     pub wood_stockpiles: u64,
-    pub stone_stockpiles: u64
+    pub stone_stockpiles: u64,
+    pub storehouses: u64,
+    pub vaults: u64,
+    // Buildings being put up, with the work each still needs, in the order builders
+    // work on them.
+    #[serde(default)]
+    pub construction: Vec<(Buildings, u64)>,
+    // Parcels found by surveyors, and the effort put into the next one.
+    #[serde(default)]
+    pub surveyed: u64,
+    #[serde(default)]
+    pub survey: Quantity
 }
 
 impl Land {
+    /**
+     * Starting land for a new game, between 900 and 1100 parcels.
+     */
+    pub fn genesis(rng: &mut ChaChaRng) -> Self {
+        Self { total_land: 900 + rng.gen_range(0..=200), ..Default::default() }
+    }
+
     pub fn free_land(&self) -> u64 {
         self.total_land - self.land_use()
     }
@@ -25,37 +59,216 @@ impl Land {
     pub fn land_use(&self) -> u64 {
         self.tents +
         self.wooden_huts +
+        self.houses +
+        self.mansions +
+        self.schools +
+        self.wells +
         self.barns +
+        self.granaries +
         self.wood_stockpiles +
-        self.stone_stockpiles
+        self.stone_stockpiles +
+        self.storehouses +
+        self.vaults +
+        self.construction.len() as u64
+    }
+
+    /**
+     * Average housing quality weighted by how many citizens each building houses.
+     * A settlement without housing is considered neutral.
+     */
+    pub fn housing_quality(&self) -> Quantity {
+        let housing = [
+            (Buildings::Tent, self.tents),
+            (Buildings::WoodenHut, self.wooden_huts),
+            (Buildings::House, self.houses),
+            (Buildings::Mansion, self.mansions)
+        ];
+
+        let capacity: u64 = housing.iter()
+            .map(|(building, count)| building.population_capacity_increase() * count)
+            .sum();
+
+        if capacity == 0 {
+            return Quantity::ONE;
+        }
+
+        let quality = housing.iter()
+            .fold(Quantity::ZERO, |quality, (building, count)| quality + building.housing_quality() * (building.population_capacity_increase() * count));
+
+        quality / Quantity::units(capacity as i64)
     }
 
     pub fn apply_action(self, action: &Action, ctx: &mut Context) -> Result<Self, IterationError> {
         match action {
-            Action::Build(building) => if self.free_land() > 0 {
-                match building {
-                    Buildings::Tent => Ok(Self { tents: self.tents + 1, ..self }),
-                    Buildings::WoodenHut => Ok(Self { wooden_huts: self.wooden_huts + 1, ..self }),
-                    Buildings::Barn => Ok(Self { barns: self.barns + 1, ..self }),
-                    Buildings::WoodStockpile => Ok(Self { wood_stockpiles: self.wood_stockpiles + 1, ..self }),
-                    Buildings::StoneStockpile => Ok(Self { stone_stockpiles: self.stone_stockpiles + 1, ..self }),
-                }
-            } else {
-                Err(IterationError::NotEnaughtFreeLand)
+            Action::Build(_) if self.free_land() == 0 => Err(IterationError::NotEnaughtFreeLand),
+            Action::Build(building) if ctx.construction => {
+                let mut construction = self.construction;
+                construction.push((building.clone(), building.construction_work()));
+                Ok(Self { construction, ..self })
+            },
+            Action::Build(building) => {
+                ctx.events.push(Event::BuildingBuilt(building.clone()));
+                Ok(self.raise(building))
+            },
+            Action::CancelConstruction(index) if *index < self.construction.len() => {
+                let mut construction = self.construction;
+                construction.remove(*index);
+                Ok(Self { construction, ..self })
+            },
+            Action::ReorderConstruction(from, to) if *from < self.construction.len() && *to < self.construction.len() => {
+                let mut construction = self.construction;
+                let moved = construction.remove(*from);
+                construction.insert(*to, moved);
+                Ok(Self { construction, ..self })
             },
+            Action::CancelConstruction(index) => Err(IterationError::NotUnderConstruction(*index)),
+            Action::ReorderConstruction(from, to) => Err(IterationError::NotUnderConstruction(match *from < self.construction.len() {
+                true => *to,
+                false => *from
+            })),
             _ => Ok(self)
         }
     }
 
-    pub fn hash(&self) -> u64 {
-        let mut hasher = DefaultHasher::default();
+    /**
+     * Finished buildings of the kind on the land.
+     */
+    pub fn count(&self, building: &Buildings) -> u64 {
+        match building {
+            Buildings::Tent => self.tents,
+            Buildings::WoodenHut => self.wooden_huts,
+            Buildings::House => self.houses,
+            Buildings::Mansion => self.mansions,
+            Buildings::School => self.schools,
+            Buildings::Well => self.wells,
+            Buildings::Barn => self.barns,
+            Buildings::Granary => self.granaries,
+            Buildings::WoodStockpile => self.wood_stockpiles,
+            Buildings::StoneStockpile => self.stone_stockpiles,
+            Buildings::Storehouse => self.storehouses,
+            Buildings::Vault => self.vaults,
+        }
+    }
+
+    /**
+     * Counts a finished building.
+     */
+    pub fn raise(self, building: &Buildings) -> Self {
+        match building {
+            Buildings::Tent => Self { tents: self.tents + 1, ..self },
+            Buildings::WoodenHut => Self { wooden_huts: self.wooden_huts + 1, ..self },
+            Buildings::House => Self { houses: self.houses + 1, ..self },
+            Buildings::Mansion => Self { mansions: self.mansions + 1, ..self },
+            Buildings::School => Self { schools: self.schools + 1, ..self },
+            Buildings::Well => Self { wells: self.wells + 1, ..self },
+            Buildings::Barn => Self { barns: self.barns + 1, ..self },
+            Buildings::Granary => Self { granaries: self.granaries + 1, ..self },
+            Buildings::WoodStockpile => Self { wood_stockpiles: self.wood_stockpiles + 1, ..self },
+            Buildings::StoneStockpile => Self { stone_stockpiles: self.stone_stockpiles + 1, ..self },
+            Buildings::Storehouse => Self { storehouses: self.storehouses + 1, ..self },
+            Buildings::Vault => Self { vaults: self.vaults + 1, ..self },
+        }
+    }
+
+    /**
+     * Share of the costs of a building under construction that can be salvaged when
+     * cancelling it, in proportion to the work it still needed.
+     */
+    pub fn salvage(&self, index: usize) -> Vec<(Resource, Quantity)> {
+        match self.construction.get(index) {
+            Some((building, remaining)) => {
+                let work = u64::max(building.construction_work(), 1) as i64;
+
+                building.costs().into_iter()
+                    .map(|(resource, cost)| (resource, Quantity::hundredths(cost.as_hundredths() * *remaining as i64 / work)))
+                    .collect()
+            },
+            None => vec!{}
+        }
+    }
+
+    /**
+     * Survey effort the next parcel of land takes to find, along the land expansion
+     * curve.
+     */
+    pub fn parcel_effort(&self) -> Quantity {
+        LAND_EXPANSION.cost(self.surveyed + 1)
+    }
+
+    /**
+     * Puts the effort of the surveyors into finding land. Each parcel takes more effort
+     * than the one found before it, more so the more land was found.
+     */
+    pub fn explore(self, surveyors: u64) -> Self {
+        let mut land = Self { survey: self.survey + Job::Surveyor.get_production_rate() * surveyors, ..self };
+
+        while land.survey >= land.parcel_effort() {
+            land = Self {
+                total_land: land.total_land + 1,
+                surveyed: land.surveyed + 1,
+                survey: land.survey - land.parcel_effort(),
+                ..land
+            };
+        }
+
+        land
+    }
+
+    /**
+     * Work put into construction on each iteration by the builders and the settlers.
+     */
+    pub fn construction_rate(builders: u64) -> u64 {
+        SETTLERS_WORK + (Job::Builder.get_production_rate() * builders).whole() as u64
+    }
+
+    /**
+     * Puts the work of the builders, and of the settlers, into the buildings under
+     * construction, first in the queue first, and takes out the ones it finishes.
+     */
+    pub fn construct(self, builders: u64) -> (Self, Vec<Buildings>) {
+        let mut work = Self::construction_rate(builders);
+        let mut finished = vec!{};
+        let mut construction = vec!{};
+
+        for (building, remaining) in self.construction.iter().cloned() {
+            match remaining <= work {
+                true => {
+                    work -= remaining;
+                    finished.push(building);
+                },
+                false => {
+                    construction.push((building, remaining - work));
+                    work = 0;
+                }
+            }
+        }
+
+        (Self { construction, ..self }, finished)
+    }
+
+    pub fn hash(&self) -> Digest {
+        let mut hasher = StableHasher::default();
         hasher.write_u64(self.total_land);
         hasher.write_u64(self.tents);
         hasher.write_u64(self.wooden_huts);
+        hasher.write_u64(self.houses);
+        hasher.write_u64(self.mansions);
+        hasher.write_u64(self.schools);
+        hasher.write_u64(self.wells);
         hasher.write_u64(self.barns);
+        hasher.write_u64(self.granaries);
         hasher.write_u64(self.wood_stockpiles);
         hasher.write_u64(self.stone_stockpiles);
-        hasher.finish()
+        hasher.write_u64(self.storehouses);
+        hasher.write_u64(self.vaults);
+        hasher.write_usize(self.construction.len());
+        for (building, remaining) in self.construction.iter() {
+            hasher.write_u8(building.clone() as u8);
+            hasher.write_u64(*remaining);
+        }
+        hasher.write_u64(self.surveyed);
+        hasher.write(&self.survey.to_le_bytes());
+        hasher.digest()
     }
 }
 
@@ -65,9 +278,89 @@ impl Default for Land {
             total_land: 1000,
             tents: 0,
             wooden_huts: 0,
+            houses: 0,
+            mansions: 0,
+            schools: 0,
+            wells: 0,
             barns: 0,
+            granaries: 0,
             wood_stockpiles: 0,
-            stone_stockpiles: 0
+            stone_stockpiles: 0,
+            storehouses: 0,
+            vaults: 0,
+            construction: vec!{},
+            surveyed: 0,
+            survey: Quantity::ZERO
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::quantity::Quantity;
+    use crate::game::buildings::Buildings;
+    use crate::game::jobs::Job;
+    use crate::game::settings::Settings;
+    use crate::game::rules::LAND_EXPANSION;
+
+    #[test]
+    fn builders_put_buildings_up_over_time() {
+        let state = State::with_settings(-141872649309347578469772012024767025949, Settings { construction: true, ..Default::default() })
+            .apply_plan(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 60),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::Idle, 10),
+            }).unwrap();
+
+        assert_eq!(state.land().wooden_huts, 0);
+        assert_eq!(state.land().construction, vec!{(Buildings::WoodenHut, 10)});
+        assert_eq!(state.land().free_land(), state.land().total_land - 1);
+        assert_eq!(state.citizens().max_population, 0);
+
+        let state = state.apply_plan(vec!{(Action::Idle, 15)}).unwrap();
+        assert_eq!(state.land().wooden_huts, 1);
+        assert!(state.land().construction.is_empty());
+        assert_eq!(state.citizens().max_population, 3);
+
+        let state = state.apply_plan(vec!{
+            (Action::RecruitCitizen, 1),
+            (Action::AssignJob(Job::Builder), 1),
+            (Action::Build(Buildings::WoodenHut), 1),
+        }).unwrap();
+        assert_eq!(state.land().construction, vec!{(Buildings::WoodenHut, 10)});
+
+        let state = state.apply_plan(vec!{(Action::Idle, 1)}).unwrap();
+        assert_eq!(state.land().wooden_huts, 2);
+        assert_eq!(state.citizens().max_population, 6);
+        assert!(state.check().is_ok());
+    }
+
+    #[test]
+    fn surveyors_find_land_with_rising_effort() {
+        let state = State::new(-141872649309347578469772012024767025949)
+            .apply_plan(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 60),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Surveyor), 1),
+            }).unwrap();
+        let total_land = state.land().total_land;
+
+        // Parcels take 1, 2.01 and 3.04 effort, 13 iterations of a surveyor in all.
+        let surveyed = state.apply_plan(vec!{(Action::Idle, 12)}).unwrap();
+        assert_eq!(surveyed.land().surveyed, 3);
+        assert_eq!(surveyed.land().total_land, total_land + 3);
+        assert_eq!(surveyed.land().survey, Quantity::hundredths(45));
+        assert_eq!(surveyed.land().parcel_effort(), Quantity::hundredths(409));
+        assert_eq!(LAND_EXPANSION.cost(100), Quantity::hundredths(19801));
+        assert!(surveyed.check().is_ok());
+
+        let idle = surveyed.apply_plan(vec!{(Action::DischargeJob(Job::Surveyor), 1), (Action::Idle, 20)}).unwrap();
+        assert_eq!(idle.land().total_land, total_land + 3);
+    }
+}
@@ -0,0 +1,234 @@
+use super::state::{State, LogEntry};
+use super::errors::Error;
+use super::hashing::{Digest, StableHasher};
+use super::merkle::LogTree;
+
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+// Hex digest, action count, byte count and hex digest, spaced, and the line break.
+const HEADER_LENGTH: u64 = 64 + 1 + 20 + 1 + 20 + 1 + 64 + 1;
+
+/**
+ * First line of a log file: the game, how many actions and bytes of log entries the
+ * file holds after it, and a digest of those actions. A save tells from it alone
+ * whether its log extends the file. It has a fixed width, so it is rewritten in place.
+ */
+struct Header {
+    game: Digest,
+    actions: u64,
+    length: u64,
+    digest: Digest
+}
+
+impl Header {
+    fn new(game: Digest, log: &[LogEntry], length: u64) -> Self {
+        Self { game, actions: State::count_actions(log), length, digest: Self::digest(log) }
+    }
+
+    fn digest(log: &[LogEntry]) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write(&serde_json::to_vec(log).unwrap());
+        hasher.digest()
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end().split(' ');
+        let header = Self {
+            game: Digest::from_hex(fields.next()?)?,
+            actions: fields.next()?.parse().ok()?,
+            length: fields.next()?.parse().ok()?,
+            digest: Digest::from_hex(fields.next()?)?
+        };
+
+        Some(header)
+    }
+
+    fn line(&self) -> String {
+        format!("{} {:020} {:020} {}\n", self.game, self.actions, self.length, self.digest)
+    }
+}
+
+/**
+ * Action log kept apart from the save, so long games don't rewrite it on every save.
+ * Its first line is a header and every other line a JSON log entry. New actions are
+ * appended without reading the file back; it is only rewritten when the log of the
+ * save no longer extends it, as after a rollback.
+ */
+#[derive(Clone, Debug)]
+pub struct LogFile {
+    pub path: PathBuf
+}
+
+impl LogFile {
+    /**
+     * Log of the given game in the file, with the entries split by appends merged
+     * back. Nothing is returned when the file doesn't exist or holds another game.
+     * Files written before headers counted the log have the id of the game alone.
+     */
+    fn read(&self, game_id: Digest) -> Result<Option<Vec<LogEntry>>, Error> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(Error::Io(error))
+        };
+
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(Error::Io)?;
+
+        let length = match Header::parse(&line) {
+            Some(header) if header.game == game_id => header.length,
+            None if Digest::from_hex(line.trim_end()) == Some(game_id) => u64::MAX,
+            _ => return Ok(None)
+        };
+
+        let mut log: Vec<LogEntry> = vec!{};
+
+        for line in reader.take(length).lines() {
+            let (action, count): LogEntry = serde_json::from_str(&line.map_err(Error::Io)?).map_err(Error::InputParseError)?;
+
+            match log.last_mut() {
+                Some(last_entry) if last_entry.0 == action => last_entry.1 += count,
+                _ => log.push((action, count))
+            }
+        }
+
+        Ok(Some(log))
+    }
+
+    /**
+     * Header of the file, if it exists and has one.
+     */
+    fn header(&self) -> Result<Option<Header>, Error> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(Error::Io(error))
+        };
+
+        let mut line = vec!{0; HEADER_LENGTH as usize};
+        match file.read_exact(&mut line) {
+            Ok(()) => Ok(Header::parse(&String::from_utf8_lossy(&line))),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(error) => Err(Error::Io(error))
+        }
+    }
+
+    /**
+     * Puts the log from the file back into a state read from a save without one. A
+     * save that still holds its log keeps it, and it is moved to the file once written.
+     */
+    pub fn attach(&self, state: State) -> Result<State, Error> {
+        if !state.log().is_empty() || state.log_root() == LogTree::default().root() {
+            return Ok(state);
+        }
+
+        match self.read(state.game_id())? {
+            Some(log) => Ok(state.with_log(log)),
+            None => Err(Error::MissingLogFile(self.path.display().to_string()))
+        }
+    }
+
+    /**
+     * Appends the actions of the state the file doesn't have yet, then updates the
+     * header. Anything written past what the header counts, as by a save cut short, is
+     * dropped first. The file is rewritten when it belongs to another game or to a
+     * different history.
+     */
+    pub fn append(&self, state: &State) -> Result<(), Error> {
+        let game_id = state.game_id();
+
+        match self.header()? {
+            Some(header) if header.game == game_id
+                && Header::digest(&State::split_log(state.log(), header.actions).0) == header.digest => {
+                let lines = Self::lines(&State::split_log(state.log(), header.actions).1)?;
+                let mut file = OpenOptions::new().write(true).open(&self.path).map_err(Error::Io)?;
+                file.set_len(HEADER_LENGTH + header.length).map_err(Error::Io)?;
+                file.seek(SeekFrom::End(0)).map_err(Error::Io)?;
+                file.write_all(lines.as_bytes()).map_err(Error::Io)?;
+                file.seek(SeekFrom::Start(0)).map_err(Error::Io)?;
+                file.write_all(Header::new(game_id, state.log(), header.length + lines.len() as u64).line().as_bytes()).map_err(Error::Io)
+            },
+            _ => {
+                let lines = Self::lines(state.log())?;
+                let mut file = File::create(&self.path).map_err(Error::Io)?;
+                file.write_all(Header::new(game_id, state.log(), lines.len() as u64).line().as_bytes()).map_err(Error::Io)?;
+                file.write_all(lines.as_bytes()).map_err(Error::Io)
+            }
+        }
+    }
+
+    fn lines(log: &[LogEntry]) -> Result<String, Error> {
+        let mut lines = String::new();
+
+        for log_entry in log {
+            lines.push_str(&serde_json::to_string(log_entry).map_err(Error::InputParseError)?);
+            lines.push('\n');
+        }
+
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::save_file::SaveFile;
+    use super::LogFile;
+
+    #[test]
+    fn log_file_keeps_log_out_of_save() {
+        let path = std::env::temp_dir().join(format!("cliciv-{}.log", std::process::id()));
+        let save_file = SaveFile { log_file: Some(LogFile { path: path.clone() }), ..Default::default() };
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        save_file.write(&state, &mut vec!{}).unwrap();
+
+        let state = state.apply_log(vec!{(Action::Collect(PrimaryResource::Food), 5)}).unwrap();
+        let mut bytes = vec!{};
+        save_file.write(&state, &mut bytes).unwrap();
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+
+        let mut reader = save_file.clone();
+        let loaded = reader.read(&bytes[..]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(lines, 3);
+        assert!(SaveFile::default().read(&bytes[..]).unwrap().log().is_empty());
+        assert_eq!(loaded.log(), state.log());
+        assert!(loaded.check().is_ok());
+    }
+
+    #[test]
+    fn log_file_appends_after_what_its_header_counts() {
+        let path = std::env::temp_dir().join(format!("cliciv-{}-header.log", std::process::id()));
+        let save_file = SaveFile { log_file: Some(LogFile { path: path.clone() }), ..Default::default() };
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        save_file.write(&state, &mut vec!{}).unwrap();
+
+        // A save cut short after writing to the log, but before counting it in the header.
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        std::io::Write::write_all(&mut file, b"[\"Idle\",3]\n").unwrap();
+
+        let state = state.apply_log(vec!{(Action::Collect(PrimaryResource::Wood), 5)}).unwrap();
+        let mut bytes = vec!{};
+        save_file.write(&state, &mut bytes).unwrap();
+        let loaded = save_file.clone().read(&bytes[..]).unwrap();
+
+        // Files written before headers counted the log are still read.
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, contents.replacen(&contents[64..contents.find('\n').unwrap()], "", 1)).unwrap();
+        let legacy = save_file.clone().read(&bytes[..]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.log(), state.log());
+        assert!(loaded.check().is_ok());
+        assert_eq!(legacy.log(), state.log());
+    }
+}
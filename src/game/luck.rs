@@ -0,0 +1,87 @@
+use super::state::State;
+use super::observer::StateObserver;
+use super::resources::{Resource, SecondaryResource};
+use super::rules::Drop;
+use super::errors::IterationError;
+use super::quantity::Quantity;
+
+use serde::Serialize;
+
+/**
+ * Units of a resource found as a drop while gathering over the whole game, against
+ * what the chances of the drops promised for the draws made.
+ */
+#[derive(Serialize)]
+pub struct Luck {
+    pub resource: &'static str,
+    pub draws: u64,
+    pub expected: Quantity,
+    pub found: u64,
+    // Found beyond what was expected, negative when the run was unlucky.
+    pub surplus: Quantity
+}
+
+impl Luck {
+    fn of(resource: &Resource) -> Self {
+        Self { resource: resource.name(), draws: 0, expected: Quantity::ZERO, found: 0, surplus: Quantity::ZERO }
+    }
+}
+
+struct Tally(Vec<Luck>);
+
+impl StateObserver for Tally {
+    fn on_draw(&mut self, _state: &State, drop: &Drop, draws: u64, found: u64) {
+        let name = drop.resource.name();
+        let index = match self.0.iter().position(|luck| luck.resource == name) {
+            Some(index) => index,
+            None => {
+                self.0.push(Luck::of(&drop.resource));
+                self.0.len() - 1
+            }
+        };
+        let luck = &mut self.0[index];
+
+        luck.draws += draws;
+        luck.expected += drop.chance * (draws * drop.units);
+        luck.found += found * drop.units;
+        luck.surplus = Quantity::units(luck.found as i64) - luck.expected;
+    }
+}
+
+/**
+ * Replays the log counting every roll for a drop. Skins, herbs and ore are always
+ * listed; other resources once dropped. Resources granted or traded for aren't
+ * rolled for, so they don't count.
+ */
+pub fn luck(state: &State) -> Result<Vec<Luck>, IterationError> {
+    let mut tally = Tally(vec!{SecondaryResource::Skins, SecondaryResource::Herbs, SecondaryResource::Ore}.into_iter()
+        .map(|resource| Luck::of(&Resource::Secondary(resource)))
+        .collect());
+    state.replay_observed(&mut tally)?;
+
+    Ok(tally.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::quantity::Quantity;
+    use super::luck;
+
+    #[test]
+    fn luck_counts_every_roll_for_secondary_resources() {
+        let initial = State::new(-141872649309347578469772012024767025949);
+        let state = initial.clone()
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 200), (Action::Collect(PrimaryResource::Wood), 60)}).unwrap();
+        let luck = luck(&state).unwrap();
+
+        assert_eq!(luck.iter().map(|luck| (luck.resource, luck.draws)).collect::<Vec<_>>(), vec!{("skins", 200), ("herbs", 60), ("ore", 0)});
+        assert_eq!(luck[0].expected, Quantity::units(20));
+        assert_eq!(Quantity::units(luck[0].found as i64), state.resources.skins - initial.resources.skins);
+        assert_eq!(Quantity::units(luck[1].found as i64), state.resources.herbs - initial.resources.herbs);
+        assert_eq!(luck[1].surplus, Quantity::units(luck[1].found as i64) - Quantity::units(6));
+        assert_eq!(luck[2].surplus, Quantity::ZERO);
+    }
+}
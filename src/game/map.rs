@@ -0,0 +1,86 @@
+use super::land::Land;
+use super::buildings::Buildings;
+
+use std::iter::repeat_n;
+
+// Parcels drawn on each row of the map unless told otherwise.
+pub const MAP_WIDTH: usize = 50;
+
+const FREE: char = '.';
+const UNDER_CONSTRUCTION: char = '+';
+
+/**
+ * Glyph a parcel taken by the building is drawn with.
+ */
+pub fn glyph(building: &Buildings) -> char {
+    match building {
+        Buildings::Tent => '^',
+        Buildings::WoodenHut => 'h',
+        Buildings::House => 'H',
+        Buildings::Mansion => 'M',
+        Buildings::School => 'S',
+        Buildings::Well => 'o',
+        Buildings::Barn => 'B',
+        Buildings::Granary => 'G',
+        Buildings::WoodStockpile => 'w',
+        Buildings::StoneStockpile => 's',
+        Buildings::Storehouse => 'T',
+        Buildings::Vault => 'V'
+    }
+}
+
+/**
+ * Land drawn as rows of parcels, one glyph each: buildings grouped by kind in the
+ * order they are listed, then the ones under construction, then free land.
+ */
+pub fn map(land: &Land, width: usize) -> Vec<String> {
+    let parcels: Vec<char> = Buildings::all().iter()
+        .flat_map(|building| repeat_n(glyph(building), land.count(building) as usize))
+        .chain(repeat_n(UNDER_CONSTRUCTION, land.construction.len()))
+        .chain(repeat_n(FREE, land.free_land() as usize))
+        .collect();
+
+    parcels.chunks(usize::max(width, 1)).map(|row| row.iter().collect()).collect()
+}
+
+/**
+ * What each glyph on the map stands for, for the buildings on the land.
+ */
+pub fn legend(land: &Land) -> Vec<String> {
+    let mut legend: Vec<String> = Buildings::all().iter()
+        .filter(|building| land.count(building) > 0)
+        .map(|building| format!("{} {:?} ({})", glyph(building), building, land.count(building)))
+        .collect();
+
+    if !land.construction.is_empty() {
+        legend.push(format!("{} under construction ({})", UNDER_CONSTRUCTION, land.construction.len()));
+    }
+    legend.push(format!("{} free ({})", FREE, land.free_land()));
+
+    legend
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::buildings::Buildings;
+    use super::{map, legend};
+
+    #[test]
+    fn maps_draw_every_parcel_of_land() {
+        let state = State::new(-141872649309347578469772012024767025949)
+            .apply_plan(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 60),
+                (Action::Build(Buildings::WoodenHut), 2),
+            }).unwrap();
+        let rows = map(state.land(), 40);
+
+        assert!(rows[0].starts_with("hh.."));
+        assert!(rows.iter().all(|row| row.chars().count() <= 40));
+        assert_eq!(rows.iter().map(|row| row.chars().count() as u64).sum::<u64>(), state.land().total_land);
+        assert_eq!(legend(state.land())[0], "h WoodenHut (2)");
+    }
+}
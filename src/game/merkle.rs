@@ -0,0 +1,197 @@
+use super::actions::Action;
+use super::hashing::{Digest, StableHasher};
+
+use serde::{Serialize, Deserialize};
+use std::hash::Hasher;
+
+// Domain separation between leaves and inner nodes.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/**
+ * Append only Merkle tree over every action taken, kept as the roots of its perfect
+ * subtrees (peaks) so a new action is added in logarithmic time. Its root commits to
+ * the whole log.
+ */
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct LogTree {
+    leaves: u64,
+    peaks: Vec<(u32, Digest)>
+}
+
+impl LogTree {
+    pub fn leaf(index: u64, iteration: usize, action: &Action) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write_u8(LEAF_PREFIX);
+        hasher.write_u64(index);
+        hasher.write_usize(iteration);
+        hasher.write(&serde_json::to_vec(action).unwrap());
+        hasher.digest()
+    }
+
+    fn node(left: &Digest, right: &Digest) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write_u8(NODE_PREFIX);
+        hasher.write(&left.0);
+        hasher.write(&right.0);
+        hasher.digest()
+    }
+
+    /**
+     * Peaks are bagged from right to left into a single root.
+     */
+    fn bag<'a, I: DoubleEndedIterator<Item = &'a Digest>>(peaks: I) -> Digest {
+        let mut peaks = peaks.rev();
+
+        match peaks.next() {
+            Some(last) => peaks.fold(*last, |root, peak| Self::node(peak, &root)),
+            None => Digest::default()
+        }
+    }
+
+    pub fn leaves(&self) -> u64 {
+        self.leaves
+    }
+
+    pub fn push(&mut self, leaf: Digest) {
+        let mut peak = (0, leaf);
+
+        while let Some((height, left)) = self.peaks.last().cloned() {
+            if height != peak.0 {
+                break;
+            }
+
+            self.peaks.pop();
+            peak = (height + 1, Self::node(&left, &peak.1));
+        }
+
+        self.peaks.push(peak);
+        self.leaves += 1;
+    }
+
+    pub fn root(&self) -> Digest {
+        Self::bag(self.peaks.iter().map(|(_, peak)| peak))
+    }
+
+    /**
+     * Inclusion proof of a leaf, built from every leaf of the tree.
+     */
+    pub fn prove(leaves: &[Digest], index: usize) -> Option<InclusionProof> {
+        if index >= leaves.len() {
+            return None;
+        }
+
+        let mut peaks = vec!{};
+        let mut path = vec!{};
+        let mut peak_index = 0;
+        let mut start = 0;
+
+        for height in (0..usize::BITS).rev() {
+            let size = 1 << height;
+
+            if leaves.len() & size == 0 {
+                continue;
+            }
+
+            let mut level = leaves[start..start + size].to_vec();
+
+            if (start..start + size).contains(&index) {
+                peak_index = peaks.len();
+                let mut position = index - start;
+
+                while level.len() > 1 {
+                    path.push(match position % 2 {
+                        0 => (Side::Right, level[position + 1]),
+                        _ => (Side::Left, level[position - 1]),
+                    });
+                    level = level.chunks(2).map(|pair| Self::node(&pair[0], &pair[1])).collect();
+                    position /= 2;
+                }
+            } else {
+                while level.len() > 1 {
+                    level = level.chunks(2).map(|pair| Self::node(&pair[0], &pair[1])).collect();
+                }
+            }
+
+            peaks.push(level[0]);
+            start += size;
+        }
+
+        Some(InclusionProof { leaf: leaves[index], path, peaks, peak_index })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub enum Side {
+    Left,
+    Right
+}
+
+/**
+ * Path from a leaf up to its peak, along with every peak, so anyone holding the log
+ * root can check the leaf belongs to it without the rest of the log.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InclusionProof {
+    pub leaf: Digest,
+    path: Vec<(Side, Digest)>,
+    peaks: Vec<Digest>,
+    peak_index: usize
+}
+
+impl InclusionProof {
+    pub fn verify(&self, root: &Digest) -> bool {
+        let peak = self.path.iter().fold(self.leaf, |node, (side, sibling)| match side {
+            Side::Left => LogTree::node(sibling, &node),
+            Side::Right => LogTree::node(&node, sibling),
+        });
+
+        self.peaks.get(self.peak_index) == Some(&peak) && LogTree::bag(self.peaks.iter()) == *root
+    }
+}
+
+/**
+ * Proof that an action was taken at the given position of the log and iteration. The
+ * root it was built for is included for reference; it has to be checked against a
+ * root known by other means.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ActionProof {
+    pub root: Digest,
+    pub index: u64,
+    pub iteration: usize,
+    pub action: Action,
+    pub proof: InclusionProof
+}
+
+impl ActionProof {
+    pub fn verify(&self, root: &Digest) -> bool {
+        LogTree::leaf(self.index, self.iteration, &self.action) == self.proof.leaf && self.proof.verify(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+
+    #[test]
+    fn log_actions_have_inclusion_proofs() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 6),
+                (Action::Collect(PrimaryResource::Wood), 7),
+            }).unwrap();
+
+        for index in 0..13 {
+            assert!(state.prove(index).unwrap().verify(&state.log_root()));
+        }
+
+        let mut forged_proof = state.prove(3).unwrap();
+        forged_proof.action = Action::Collect(PrimaryResource::Stone);
+
+        assert!(!forged_proof.verify(&state.log_root()));
+        assert!(state.prove(13).is_none());
+    }
+}
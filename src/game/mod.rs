@@ -1,9 +1,40 @@
-pub mod resources;
-pub mod citizens;
-pub mod land;
-pub mod state;
-pub mod utils;
-pub mod errors;
-pub mod actions;
-pub mod buildings;
-pub mod jobs;
\ No newline at end of file
+pub(crate) mod resources;
+pub(crate) mod citizens;
+pub(crate) mod land;
+pub(crate) mod state;
+pub(crate) mod errors;
+pub(crate) mod actions;
+pub(crate) mod buildings;
+pub(crate) mod jobs;
+pub(crate) mod scenarios;
+pub(crate) mod settings;
+pub(crate) mod events;
+pub(crate) mod hashing;
+pub(crate) mod signing;
+pub(crate) mod save_file;
+pub(crate) mod merkle;
+pub(crate) mod quantity;
+pub(crate) mod log_file;
+pub(crate) mod rules;
+pub(crate) mod phrase;
+pub(crate) mod run_proof;
+pub(crate) mod trade;
+pub(crate) mod database;
+pub(crate) mod remote;
+pub(crate) mod observer;
+pub(crate) mod invariants;
+pub(crate) mod simulation;
+pub(crate) mod porcelain;
+pub(crate) mod webhook;
+pub(crate) mod civclicker;
+pub(crate) mod desktop;
+pub(crate) mod forecast;
+pub(crate) mod advisor;
+pub(crate) mod autoplay;
+pub(crate) mod optimizer;
+pub(crate) mod roi;
+pub(crate) mod session;
+pub(crate) mod timeline;
+pub(crate) mod challenge;
+pub(crate) mod map;
+pub(crate) mod luck;
@@ -0,0 +1,93 @@
+use super::state::State;
+use super::actions::Action;
+use super::events::Event;
+use super::rules::Drop;
+
+/**
+ * Callbacks for programs embedding the engine, such as interfaces, bots or servers, to
+ * follow a game as actions are applied instead of comparing states. Every callback
+ * does nothing unless implemented.
+ */
+pub trait StateObserver {
+    /**
+     * Called with the state an action led to.
+     */
+    fn on_action(&mut self, _state: &State, _action: &Action) {}
+
+    /**
+     * Called with the state at the end of every iteration.
+     */
+    fn on_iteration(&mut self, _state: &State) {}
+
+    /**
+     * Called for every event, with the state after the action that caused it.
+     */
+    fn on_event(&mut self, _state: &State, _iteration: usize, _event: &Event) {}
+
+    /**
+     * Called whenever gathering rolls for a drop, with the draws made and the times
+     * it was found, once the action is applied.
+     */
+    fn on_draw(&mut self, _state: &State, _drop: &Drop, _draws: u64, _found: u64) {}
+
+    /**
+     * Called by `State::apply_plan_observed` once each entry of the plan is applied,
+     * with how many are applied out of the whole plan.
+     */
+    fn on_plan_progress(&mut self, _state: &State, _applied: usize, _total: usize) {}
+}
+
+impl StateObserver for () {}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::buildings::Buildings;
+    use crate::game::jobs::Job;
+    use crate::game::settings::Settings;
+    use crate::game::events::Event;
+    use super::StateObserver;
+
+    #[test]
+    fn observers_follow_the_game() {
+        #[derive(Default)]
+        struct Counter {
+            actions: usize,
+            iterations: usize,
+            events: Vec<Event>
+        }
+
+        impl StateObserver for Counter {
+            fn on_action(&mut self, _state: &State, _action: &Action) {
+                self.actions += 1;
+            }
+
+            fn on_iteration(&mut self, _state: &State) {
+                self.iterations += 1;
+            }
+
+            fn on_event(&mut self, _state: &State, _iteration: usize, event: &Event) {
+                self.events.push(event.clone());
+            }
+        }
+
+        let mut counter = Counter::default();
+        let settings = Settings { free_management: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log_observed(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 200),
+                (Action::Collect(PrimaryResource::Stone), 200),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+            }, &mut counter).unwrap();
+
+        assert_eq!(counter.actions, 603);
+        assert_eq!(counter.iterations, 602);
+        assert_eq!(counter.events.last(), Some(&Event::CitizenRecruited));
+        assert_eq!(counter.events.len(), state.recent_events().len());
+    }
+}
@@ -0,0 +1,277 @@
+use super::state::{State, LogEntry};
+use super::actions::Action;
+use super::buildings::Buildings;
+use super::jobs::Job;
+use super::resources::Resource;
+use super::quantity::Quantity;
+
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+// Laborer jobs the search moves workers between.
+const JOBS: [Job; 3] = [Job::Farmer, Job::Woodcutter, Job::Miner];
+
+// Bound on the climbing steps, each moving one worker or adding one building.
+const MAX_STEPS: usize = 200;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Metric {
+    Resource(Resource),
+    Population,
+}
+
+impl Metric {
+    fn measure(&self, state: &State) -> Quantity {
+        match self {
+            Self::Resource(resource) => state.resources.amount(resource),
+            Self::Population => Quantity::units(state.population().0 as i64)
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Resource(resource) => resource.name(),
+            Self::Population => "population"
+        }
+    }
+
+    /**
+     * Buildings worth trying for the metric: the ones storing the resource, or the
+     * ones housing people.
+     */
+    fn buildings(&self) -> Vec<Buildings> {
+        Buildings::all().into_iter().filter(|building| match self {
+            Self::Resource(resource) => building.resource_storage_increase().iter().any(|(stored, _)| stored == resource),
+            Self::Population => building.population_capacity_increase() > 0
+        }).collect()
+    }
+}
+
+/**
+ * What an optimization aims for, written as `maximize stone in 200 turns` or
+ * `minimize food in 50 turns`. Besides resources, `population` can be aimed for.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Target {
+    pub maximize: bool,
+    pub metric: Metric,
+    pub turns: u64
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(target: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = target.split_whitespace().collect();
+
+        match words.as_slice() {
+            [goal, metric, "in", turns, "turn" | "turns"] => Ok(Self {
+                maximize: match *goal {
+                    "maximize" => true,
+                    "minimize" => false,
+                    _ => return Err(target.to_string())
+                },
+                metric: match *metric {
+                    "population" => Metric::Population,
+                    metric => Metric::Resource(metric.parse().map_err(|_| target.to_string())?)
+                },
+                turns: turns.parse().map_err(|_| target.to_string())?
+            }),
+            _ => Err(target.to_string())
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} in {} turns", if self.maximize { "maximize" } else { "minimize" }, self.metric.name(), self.turns)
+    }
+}
+
+/**
+ * Laborers on each job, and buildings to put up, at the start of a plan.
+ */
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Allocation {
+    pub idle: u64,
+    pub farmers: u64,
+    pub woodcutters: u64,
+    pub miners: u64,
+    pub builds: Vec<Buildings>
+}
+
+impl Allocation {
+    fn of(state: &State) -> Self {
+        let citizens = state.citizens();
+
+        Self { idle: citizens.idle, farmers: citizens.farmers, woodcutters: citizens.woodcutters, miners: citizens.miners, builds: vec!{} }
+    }
+
+    fn workers(&self, job: Option<&Job>) -> u64 {
+        match job {
+            None => self.idle,
+            Some(Job::Farmer) => self.farmers,
+            Some(Job::Woodcutter) => self.woodcutters,
+            Some(Job::Miner) => self.miners,
+            Some(_) => 0
+        }
+    }
+
+    fn workers_mut(&mut self, job: Option<&Job>) -> &mut u64 {
+        match job {
+            Some(Job::Farmer) => &mut self.farmers,
+            Some(Job::Woodcutter) => &mut self.woodcutters,
+            Some(Job::Miner) => &mut self.miners,
+            _ => &mut self.idle
+        }
+    }
+
+    /**
+     * Allocations one step away: one worker moved between jobs or out of idleness,
+     * or one more building while under the limit.
+     */
+    fn neighbours(&self, buildings: &[Buildings], max_builds: usize) -> Vec<Self> {
+        let places: Vec<Option<&Job>> = std::iter::once(None).chain(JOBS.iter().map(Some)).collect();
+        let mut neighbours = vec!{};
+
+        for from in places.iter() {
+            for to in places.iter().filter(|to| *to != from) {
+                if self.workers(*from) > 0 {
+                    let mut neighbour = self.clone();
+                    *neighbour.workers_mut(*from) -= 1;
+                    *neighbour.workers_mut(*to) += 1;
+                    neighbours.push(neighbour);
+                }
+            }
+        }
+
+        if self.builds.len() < max_builds {
+            for building in buildings {
+                let mut neighbour = self.clone();
+                neighbour.builds.push(building.clone());
+                neighbours.push(neighbour);
+            }
+        }
+
+        neighbours
+    }
+
+    /**
+     * Actions taking the game from its current allocation to this one, then idling
+     * for the rest of the turns.
+     */
+    fn plan(&self, current: &Self, turns: u64) -> Option<Vec<LogEntry>> {
+        let mut plan: Vec<LogEntry> = self.builds.iter().map(|building| (Action::Build(building.clone()), 1)).collect();
+
+        for job in JOBS.iter() {
+            let (from, to) = (current.workers(Some(job)), self.workers(Some(job)));
+            if from > to {
+                plan.push((Action::DischargeJob(job.clone()), from - to));
+            }
+        }
+
+        for job in JOBS.iter() {
+            let (from, to) = (current.workers(Some(job)), self.workers(Some(job)));
+            if to > from {
+                plan.push((Action::AssignJob(job.clone()), to - from));
+            }
+        }
+
+        let actions = State::count_actions(&plan);
+        if actions > turns {
+            return None;
+        }
+
+        if actions < turns {
+            plan.push((Action::Idle, turns - actions));
+        }
+
+        Some(plan)
+    }
+}
+
+/**
+ * Best allocation found for a target, with the plan reaching it.
+ */
+#[derive(Serialize)]
+pub struct Optimization {
+    pub target: String,
+    // The metric if the game is left idle instead.
+    pub baseline: Quantity,
+    pub value: Quantity,
+    pub allocation: Allocation,
+    pub plan: Vec<LogEntry>
+}
+
+/**
+ * Climbs from the current allocation of laborers, and up to the given number of
+ * buildings, taking the best neighbouring allocation while any improves on the
+ * target. Each allocation is scored by playing its plan out on a copy of the game,
+ * so the search finds a local optimum, not necessarily the best one.
+ */
+pub fn optimize(state: &State, target: &Target, max_builds: usize) -> Optimization {
+    let current = Allocation::of(state);
+    let buildings = target.metric.buildings();
+    let score = |allocation: &Allocation| -> Option<(Quantity, Vec<LogEntry>)> {
+        let plan = allocation.plan(&current, target.turns)?;
+        let value = target.metric.measure(&state.apply_plan(plan.clone()).ok()?);
+        Some((value, plan))
+    };
+    let better = |value: Quantity, than: Quantity| match target.maximize {
+        true => value > than,
+        false => value < than
+    };
+
+    let (baseline, baseline_plan) = score(&current).unwrap_or_else(|| (target.metric.measure(state), vec!{}));
+    let (mut allocation, mut value, mut plan) = (current.clone(), baseline, baseline_plan);
+
+    for _ in 0..MAX_STEPS {
+        let mut best: Option<(Allocation, Quantity, Vec<LogEntry>)> = None;
+
+        for neighbour in allocation.neighbours(&buildings, max_builds) {
+            if let Some((neighbour_value, neighbour_plan)) = score(&neighbour) {
+                if best.as_ref().is_none_or(|(_, best_value, _)| better(neighbour_value, *best_value)) {
+                    best = Some((neighbour, neighbour_value, neighbour_plan));
+                }
+            }
+        }
+
+        match best {
+            Some((neighbour, neighbour_value, neighbour_plan)) if better(neighbour_value, value) => {
+                allocation = neighbour;
+                value = neighbour_value;
+                plan = neighbour_plan;
+            },
+            _ => break
+        }
+    }
+
+    Optimization { target: target.to_string(), baseline, value, allocation, plan }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::buildings::Buildings;
+    use crate::game::settings::Settings;
+    use super::{optimize, Target};
+
+    #[test]
+    fn optimizer_puts_workers_on_the_target() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{(Action::Build(Buildings::Tent), 3), (Action::RecruitCitizen, 3)}).unwrap();
+        let target: Target = "maximize wood in 50 turns".parse().unwrap();
+        let optimization = optimize(&state, &target, 0);
+        let played = state.apply_plan(optimization.plan.clone()).unwrap();
+
+        assert_eq!(optimization.allocation.woodcutters, 3);
+        assert!(optimization.value > optimization.baseline);
+        assert_eq!(played.resources.wood, optimization.value);
+        assert_eq!(played.iterations(), state.iterations() + 50);
+        assert!("maximize rocks in 50 turns".parse::<Target>().is_err());
+        assert!("maximize wood soon".parse::<Target>().is_err());
+    }
+}
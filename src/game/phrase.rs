@@ -0,0 +1,37 @@
+use bip39::Mnemonic;
+use std::convert::TryInto;
+
+/**
+ * Seed written as the twelve words of a BIP39 phrase, easier to share than 39 digits.
+ */
+pub fn seed_phrase(seed: i128) -> String {
+    Mnemonic::from_entropy(&seed.to_be_bytes()).unwrap().to_string()
+}
+
+/**
+ * Seed behind a twelve word phrase. Words are case insensitive and may be separated by
+ * any whitespace.
+ */
+pub fn parse_seed_phrase(phrase: &str) -> Option<i128> {
+    let words: Vec<String> = phrase.split_whitespace().map(str::to_lowercase).collect();
+    let entropy = Mnemonic::parse_normalized(&words.join(" ")).ok()?.to_entropy();
+
+    Some(i128::from_be_bytes(entropy.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{seed_phrase, parse_seed_phrase};
+
+    #[test]
+    fn seed_phrases_round_trip() {
+        for seed in [43932030939219715774207308070970463251, -7, 0] {
+            let phrase = seed_phrase(seed);
+
+            assert_eq!(phrase.split(' ').count(), 12);
+            assert_eq!(parse_seed_phrase(&phrase.to_uppercase()), Some(seed));
+        }
+
+        assert_eq!(parse_seed_phrase("not a seed phrase"), None);
+    }
+}
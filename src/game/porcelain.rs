@@ -0,0 +1,66 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/**
+ * Version of the porcelain format, given on its first line as `porcelain=<version>`.
+ * Keys are only ever added within a version; renaming or removing one, or changing
+ * how values are written, takes a new version.
+ */
+pub const PORCELAIN_VERSION: u32 = 1;
+
+/**
+ * Porcelain output, for scripts: one `key=value` line per field, sorted by key after
+ * the version line. Nested fields are named by their path, like `resources.food` or
+ * `citizens.trainees[0]`. Amounts are written with two decimals, text as is and
+ * missing values as nothing.
+ */
+pub fn porcelain<T: Serialize>(value: &T) -> Result<String, serde_json::Error> {
+    let mut fields = BTreeMap::new();
+    flatten(String::new(), &serde_json::to_value(value)?, &mut fields);
+
+    Ok(lines(&fields))
+}
+
+/**
+ * Writes fields already flattened in the porcelain format.
+ */
+pub fn lines(fields: &BTreeMap<String, String>) -> String {
+    let mut lines = format!("porcelain={}\n", PORCELAIN_VERSION);
+
+    for (key, value) in fields {
+        lines.push_str(&format!("{}={}\n", key, value));
+    }
+
+    lines
+}
+
+/**
+ * Adds the leaves of a serialized value to the fields, named by their path. Quantities
+ * are serialized as floats and shown with their two decimals.
+ */
+pub fn flatten(path: String, value: &serde_json::Value, fields: &mut BTreeMap<String, String>) {
+    match value {
+        serde_json::Value::Object(map) => for (key, value) in map {
+            let key = match path.is_empty() {
+                true => key.clone(),
+                false => format!("{}.{}", path, key)
+            };
+            flatten(key, value, fields);
+        },
+        serde_json::Value::Array(items) => for (index, item) in items.iter().enumerate() {
+            flatten(format!("{}[{}]", path, index), item, fields);
+        },
+        serde_json::Value::Number(number) if number.is_f64() => {
+            fields.insert(path, format!("{:.2}", number.as_f64().unwrap_or_default()));
+        },
+        serde_json::Value::String(text) => {
+            fields.insert(path, text.clone());
+        },
+        serde_json::Value::Null => {
+            fields.insert(path, String::new());
+        },
+        value => {
+            fields.insert(path, value.to_string());
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::ops::{Add, AddAssign, Sub, SubAssign, Mul, Div};
+
+/**
+ * Fixed point amount with two decimals, stored as an integer count of hundredths so
+ * the game math gives the same results on every platform. Products and quotients are
+ * rounded half away from zero. It is serialized as a decimal number.
+ */
+#[derive(Clone, Copy, Default, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Quantity(i64);
+
+impl Quantity {
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(100);
+
+    pub const fn units(units: i64) -> Self {
+        Self(units * 100)
+    }
+
+    pub const fn hundredths(hundredths: i64) -> Self {
+        Self(hundredths)
+    }
+
+    pub fn as_hundredths(&self) -> i64 {
+        self.0
+    }
+
+    /**
+     * Whole units, truncated towards zero.
+     */
+    pub fn whole(&self) -> i64 {
+        self.0 / 100
+    }
+
+    pub fn to_le_bytes(self) -> [u8; 8] {
+        self.0.to_le_bytes()
+    }
+
+    fn round_div(numerator: i128, denominator: i128) -> i64 {
+        let half = denominator.abs() / 2;
+        let rounded = if (numerator < 0) == (denominator < 0) {
+            (numerator.abs() + half) / denominator.abs()
+        } else {
+            -((numerator.abs() + half) / denominator.abs())
+        };
+
+        rounded as i64
+    }
+}
+
+impl Add for Quantity {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Quantity {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Quantity {
+    fn sub_assign(&mut self, other: Self) {
+        self.0 -= other.0;
+    }
+}
+
+impl Mul for Quantity {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self(Self::round_div(self.0 as i128 * other.0 as i128, 100))
+    }
+}
+
+impl Mul<u64> for Quantity {
+    type Output = Self;
+
+    fn mul(self, count: u64) -> Self {
+        Self(self.0 * count as i64)
+    }
+}
+
+impl Div for Quantity {
+    type Output = Self;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn div(self, other: Self) -> Self {
+        Self(Self::round_div(self.0 as i128 * 100, other.0 as i128))
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        write!(f, "{}{}.{:02}", sign, self.0.abs() / 100, self.0.abs() % 100)
+    }
+}
+
+impl Serialize for Quantity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.0 as f64 / 100.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let amount = f64::deserialize(deserializer)?;
+        Ok(Self((amount * 100.0).round() as i64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Quantity;
+
+    #[test]
+    fn quantities_round_half_away_from_zero() {
+        assert_eq!(Quantity::hundredths(5) * Quantity::hundredths(10), Quantity::hundredths(1));
+        assert_eq!(Quantity::hundredths(-5) * Quantity::hundredths(10), Quantity::hundredths(-1));
+        assert_eq!(Quantity::hundredths(-5) * Quantity::hundredths(9), Quantity::ZERO);
+        assert_eq!(Quantity::units(-1) / Quantity::units(3), Quantity::hundredths(-33));
+        assert_eq!(Quantity::units(-2) / Quantity::units(3), Quantity::hundredths(-67));
+        assert_eq!(Quantity::hundredths(1) / Quantity::units(-2), Quantity::hundredths(-1));
+        assert_eq!(Quantity::hundredths(-1) / Quantity::units(-2), Quantity::hundredths(1));
+    }
+
+    #[test]
+    fn negative_quantities_display_their_sign() {
+        assert_eq!(Quantity::hundredths(-5).to_string(), "-0.05");
+        assert_eq!(Quantity::hundredths(-1234).to_string(), "-12.34");
+        assert_eq!(Quantity::units(-3).to_string(), "-3.00");
+        assert_eq!(Quantity::hundredths(7).to_string(), "0.07");
+    }
+
+    #[test]
+    fn quantities_round_trip_through_floats() {
+        for hundredths in [-1234, -1, 0, 7, 29, 115, 12345678] {
+            let quantity = Quantity::hundredths(hundredths);
+            let json = serde_json::to_string(&quantity).unwrap();
+
+            assert_eq!(serde_json::from_str::<Quantity>(&json).unwrap(), quantity);
+        }
+
+        assert_eq!(serde_json::to_string(&Quantity::hundredths(-1234)).unwrap(), "-12.34");
+        assert_eq!(serde_json::from_str::<Quantity>("1.15").unwrap(), Quantity::hundredths(115));
+        assert_eq!(serde_json::from_str::<Quantity>("0.005").unwrap(), Quantity::hundredths(1));
+    }
+}
@@ -0,0 +1,151 @@
+use super::errors::{Error, SyncError};
+use super::hashing::StableHasher;
+
+use std::fs::File;
+use std::hash::Hasher;
+use std::io::{ErrorKind, Read};
+
+/**
+ * Version of the remote save when it was fetched, which it must still be at for the
+ * save to be replaced: none when there was no save, or its ETag over HTTP, or a digest
+ * of its contents on a local path. HTTP servers that send no ETag can't be guarded.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Version {
+    Missing,
+    Tagged(String),
+    Untagged
+}
+
+/**
+ * Place a save is shared from between machines. It is either an HTTP URL, such as a
+ * WebDAV folder, taking GET requests and conditional PUT requests, or a local path,
+ * with or without `file://`. Credentials can be given in the URL, as basic auth; S3
+ * buckets, which want signed requests, are not supported.
+ */
+#[derive(Clone, Debug)]
+pub struct Remote {
+    pub url: String
+}
+
+impl Remote {
+    fn is_http(&self) -> bool {
+        self.url.starts_with("http://") || self.url.starts_with("https://")
+    }
+
+    fn path(&self) -> &str {
+        self.url.strip_prefix("file://").unwrap_or(&self.url)
+    }
+
+    fn digest(bytes: &[u8]) -> Version {
+        let mut hasher = StableHasher::default();
+        hasher.write(bytes);
+        Version::Tagged(hasher.digest().to_hex())
+    }
+
+    /**
+     * Contents of the remote save, or nothing when there is none yet, along with the
+     * version it is at.
+     */
+    pub fn fetch(&self) -> Result<(Option<Vec<u8>>, Version), Error> {
+        let mut bytes = vec!{};
+
+        if self.is_http() {
+            let version = match ureq::get(&self.url).call() {
+                Ok(response) => {
+                    let version = response.header("ETag").map_or(Version::Untagged, |tag| Version::Tagged(tag.to_string()));
+                    response.into_reader().read_to_end(&mut bytes).map_err(Error::Io)?;
+                    version
+                },
+                Err(ureq::Error::Status(404, _)) => return Ok((None, Version::Missing)),
+                Err(error) => return Err(Error::Remote(error.to_string()))
+            };
+
+            Ok((Some(bytes), version))
+        } else {
+            match File::open(self.path()) {
+                Ok(mut file) => file.read_to_end(&mut bytes).map_err(Error::Io)?,
+                Err(error) if error.kind() == ErrorKind::NotFound => return Ok((None, Version::Missing)),
+                Err(error) => return Err(Error::Io(error))
+            };
+
+            Ok((Some(bytes.clone()), Self::digest(&bytes)))
+        }
+    }
+
+    /**
+     * Replaces the remote save, as long as it is still at the version fetched. Over
+     * HTTP the server checks it, with `If-Match` or `If-None-Match`. Local saves are
+     * written next to the remote one and moved in place once it is found unchanged.
+     */
+    pub fn store(&self, bytes: &[u8], version: &Version) -> Result<(), Error> {
+        if self.is_http() {
+            let request = match version {
+                Version::Missing => ureq::put(&self.url).set("If-None-Match", "*"),
+                Version::Tagged(tag) => ureq::put(&self.url).set("If-Match", tag),
+                Version::Untagged => ureq::put(&self.url)
+            };
+
+            return match request.send_bytes(bytes) {
+                Ok(_) => Ok(()),
+                Err(ureq::Error::Status(412, _)) => Err(Error::Sync(SyncError::RemoteChanged)),
+                Err(error) => Err(Error::Remote(error.to_string()))
+            };
+        }
+
+        let temporary = format!("{}.tmp", self.path());
+        std::fs::write(&temporary, bytes).map_err(Error::Io)?;
+
+        let current = match std::fs::read(self.path()) {
+            Ok(current) => Self::digest(&current),
+            Err(error) if error.kind() == ErrorKind::NotFound => Version::Missing,
+            Err(error) => return Err(Error::Io(error))
+        };
+
+        match current == *version {
+            true => std::fs::rename(&temporary, self.path()).map_err(Error::Io),
+            false => {
+                std::fs::remove_file(&temporary).map_err(Error::Io)?;
+                Err(Error::Sync(SyncError::RemoteChanged))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::errors::{Error, SyncError};
+    use crate::game::save_file::SaveFile;
+    use super::{Remote, Version};
+
+    #[test]
+    fn synced_saves_only_replace_ones_they_extend() {
+        let path = std::env::temp_dir().join(format!("cliciv-{}-remote.save", std::process::id()));
+        let remote = Remote { url: format!("file://{}", path.display()) };
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        let mut bytes = vec!{};
+        SaveFile::default().write(&state, &mut bytes).unwrap();
+        remote.store(&bytes, &Version::Missing).unwrap();
+
+        let (fetched, version) = remote.fetch().unwrap();
+        let pulled = SaveFile::default().read(&fetched.unwrap()[..]).unwrap();
+        let overwritten = remote.store(&bytes, &Version::Missing);
+        remote.store(&bytes[1..], &version).unwrap();
+        let stale = remote.store(&bytes, &version);
+        std::fs::remove_file(&path).unwrap();
+        let ahead = state.clone().apply_log(vec!{(Action::Collect(PrimaryResource::Wood), 5)}).unwrap();
+        let diverged = state.clone().apply_log(vec!{(Action::Collect(PrimaryResource::Stone), 5)}).unwrap();
+
+        assert_eq!(pulled.hash(), state.hash());
+        assert!(ahead.extends(&pulled));
+        assert!(!pulled.extends(&ahead));
+        assert!(!diverged.extends(&ahead) && !ahead.extends(&diverged));
+        assert!(matches!(overwritten, Err(Error::Sync(SyncError::RemoteChanged))));
+        assert!(matches!(stale, Err(Error::Sync(SyncError::RemoteChanged))));
+        assert!(remote.fetch().unwrap().0.is_none());
+    }
+}
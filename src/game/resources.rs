@@ -1,14 +1,21 @@
-use super::utils::{AsBytes, RoundTo2};
+use super::quantity::Quantity;
 use super::errors::IterationError;
 use super::actions::Action;
 use super::state::Context;
+use super::citizens::Citizens;
+use super::jobs::{CitizenClass, Job};
+use super::buildings::Buildings;
+use super::hashing::{Digest, StableHasher};
+use super::events::Event;
 
 use serde::{Serialize, Deserialize};
+use rand::Rng;
 use rand::distributions::{Distribution, Bernoulli};
-use std::collections::hash_map::DefaultHasher;
+use rand_chacha::ChaChaRng;
 use std::hash::Hasher;
+use std::str::FromStr;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub enum Resource {
     Primary(PrimaryResource),
     Secondary(SecondaryResource),
@@ -16,6 +23,77 @@ pub enum Resource {
     Special(SpecialResource),
 }
 
+impl Resource {
+    pub fn all() -> Vec<Self> {
+        vec!{
+            Self::Primary(PrimaryResource::Food),
+            Self::Primary(PrimaryResource::Wood),
+            Self::Primary(PrimaryResource::Stone),
+            Self::Secondary(SecondaryResource::Skins),
+            Self::Secondary(SecondaryResource::Herbs),
+            Self::Secondary(SecondaryResource::Ore),
+            Self::Tertiary(TertiaryResource::Leather),
+            Self::Tertiary(TertiaryResource::Piety),
+            Self::Tertiary(TertiaryResource::Metal),
+            Self::Special(SpecialResource::Gold),
+            Self::Special(SpecialResource::Corpses),
+            Self::Special(SpecialResource::Gems),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Primary(PrimaryResource::Food) => "food",
+            Self::Primary(PrimaryResource::Wood) => "wood",
+            Self::Primary(PrimaryResource::Stone) => "stone",
+            Self::Secondary(SecondaryResource::Skins) => "skins",
+            Self::Secondary(SecondaryResource::Herbs) => "herbs",
+            Self::Secondary(SecondaryResource::Ore) => "ore",
+            Self::Tertiary(TertiaryResource::Leather) => "leather",
+            Self::Tertiary(TertiaryResource::Piety) => "piety",
+            Self::Tertiary(TertiaryResource::Metal) => "metal",
+            Self::Special(SpecialResource::Gold) => "gold",
+            Self::Special(SpecialResource::Corpses) => "corpses",
+            Self::Special(SpecialResource::Gems) => "gems",
+        }
+    }
+
+    /**
+     * Piety gained for every unit of the resource sacrificed, if the gods accept it.
+     */
+    pub fn sacrifice_piety(&self) -> Option<Quantity> {
+        match self {
+            Self::Primary(PrimaryResource::Food) => Some(Quantity::hundredths(5)),
+            Self::Special(SpecialResource::Gold) => Some(Quantity::hundredths(50)),
+            Self::Special(SpecialResource::Corpses) => Some(Quantity::ONE),
+            Self::Special(SpecialResource::Gems) => Some(Quantity::units(10)),
+            _ => None
+        }
+    }
+}
+
+impl FromStr for Resource {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "food" => Ok(Self::Primary(PrimaryResource::Food)),
+            "wood" => Ok(Self::Primary(PrimaryResource::Wood)),
+            "stone" => Ok(Self::Primary(PrimaryResource::Stone)),
+            "skins" => Ok(Self::Secondary(SecondaryResource::Skins)),
+            "herbs" => Ok(Self::Secondary(SecondaryResource::Herbs)),
+            "ore" => Ok(Self::Secondary(SecondaryResource::Ore)),
+            "leather" => Ok(Self::Tertiary(TertiaryResource::Leather)),
+            "piety" => Ok(Self::Tertiary(TertiaryResource::Piety)),
+            "metal" => Ok(Self::Tertiary(TertiaryResource::Metal)),
+            "gold" => Ok(Self::Special(SpecialResource::Gold)),
+            "corpses" => Ok(Self::Special(SpecialResource::Corpses)),
+            "gems" => Ok(Self::Special(SpecialResource::Gems)),
+            _ => Err(name.to_string()),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub enum PrimaryResource {
     Food,
@@ -23,288 +101,679 @@ pub enum PrimaryResource {
     Stone,
 }
 
-impl PrimaryResource {
-    fn get_secondary_resource(&self) -> SecondaryResource {
-        match self {
-            Self::Food => SecondaryResource::Skins,
-            Self::Wood => SecondaryResource::Herbs,
-            Self::Stone => SecondaryResource::Ore,
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub enum SecondaryResource {
     Skins,
     Herbs,
     Ore,
 }
 
-impl SecondaryResource {
-    fn get_distribution(&self) -> Bernoulli {
-        match self {
-            Self::Skins => Bernoulli::from_ratio(1, 10).unwrap(),
-            Self::Herbs => Bernoulli::from_ratio(1, 10).unwrap(),
-            Self::Ore => Bernoulli::from_ratio(1, 10).unwrap(),
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub enum TertiaryResource {
     Leather,
     Piety,
     Metal,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
 pub enum SpecialResource {
     Gold,
-    Corpses
+    Corpses,
+    Gems
 }
 
+// Food paid to recruit a citizen.
+pub const RECRUIT_COST: Quantity = Quantity::units(20);
+
+// Units of a primary resource bartered away for each unit of another received.
+pub const BARTER_RATIO: u64 = 3;
+
+// Piety clerics spend on every iteration they read the omens of, and most iterations
+// they can read ahead.
+pub const OMEN_PIETY: Quantity = Quantity::hundredths(10);
+pub const MAX_OMENS: u64 = 50;
+
+// Draws in a row a drop can be missed in before it is found for sure, when pity is
+// played with.
+pub const PITY_DRAWS: u64 = 20;
+
+// Chance of each miner striking gold on an iteration, and the gold a strike yields,
+// when gold strikes are played with.
+pub const GOLD_STRIKE_CHANCE: Quantity = Quantity::hundredths(1);
+pub const GOLD_STRIKE: Quantity = Quantity::units(5);
+
+// Gems that can be kept, as no building stores them, and their chance of turning up
+// with each unit of stone mined when they are played with.
+pub const MAX_GEMS: Quantity = Quantity::units(10);
+pub const GEM_CHANCE: Quantity = Quantity::hundredths(1);
+
+// Food that can be stored without spoiling, regardless of barns and granaries.
+const FRESH_FOOD_STORAGE: Quantity = Quantity::units(200);
+
 // TODO: Hacer que las propedades no sean públicas.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Resources {
     // Main
-    pub food: f64,
-    pub food_cons_rate: f64,
-    pub food_prod_rate: f64,
-    pub food_prod_rate_multiplier: f64,
-    pub max_food: f64,
-    pub wood: f64,
-    pub wood_prod_rate: f64,
-    pub wood_prod_rate_multiplier: f64,
-    pub max_wood: f64,
-    pub stone: f64,
-    pub stone_prod_rate: f64,
-    pub stone_prod_rate_multiplier: f64,
-    pub max_stone: f64,
+    pub food: Quantity,
+    pub food_cons_rate: Quantity,
+    pub food_prod_rate: Quantity,
+    pub food_prod_rate_multiplier: Quantity,
+    pub max_food: Quantity,
+    pub food_spoilage_percent: Quantity,
+    pub wood: Quantity,
+    pub wood_prod_rate: Quantity,
+    pub wood_prod_rate_multiplier: Quantity,
+    pub max_wood: Quantity,
+    pub stone: Quantity,
+    pub stone_prod_rate: Quantity,
+    pub stone_prod_rate_multiplier: Quantity,
+    pub max_stone: Quantity,
+    pub water: Quantity,
+    pub water_cons_rate: Quantity,
+    pub water_prod_rate: Quantity,
+    pub max_water: Quantity,
 
     // Secondary
-    pub skins: f64,
-    pub herbs: f64,
-    pub ore: f64,
-    pub leather: f64,
-    pub piety: f64,
-    pub metal: f64,
+    pub skins: Quantity,
+    pub max_skins: Quantity,
+    pub herbs: Quantity,
+    pub max_herbs: Quantity,
+    pub ore: Quantity,
+    pub max_ore: Quantity,
+    pub leather: Quantity,
+    pub max_leather: Quantity,
+    pub piety: Quantity,
+    pub metal: Quantity,
+    pub max_metal: Quantity,
 
     // Special
-    pub gold: f64,
-    pub corpses: f64
+    pub gold: Quantity,
+    pub max_gold: Quantity,
+    pub corpses: Quantity,
+
+    // Draws in a row each drop has not been found in, when pity is played with.
+    #[serde(default)]
+    pub misses: Vec<(Resource, u64)>,
+    #[serde(default)]
+    pub gems: Quantity
 }
 
 impl Resources {
-    fn increase(self, resource: Resource, amount: f64, ctx: &mut Context) -> Result<Self, IterationError> {
+    /**
+     * Starting resources for a new game: wood and stone storage vary slightly and
+     * one secondary resource gets a small head start.
+     */
+    pub fn genesis(rng: &mut ChaChaRng) -> Self {
+        let resources = Self {
+            max_wood: Quantity::units(180 + 10 * rng.gen_range(0..=4) as i64),
+            max_stone: Quantity::units(180 + 10 * rng.gen_range(0..=4) as i64),
+            ..Default::default()
+        };
+
+        let bonus = [
+            SecondaryResource::Skins,
+            SecondaryResource::Herbs,
+            SecondaryResource::Ore
+        ][rng.gen_range(0..3)].clone();
+        let amount = Quantity::units(rng.gen_range(5..=15) as i64);
+
+        match bonus {
+            SecondaryResource::Skins => Self { skins: amount, ..resources },
+            SecondaryResource::Herbs => Self { herbs: amount, ..resources },
+            SecondaryResource::Ore => Self { ore: amount, ..resources },
+        }
+    }
+
+    pub fn amount(&self, resource: &Resource) -> Quantity {
+        match resource {
+            Resource::Primary(primary_resource) => match primary_resource {
+                PrimaryResource::Food => self.food,
+                PrimaryResource::Wood => self.wood,
+                PrimaryResource::Stone => self.stone,
+            },
+            Resource::Secondary(secondary_resource) => match secondary_resource {
+                SecondaryResource::Skins => self.skins,
+                SecondaryResource::Herbs => self.herbs,
+                SecondaryResource::Ore => self.ore,
+            },
+            Resource::Tertiary(tertiary_resource) => match tertiary_resource {
+                TertiaryResource::Leather => self.leather,
+                TertiaryResource::Piety => self.piety,
+                TertiaryResource::Metal => self.metal,
+            },
+            Resource::Special(special_resource) => match special_resource {
+                SpecialResource::Gold => self.gold,
+                SpecialResource::Corpses => self.corpses,
+                SpecialResource::Gems => self.gems,
+            },
+        }
+    }
+
+    /**
+     * Most of the resource that can be stored. Piety and corpses have no limit.
+     */
+    pub fn capacity(&self, resource: &Resource) -> Option<Quantity> {
+        match resource {
+            Resource::Primary(PrimaryResource::Food) => Some(self.max_food),
+            Resource::Primary(PrimaryResource::Wood) => Some(self.max_wood),
+            Resource::Primary(PrimaryResource::Stone) => Some(self.max_stone),
+            Resource::Secondary(SecondaryResource::Skins) => Some(self.max_skins),
+            Resource::Secondary(SecondaryResource::Herbs) => Some(self.max_herbs),
+            Resource::Secondary(SecondaryResource::Ore) => Some(self.max_ore),
+            Resource::Tertiary(TertiaryResource::Leather) => Some(self.max_leather),
+            Resource::Tertiary(TertiaryResource::Metal) => Some(self.max_metal),
+            Resource::Special(SpecialResource::Gold) => Some(self.max_gold),
+            Resource::Special(SpecialResource::Gems) => Some(MAX_GEMS),
+            Resource::Tertiary(TertiaryResource::Piety) | Resource::Special(SpecialResource::Corpses) => None,
+        }
+    }
+
+    /**
+     * Draws in a row the resource has gone unfound in as a drop.
+     */
+    pub fn misses(&self, resource: &Resource) -> u64 {
+        self.misses.iter().find(|(missed, _)| missed == resource).map_or(0, |(_, misses)| *misses)
+    }
+
+    fn miss(self, resource: Resource, misses: u64) -> Self {
+        let mut tally: Vec<(Resource, u64)> = self.misses.into_iter().filter(|(missed, _)| *missed != resource).collect();
+        if misses > 0 {
+            tally.push((resource, misses));
+        }
+        tally.sort_by_key(|(missed, _)| missed.name());
+
+        Self { misses: tally, ..self }
+    }
+
+    /**
+     * Resources that reached their capacity since the previous resources.
+     */
+    pub fn capped(&self, previous: &Self) -> Vec<Resource> {
+        Resource::all().into_iter()
+            .filter(|resource| match self.capacity(resource) {
+                Some(capacity) => self.amount(resource) >= capacity && previous.amount(resource) < capacity,
+                None => false
+            })
+            .collect()
+    }
+
+    fn increase(self, resource: Resource, amount: Quantity, ctx: &mut Context) -> Result<Self, IterationError> {
         match resource {
             Resource::Primary(primary_resource) => {
-                let secondary_resource_amount = primary_resource.get_secondary_resource()
-                    .get_distribution()
-                    .sample_iter(&mut ctx.rng)
-                    .take(amount as usize)
-                    .map(u64::from)
-                    .sum::<u64>() as f64;
-
-                match primary_resource {
-                    PrimaryResource::Food => Ok(Self {
-                        food: f64::min(self.food + amount, self.max_food).round_to_2(),
-                        skins: (self.skins + secondary_resource_amount).round_to_2(),
-                        ..self
-                    }),
-                    PrimaryResource::Wood => Ok(Self {
-                        wood: f64::min(self.wood + amount, self.max_wood).round_to_2(),
-                        herbs: (self.herbs + secondary_resource_amount).round_to_2(),
-                        ..self
-                    }),
-                    PrimaryResource::Stone => Ok(Self {
-                        stone: f64::min(self.stone + amount, self.max_wood).round_to_2(),
-                        ore: (self.ore + secondary_resource_amount).round_to_2(),
-                        ..self
-                    }),
+                let draws = amount.whole().max(0) as u64;
+                let mut resources = match primary_resource {
+                    PrimaryResource::Food => Self { food: Quantity::min(self.food + amount, self.max_food), ..self },
+                    PrimaryResource::Wood => Self { wood: Quantity::min(self.wood + amount, self.max_wood), ..self },
+                    PrimaryResource::Stone => Self { stone: Quantity::min(self.stone + amount, self.max_stone), ..self },
+                };
+
+                for drop in ctx.drops.drops(&primary_resource).to_vec() {
+                    let pity = ctx.pity;
+                    let (found, misses) = drop.distribution()
+                        .sample_iter(&mut ctx.rng)
+                        .take(draws as usize)
+                        .fold((0, resources.misses(&drop.resource)), |(found, misses), hit| {
+                            match hit || (pity && misses + 1 >= PITY_DRAWS) {
+                                true => (found + 1, 0),
+                                false => (found, misses + 1)
+                            }
+                        });
+                    if draws > 0 {
+                        ctx.draws.push((drop.clone(), draws, found));
+                    }
+
+                    resources = resources.grant(drop.resource.clone(), Quantity::units((found * drop.units) as i64))?;
+                    if pity {
+                        resources = resources.miss(drop.resource, misses);
+                    }
                 }
+
+                Ok(resources)
             },
             Resource::Secondary(secondary_resource) => match secondary_resource {
-                SecondaryResource::Skins => Ok(Self { skins: (self.skins + amount).round_to_2(), ..self }),
-                SecondaryResource::Herbs => Ok(Self { herbs: (self.herbs + amount.round_to_2()), ..self }),
-                SecondaryResource::Ore => Ok(Self { ore: (self.ore + amount).round_to_2(), ..self }),
+                SecondaryResource::Skins => Ok(Self { skins: Quantity::min(self.skins + amount, self.max_skins), ..self }),
+                SecondaryResource::Herbs => Ok(Self { herbs: Quantity::min(self.herbs + amount, self.max_herbs), ..self }),
+                SecondaryResource::Ore => Ok(Self { ore: Quantity::min(self.ore + amount, self.max_ore), ..self }),
             },
             Resource::Tertiary(tertiary_resource) => match tertiary_resource {
-                TertiaryResource::Leather => Ok(Self { leather: (self.leather + amount).round_to_2(), ..self }),
-                TertiaryResource::Piety => Ok(Self { piety: (self.piety + amount).round_to_2(), ..self }),
-                TertiaryResource::Metal => Ok(Self { metal: (self.metal + amount).round_to_2(), ..self }),
+                TertiaryResource::Leather => Ok(Self { leather: Quantity::min(self.leather + amount, self.max_leather), ..self }),
+                TertiaryResource::Piety => Ok(Self { piety: (self.piety + amount), ..self }),
+                TertiaryResource::Metal => Ok(Self { metal: Quantity::min(self.metal + amount, self.max_metal), ..self }),
             },
             Resource::Special(special_resource) => match special_resource {
-                SpecialResource::Gold => Ok(Self { gold: (self.gold + amount).round_to_2(), ..self }),
-                SpecialResource::Corpses => Ok(Self { corpses: (self.corpses + amount).round_to_2(), ..self }),
+                SpecialResource::Gold => Ok(Self { gold: Quantity::min(self.gold + amount, self.max_gold), ..self }),
+                SpecialResource::Corpses => Ok(Self { corpses: (self.corpses + amount), ..self }),
+                SpecialResource::Gems => Ok(Self { gems: Quantity::min(self.gems + amount, MAX_GEMS), ..self }),
             },
         }
     }
 
-    fn decrease(self, resource: Resource, amount: f64) -> Result<Self, IterationError> {
+    fn decrease(self, resource: Resource, amount: Quantity) -> Result<Self, IterationError> {
         match resource {
             Resource::Primary(primary_resource) => match primary_resource {
-                PrimaryResource::Food => if self.food as i64 - amount as i64 >= 0 {
-                    Ok(Self { food: (self.food - amount).round_to_2(), ..self })
+                PrimaryResource::Food => if self.food.whole() - amount.whole() >= 0 {
+                    Ok(Self { food: (self.food - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Primary(PrimaryResource::Food)))
                 },
-                PrimaryResource::Wood => if self.wood as i64 - amount as i64 >= 0 {
-                    Ok(Self { wood: (self.wood - amount).round_to_2(), ..self })
+                PrimaryResource::Wood => if self.wood.whole() - amount.whole() >= 0 {
+                    Ok(Self { wood: (self.wood - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Primary(PrimaryResource::Wood)))
                 },
-                PrimaryResource::Stone => if self.stone as i64 - amount as i64 >= 0 {
-                    Ok(Self { stone: (self.stone - amount).round_to_2(), ..self })
+                PrimaryResource::Stone => if self.stone.whole() - amount.whole() >= 0 {
+                    Ok(Self { stone: (self.stone - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Primary(PrimaryResource::Stone)))
                 },
             },
             Resource::Secondary(secondary_resource) => match secondary_resource {
-                SecondaryResource::Skins => if self.skins as i64 - amount as i64 >= 0 {
-                    Ok(Self { skins: (self.skins - amount).round_to_2(), ..self })
+                SecondaryResource::Skins => if self.skins.whole() - amount.whole() >= 0 {
+                    Ok(Self { skins: (self.skins - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Secondary(SecondaryResource::Skins)))
                 },
-                SecondaryResource::Herbs => if self.herbs as i64 - amount as i64 >= 0 {
-                    Ok(Self { herbs: (self.herbs - amount).round_to_2(), ..self })
+                SecondaryResource::Herbs => if self.herbs.whole() - amount.whole() >= 0 {
+                    Ok(Self { herbs: (self.herbs - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Secondary(SecondaryResource::Herbs)))
                 },
-                SecondaryResource::Ore => if self.ore as i64 - amount as i64 >= 0 {
-                    Ok(Self { ore: (self.ore - amount).round_to_2(), ..self })
+                SecondaryResource::Ore => if self.ore.whole() - amount.whole() >= 0 {
+                    Ok(Self { ore: (self.ore - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Secondary(SecondaryResource::Ore)))
                 },
             },
             Resource::Tertiary(tertiary_resource) => match tertiary_resource {
-                TertiaryResource::Leather => if self.leather as i64 - amount as i64 >= 0 {
-                    Ok(Self { leather: (self.leather - amount).round_to_2(), ..self })
+                TertiaryResource::Leather => if self.leather.whole() - amount.whole() >= 0 {
+                    Ok(Self { leather: (self.leather - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Tertiary(TertiaryResource::Leather)))
                 },
-                TertiaryResource::Piety => if self.piety as i64 - amount as i64 >= 0 {
-                    Ok(Self { piety: (self.piety - amount).round_to_2(), ..self })
+                TertiaryResource::Piety => if self.piety.whole() - amount.whole() >= 0 {
+                    Ok(Self { piety: (self.piety - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Tertiary(TertiaryResource::Piety)))
                 },
-                TertiaryResource::Metal => if self.metal as i64 - amount as i64 >= 0 {
-                    Ok(Self { metal: (self.metal - amount).round_to_2(), ..self })
+                TertiaryResource::Metal => if self.metal.whole() - amount.whole() >= 0 {
+                    Ok(Self { metal: (self.metal - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Tertiary(TertiaryResource::Metal)))
                 },
             },
             Resource::Special(special_resource) => match special_resource {
-                SpecialResource::Gold => if self.gold as i64 - amount as i64 >= 0 {
-                    Ok(Self { gold: (self.gold - amount).round_to_2(), ..self })
+                SpecialResource::Gold => if self.gold.whole() - amount.whole() >= 0 {
+                    Ok(Self { gold: (self.gold - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Special(SpecialResource::Gold)))
                 },
-                SpecialResource::Corpses => if self.corpses as i64 - amount as i64 >= 0 {
-                    Ok(Self { corpses: (self.corpses - amount).round_to_2(), ..self })
+                SpecialResource::Corpses => if self.corpses.whole() - amount.whole() >= 0 {
+                    Ok(Self { corpses: (self.corpses - amount), ..self })
                 } else {
                     Err(IterationError::NotEnaughtResource(Resource::Special(SpecialResource::Corpses)))
                 },
+                SpecialResource::Gems => if self.gems.whole() - amount.whole() >= 0 {
+                    Ok(Self { gems: (self.gems - amount), ..self })
+                } else {
+                    Err(IterationError::NotEnaughtResource(Resource::Special(SpecialResource::Gems)))
+                },
+            },
+        }
+    }
+
+    /**
+     * Amount of every resource that changed since the previous resources.
+     */
+    pub fn deltas(&self, previous: &Self) -> Vec<(Resource, Quantity)> {
+        Resource::all().into_iter()
+            .map(|resource| {
+                let delta = self.amount(&resource) - previous.amount(&resource);
+                (resource, delta)
+            })
+            .filter(|(_, delta)| *delta != Quantity::ZERO)
+            .collect()
+    }
+
+    /**
+     * Food gained or lost on each iteration with the given morale.
+     */
+    pub fn food_net_rate(&self, morale: Quantity) -> Quantity {
+        self.food_prod_rate * self.food_prod_rate_multiplier * morale - self.food_cons_rate
+    }
+
+    /**
+     * Pays the costs of an action. Sandbox games get everything for free.
+     */
+    fn pay(self, costs: Vec<(Resource, Quantity)>, ctx: &Context) -> Result<Self, IterationError> {
+        if ctx.sandbox {
+            return Ok(self);
+        }
+
+        let mut resources = self;
+
+        for cost in costs {
+            resources = resources.decrease(cost.0, cost.1)?;
+        }
+
+        Ok(resources)
+    }
+
+    /**
+     * Adds resources out of thin air, or out of a trade. Unlike gathering, granting
+     * primary resources never yields secondary ones.
+     */
+    fn grant(self, resource: Resource, amount: Quantity) -> Result<Self, IterationError> {
+        match resource {
+            Resource::Primary(primary_resource) => match primary_resource {
+                PrimaryResource::Food => Ok(Self { food: Quantity::min(self.food + amount, self.max_food), ..self }),
+                PrimaryResource::Wood => Ok(Self { wood: Quantity::min(self.wood + amount, self.max_wood), ..self }),
+                PrimaryResource::Stone => Ok(Self { stone: Quantity::min(self.stone + amount, self.max_stone), ..self }),
+            },
+            Resource::Secondary(secondary_resource) => match secondary_resource {
+                SecondaryResource::Skins => Ok(Self { skins: Quantity::min(self.skins + amount, self.max_skins), ..self }),
+                SecondaryResource::Herbs => Ok(Self { herbs: Quantity::min(self.herbs + amount, self.max_herbs), ..self }),
+                SecondaryResource::Ore => Ok(Self { ore: Quantity::min(self.ore + amount, self.max_ore), ..self }),
+            },
+            Resource::Tertiary(tertiary_resource) => match tertiary_resource {
+                TertiaryResource::Leather => Ok(Self { leather: Quantity::min(self.leather + amount, self.max_leather), ..self }),
+                TertiaryResource::Piety => Ok(Self { piety: (self.piety + amount), ..self }),
+                TertiaryResource::Metal => Ok(Self { metal: Quantity::min(self.metal + amount, self.max_metal), ..self }),
+            },
+            Resource::Special(special_resource) => match special_resource {
+                SpecialResource::Gold => Ok(Self { gold: Quantity::min(self.gold + amount, self.max_gold), ..self }),
+                SpecialResource::Corpses => Ok(Self { corpses: (self.corpses + amount), ..self }),
+                SpecialResource::Gems => Ok(Self { gems: Quantity::min(self.gems + amount, MAX_GEMS), ..self }),
             },
         }
     }
 
-    fn increase_primary_resource_storage(self, primary_resource: PrimaryResource, amount: f64) -> Result<Self, IterationError> {
-        match primary_resource {
-            PrimaryResource::Food => Ok(Self { max_food: (self.max_food + amount).round_to_2(), ..self }),
-            PrimaryResource::Wood => Ok(Self { max_wood: (self.max_wood + amount).round_to_2(), ..self }),
-            PrimaryResource::Stone => Ok(Self { max_stone: (self.max_stone + amount).round_to_2(), ..self })
+    fn increase_resource_storage(self, resource: Resource, amount: Quantity) -> Result<Self, IterationError> {
+        match resource {
+            Resource::Primary(primary_resource) => match primary_resource {
+                PrimaryResource::Food => Ok(Self { max_food: (self.max_food + amount), ..self }),
+                PrimaryResource::Wood => Ok(Self { max_wood: (self.max_wood + amount), ..self }),
+                PrimaryResource::Stone => Ok(Self { max_stone: (self.max_stone + amount), ..self })
+            },
+            Resource::Secondary(secondary_resource) => match secondary_resource {
+                SecondaryResource::Skins => Ok(Self { max_skins: (self.max_skins + amount), ..self }),
+                SecondaryResource::Herbs => Ok(Self { max_herbs: (self.max_herbs + amount), ..self }),
+                SecondaryResource::Ore => Ok(Self { max_ore: (self.max_ore + amount), ..self })
+            },
+            Resource::Tertiary(tertiary_resource) => match tertiary_resource {
+                TertiaryResource::Leather => Ok(Self { max_leather: (self.max_leather + amount), ..self }),
+                TertiaryResource::Metal => Ok(Self { max_metal: (self.max_metal + amount), ..self }),
+                TertiaryResource::Piety => Ok(self)
+            },
+            Resource::Special(special_resource) => match special_resource {
+                SpecialResource::Gold => Ok(Self { max_gold: (self.max_gold + amount), ..self }),
+                SpecialResource::Corpses | SpecialResource::Gems => Ok(self)
+            }
         }
     }
 
-    fn increase_resource_production_rate(self, resource: Resource, amount: f64) -> Result<Self, IterationError> {
+    fn increase_resource_production_rate(self, resource: Resource, amount: Quantity) -> Result<Self, IterationError> {
         match resource {
             Resource::Primary(primary_resource) => match primary_resource {
-                PrimaryResource::Food => Ok(Self { food_prod_rate: (self.food_prod_rate + amount).round_to_2(), ..self }),
-                PrimaryResource::Wood => Ok(Self { wood_prod_rate: (self.wood_prod_rate + amount).round_to_2(), ..self }),
-                PrimaryResource::Stone => Ok(Self { stone_prod_rate: (self.stone_prod_rate + amount).round_to_2(), ..self })
+                PrimaryResource::Food => Ok(Self { food_prod_rate: (self.food_prod_rate + amount), ..self }),
+                PrimaryResource::Wood => Ok(Self { wood_prod_rate: (self.wood_prod_rate + amount), ..self }),
+                PrimaryResource::Stone => Ok(Self { stone_prod_rate: (self.stone_prod_rate + amount), ..self })
             }
             _ => Ok(self)
         }
     }
 
-    fn decrease_resource_production_rate(self, resource: Resource, amount: f64) -> Result<Self, IterationError> {
+    fn decrease_resource_production_rate(self, resource: Resource, amount: Quantity) -> Result<Self, IterationError> {
         match resource {
             Resource::Primary(primary_resource) => match primary_resource {
-                PrimaryResource::Food => Ok(Self { food_prod_rate: (self.food_prod_rate - amount).round_to_2(), ..self }),
-                PrimaryResource::Wood => Ok(Self { wood_prod_rate: (self.wood_prod_rate - amount).round_to_2(), ..self }),
-                PrimaryResource::Stone => Ok(Self { stone_prod_rate: (self.stone_prod_rate - amount).round_to_2(), ..self })
+                PrimaryResource::Food => Ok(Self { food_prod_rate: (self.food_prod_rate - amount), ..self }),
+                PrimaryResource::Wood => Ok(Self { wood_prod_rate: (self.wood_prod_rate - amount), ..self }),
+                PrimaryResource::Stone => Ok(Self { stone_prod_rate: (self.stone_prod_rate - amount), ..self })
             }
             _ => Ok(self)
         }
     }
 
-    fn increase_food_consumption(self, amount: f64) -> Result<Self, IterationError> {
-        Ok(Self { food_cons_rate: (self.food_cons_rate + amount).round_to_2(), ..self })
+    fn increase_food_consumption(self, amount: Quantity) -> Result<Self, IterationError> {
+        Ok(Self { food_cons_rate: (self.food_cons_rate + amount), ..self })
+    }
+
+    fn increase_water_consumption(self, amount: Quantity) -> Result<Self, IterationError> {
+        Ok(Self { water_cons_rate: (self.water_cons_rate + amount), ..self })
+    }
+
+    fn decrease_water_consumption(self, amount: Quantity) -> Result<Self, IterationError> {
+        Ok(Self { water_cons_rate: (self.water_cons_rate - amount), ..self })
+    }
+
+    /**
+     * Newborn citizens eat and drink like recruited ones, but cost no food to join.
+     */
+    pub fn feed_births(self, births: u64) -> Result<Self, IterationError> {
+        self.increase_food_consumption(Quantity::ONE * births)?
+            .increase_water_consumption(Quantity::hundredths(50) * births)
     }
 
     pub fn apply_action(self, action: &Action, ctx: &mut Context) -> Result<Self, IterationError> {
         match action {
             Action::RecruitCitizen => {
-                self.decrease(Resource::Primary(PrimaryResource::Food), 20.0)?
-                    .increase_food_consumption(1.0)
+                self.pay(vec!{(Resource::Primary(PrimaryResource::Food), RECRUIT_COST)}, ctx)?
+                    .increase_food_consumption(Quantity::ONE)?
+                    .increase_water_consumption(Quantity::hundredths(50))
             }
-            Action::Collect(primary_resource) => self.increase(Resource::Primary(primary_resource.clone()), 1.0, ctx),
-            Action::Build(building) => {
-                let mut resources = self;
+            Action::Collect(primary_resource) => self.increase(Resource::Primary(primary_resource.clone()), Quantity::ONE, ctx),
+            Action::Build(building) if ctx.construction => self.pay(building.costs(), ctx),
+            Action::Build(building) => self.pay(building.costs(), ctx)?.raise(building),
+            Action::Ennoble => self.pay(vec!{(Resource::Special(SpecialResource::Gold), Quantity::units(50))}, ctx),
+            Action::AssignJob(job) => match job.get_resource_production() {
+                Some(resource) => self.increase_resource_production_rate(resource, job.get_production_rate()),
+                None => Ok(self)
+            }?.increase_water_consumption(job.get_water_consumption()),
+            Action::DischargeJob(job) => match job.get_resource_production() {
+                Some(resource) => self.decrease_resource_production_rate(resource, job.get_production_rate()),
+                None => Ok(self)
+            }?.decrease_water_consumption(job.get_water_consumption()),
+            Action::Train(specialization) => self.pay(specialization.training_costs(), ctx),
+            Action::Grant(resource, amount) => if ctx.sandbox {
+                self.grant(resource.clone(), Quantity::units(*amount as i64))
+            } else {
+                Err(IterationError::SandboxOnly)
+            },
+            Action::Barter { from, to, amount } if from != to && *amount >= BARTER_RATIO => {
+                self.pay(vec!{(Resource::Primary(from.clone()), Quantity::units(*amount as i64))}, ctx)?
+                    .grant(Resource::Primary(to.clone()), Quantity::units((*amount / BARTER_RATIO) as i64))
+            },
+            Action::Barter { .. } => Err(IterationError::InvalidTrade),
+            Action::Sacrifice(resource, amount) => match resource.sacrifice_piety() {
+                Some(piety) if *amount > 0 => self.pay(vec!{(resource.clone(), Quantity::units(*amount as i64))}, ctx)?
+                    .grant(Resource::Tertiary(TertiaryResource::Piety), piety * *amount),
+                _ => Err(IterationError::InvalidSacrifice(resource.clone()))
+            },
+            Action::ReadOmens(iterations) => self.pay(vec!{(Resource::Tertiary(TertiaryResource::Piety), OMEN_PIETY * u64::min(*iterations, MAX_OMENS))}, ctx),
+            Action::OfferTrade(offer) => self.decrease(offer.give.0.clone(), offer.give.1),
+            Action::AcceptTrade(receipt) => self.decrease(receipt.offer.want.0.clone(), receipt.offer.want.1)?
+                .grant(receipt.offer.give.0.clone(), receipt.offer.give.1),
+            Action::SettleTrade(receipt) => self.grant(receipt.offer.want.0.clone(), receipt.offer.want.1),
+            _ => Ok(self)
+        }
+    }
 
-                for cost in building.costs() {
-                    resources = resources.decrease(cost.0, cost.1)?;
-                }
+    /**
+     * Storage, water and food keeping a finished building adds.
+     */
+    pub fn raise(self, building: &Buildings) -> Result<Self, IterationError> {
+        let mut resources = self;
 
-                if let Some(primary_resource_storage_increase) = building.primary_resource_storage_increase() {
-                    resources = resources.increase_primary_resource_storage(
-                        primary_resource_storage_increase.0,
-                        primary_resource_storage_increase.1
-                    )?;
-                }
+        for storage_increase in building.resource_storage_increase() {
+            resources = resources.increase_resource_storage(storage_increase.0, storage_increase.1)?;
+        }
 
-                Ok(resources)
-            },
-            Action::AssignJob(job) => self.increase_resource_production_rate(job.get_resource_production(), job.get_production_rate()),
-            Action::DischargeJob(job) => self.decrease_resource_production_rate(job.get_resource_production(), job.get_production_rate()),
-            _ => Ok(self)
+        Ok(Self {
+            food_spoilage_percent: resources.food_spoilage_percent * building.food_spoilage_multiplier(),
+            water_prod_rate: (resources.water_prod_rate + building.water_production_increase()),
+            max_water: (resources.max_water + building.water_storage_increase()),
+            ..resources
+        })
+    }
+
+    /**
+     * Takes back resources salvaged from a cancelled building, as far as there is room
+     * to store them.
+     */
+    pub fn salvage(self, salvaged: Vec<(Resource, Quantity)>) -> Result<Self, IterationError> {
+        salvaged.into_iter().try_fold(self, |resources, (resource, amount)| resources.grant(resource, amount))
+    }
+
+    /**
+     * Charges the nobles upkeep. Scholars always raise every primary production
+     * multiplier, nobles only add their governance bonus while their upkeep is paid.
+     */
+    pub fn govern(self, citizens: &Citizens) -> Result<Self, IterationError> {
+        let research_bonus = Job::Scholar.get_production_rate() * citizens.scholars;
+        let upkeep: Vec<(Resource, Quantity)> = CitizenClass::Noble.upkeep()
+            .into_iter()
+            .map(|(resource, amount)| (resource, amount * citizens.nobles))
+            .collect();
+
+        let affordable = citizens.nobles > 0 && upkeep.iter()
+            .all(|(resource, amount)| self.amount(resource) >= *amount);
+
+        if !affordable {
+            let multiplier = Quantity::ONE + research_bonus;
+
+            return Ok(Self {
+                food_prod_rate_multiplier: multiplier,
+                wood_prod_rate_multiplier: multiplier,
+                stone_prod_rate_multiplier: multiplier,
+                ..self
+            });
+        }
+
+        let mut resources = self;
+
+        for (resource, amount) in upkeep {
+            resources = resources.decrease(resource, amount)?;
         }
+
+        let multiplier = Quantity::ONE + research_bonus + citizens.governance_bonus();
+
+        Ok(Self {
+            food_prod_rate_multiplier: multiplier,
+            wood_prod_rate_multiplier: multiplier,
+            stone_prod_rate_multiplier: multiplier,
+            ..resources
+        })
     }
 
-    pub fn work(self, ctx: &mut Context) -> Result<Self, IterationError> {
-        let food_inc = self.food_prod_rate * self.food_prod_rate_multiplier - self.food_cons_rate;
-        let wood_inc = self.wood_prod_rate * self.wood_prod_rate_multiplier;
-        let stone_inc = self.stone_prod_rate * self.stone_prod_rate_multiplier;
+    /**
+     * Specialist production: blacksmiths smelt ore into metal and clerics gather piety.
+     */
+    pub fn craft(self, citizens: &Citizens) -> Result<Self, IterationError> {
+        let metal = Quantity::min(self.ore, Job::Blacksmith.get_production_rate() * citizens.blacksmiths);
+        let metal = Quantity::min(metal, Quantity::max(self.max_metal - self.metal, Quantity::ZERO));
+        let piety = Job::Cleric.get_production_rate() * citizens.clerics;
+
+        Ok(Self {
+            ore: self.ore - metal,
+            metal: self.metal + metal,
+            piety: self.piety + piety,
+            ..self
+        })
+    }
+
+    /**
+     * Food piled beyond what the settlement can keep fresh slowly spoils before
+     * anything else happens. Citizens and farms drink from the water supply; when it
+     * runs dry, food production slows down in proportion to the missing water.
+     * Under a soft cap, crowded laborer jobs produce less than their flat rates, and
+     * tired ones lose part of their output.
+     */
+    pub fn work(self, citizens: &Citizens, ctx: &mut Context) -> Result<Self, IterationError> {
+        let water_available = self.water + self.water_prod_rate;
+        let water_ratio = if self.water_cons_rate > water_available {
+            water_available / self.water_cons_rate
+        } else {
+            Quantity::ONE
+        };
+        let water = Quantity::min(Quantity::max(water_available - self.water_cons_rate, Quantity::ZERO), self.max_water);
+
+        let crowding = |job: Job, workers: u64| ctx.soft_cap.map_or(Quantity::ZERO, |soft_cap| job.crowding_loss(workers, soft_cap));
+        let rested = |job: Job| Quantity::ONE - citizens.fatigue(&job);
+        let food_rate = (self.food_prod_rate - crowding(Job::Farmer, citizens.farmers)) * rested(Job::Farmer);
+        let wood_rate = (self.wood_prod_rate - crowding(Job::Woodcutter, citizens.woodcutters)) * rested(Job::Woodcutter);
+        let stone_rate = (self.stone_prod_rate - crowding(Job::Miner, citizens.miners)) * rested(Job::Miner);
+
+        let food_inc = food_rate * self.food_prod_rate_multiplier * ctx.morale * water_ratio - self.food_cons_rate;
+        let wood_inc = wood_rate * self.wood_prod_rate_multiplier * ctx.morale;
+        let stone_inc = stone_rate * self.stone_prod_rate_multiplier * ctx.morale;
 
-        self.increase(Resource::Primary(PrimaryResource::Food), food_inc, ctx)?
+        let food_surplus = Quantity::max(self.food - FRESH_FOOD_STORAGE, Quantity::ZERO);
+        let food = self.food - food_surplus * self.food_spoilage_percent / Quantity::units(100);
+
+        Self { food, water, ..self }.increase(Resource::Primary(PrimaryResource::Food), food_inc, ctx)?
             .increase(Resource::Primary(PrimaryResource::Wood), wood_inc, ctx)?
-            .increase(Resource::Primary(PrimaryResource::Stone), stone_inc, ctx)
-    }
-
-    pub fn hash(&self) -> u64 {
-        let mut hasher = DefaultHasher::default();
-        hasher.write(&self.food.as_bytes()[..]);
-        hasher.write(&self.food_cons_rate.as_bytes()[..]);
-        hasher.write(&self.food_prod_rate.as_bytes()[..]);
-        hasher.write(&self.food_prod_rate_multiplier.as_bytes()[..]);
-        hasher.write(&self.max_food.as_bytes()[..]);
-        hasher.write(&self.wood.as_bytes()[..]);
-        hasher.write(&self.wood_prod_rate.as_bytes()[..]);
-        hasher.write(&self.wood_prod_rate_multiplier.as_bytes()[..]);
-        hasher.write(&self.max_wood.as_bytes()[..]);
-        hasher.write(&self.stone.as_bytes()[..]);
-        hasher.write(&self.stone_prod_rate.as_bytes()[..]);
-        hasher.write(&self.stone_prod_rate_multiplier.as_bytes()[..]);
-        hasher.write(&self.max_stone.as_bytes()[..]);
-        hasher.write(&self.skins.as_bytes()[..]);
-        hasher.write(&self.herbs.as_bytes()[..]);
-        hasher.write(&self.ore.as_bytes()[..]);
-        hasher.write(&self.leather.as_bytes()[..]);
-        hasher.write(&self.piety.as_bytes()[..]);
-        hasher.write(&self.metal.as_bytes()[..]);
-        hasher.write(&self.gold.as_bytes()[..]);
-        hasher.write(&self.corpses.as_bytes()[..]);
-        hasher.finish()
+            .increase(Resource::Primary(PrimaryResource::Stone), stone_inc, ctx)?
+            .strike_gold(citizens.miners, ctx)
+    }
+
+    /**
+     * Rolls for miners striking gold, with better odds the more miners there are.
+     * Nothing is rolled unless gold strikes are played with.
+     */
+    fn strike_gold(self, miners: u64, ctx: &mut Context) -> Result<Self, IterationError> {
+        if !ctx.gold_strikes || miners == 0 {
+            return Ok(self);
+        }
+
+        let chance = Quantity::min(GOLD_STRIKE_CHANCE * miners, Quantity::ONE);
+        if Bernoulli::from_ratio(chance.as_hundredths() as u32, 100).unwrap().sample(&mut ctx.rng) {
+            ctx.events.push(Event::GoldStruck);
+            self.grant(Resource::Special(SpecialResource::Gold), GOLD_STRIKE)
+        } else {
+            Ok(self)
+        }
+    }
+
+    pub fn hash(&self) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write(&self.food.to_le_bytes());
+        hasher.write(&self.food_cons_rate.to_le_bytes());
+        hasher.write(&self.food_prod_rate.to_le_bytes());
+        hasher.write(&self.food_prod_rate_multiplier.to_le_bytes());
+        hasher.write(&self.max_food.to_le_bytes());
+        hasher.write(&self.food_spoilage_percent.to_le_bytes());
+        hasher.write(&self.wood.to_le_bytes());
+        hasher.write(&self.wood_prod_rate.to_le_bytes());
+        hasher.write(&self.wood_prod_rate_multiplier.to_le_bytes());
+        hasher.write(&self.max_wood.to_le_bytes());
+        hasher.write(&self.stone.to_le_bytes());
+        hasher.write(&self.stone_prod_rate.to_le_bytes());
+        hasher.write(&self.stone_prod_rate_multiplier.to_le_bytes());
+        hasher.write(&self.max_stone.to_le_bytes());
+        hasher.write(&self.water.to_le_bytes());
+        hasher.write(&self.water_cons_rate.to_le_bytes());
+        hasher.write(&self.water_prod_rate.to_le_bytes());
+        hasher.write(&self.max_water.to_le_bytes());
+        hasher.write(&self.skins.to_le_bytes());
+        hasher.write(&self.max_skins.to_le_bytes());
+        hasher.write(&self.herbs.to_le_bytes());
+        hasher.write(&self.max_herbs.to_le_bytes());
+        hasher.write(&self.ore.to_le_bytes());
+        hasher.write(&self.max_ore.to_le_bytes());
+        hasher.write(&self.leather.to_le_bytes());
+        hasher.write(&self.max_leather.to_le_bytes());
+        hasher.write(&self.piety.to_le_bytes());
+        hasher.write(&self.metal.to_le_bytes());
+        hasher.write(&self.max_metal.to_le_bytes());
+        hasher.write(&self.gold.to_le_bytes());
+        hasher.write(&self.max_gold.to_le_bytes());
+        hasher.write(&self.corpses.to_le_bytes());
+        hasher.write_usize(self.misses.len());
+        for (resource, misses) in self.misses.iter() {
+            hasher.write_sized(resource.name().as_bytes());
+            hasher.write_u64(*misses);
+        }
+        hasher.write(&self.gems.to_le_bytes());
+        hasher.digest()
     }
 }
 
@@ -312,31 +781,144 @@ impl Default for Resources {
     fn default() -> Self {
         Self {
             // Main
-            food: 0.0,
-            food_cons_rate: 0.0,
-            food_prod_rate: 0.0,
-            food_prod_rate_multiplier: 1.0,
-            max_food: 200.0,
-            wood: 0.0,
-            wood_prod_rate: 0.0,
-            wood_prod_rate_multiplier: 1.0,
-            max_wood: 200.0,
-            stone: 0.0,
-            stone_prod_rate: 0.0,
-            stone_prod_rate_multiplier: 1.0,
-            max_stone: 200.0,
+            food: Quantity::ZERO,
+            food_cons_rate: Quantity::ZERO,
+            food_prod_rate: Quantity::ZERO,
+            food_prod_rate_multiplier: Quantity::ONE,
+            max_food: Quantity::units(200),
+            food_spoilage_percent: Quantity::hundredths(10),
+            wood: Quantity::ZERO,
+            wood_prod_rate: Quantity::ZERO,
+            wood_prod_rate_multiplier: Quantity::ONE,
+            max_wood: Quantity::units(200),
+            stone: Quantity::ZERO,
+            stone_prod_rate: Quantity::ZERO,
+            stone_prod_rate_multiplier: Quantity::ONE,
+            max_stone: Quantity::units(200),
+            water: Quantity::units(100),
+            water_cons_rate: Quantity::ZERO,
+            water_prod_rate: Quantity::units(5), // Every settlement starts by a river
+            max_water: Quantity::units(200),
 
             // Secondary
-            skins: 0.0,
-            herbs: 0.0,
-            ore: 0.0,
-            leather: 0.0,
-            piety: 0.0,
-            metal: 0.0,
+            skins: Quantity::ZERO,
+            max_skins: Quantity::units(100),
+            herbs: Quantity::ZERO,
+            max_herbs: Quantity::units(100),
+            ore: Quantity::ZERO,
+            max_ore: Quantity::units(100),
+            leather: Quantity::ZERO,
+            max_leather: Quantity::units(50),
+            piety: Quantity::ZERO,
+            metal: Quantity::ZERO,
+            max_metal: Quantity::units(50),
 
             // Special
-            gold: 0.0,
-            corpses: 0.0
+            gold: Quantity::ZERO,
+            max_gold: Quantity::units(100),
+            corpses: Quantity::ZERO,
+
+            misses: vec!{},
+            gems: Quantity::ZERO
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::quantity::Quantity;
+    use crate::game::buildings::Buildings;
+    use crate::game::jobs::Job;
+    use crate::game::settings::Settings;
+    use crate::game::luck::luck;
+    use crate::game::events::Event;
+    use super::{Resource, PrimaryResource, SecondaryResource, TertiaryResource, SpecialResource, PITY_DRAWS, GOLD_STRIKE, MAX_GEMS};
+
+    #[test]
+    fn sacrifices_turn_offerings_into_piety() {
+        let piety = |state: &State| state.resources.amount(&Resource::Tertiary(TertiaryResource::Piety));
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_plan(vec!{(Action::Collect(PrimaryResource::Food), 40)}).unwrap();
+
+        let sacrificed = state.clone().apply_action(Action::Sacrifice(Resource::Primary(PrimaryResource::Food), 40)).unwrap();
+        assert_eq!(piety(&sacrificed), Quantity::units(2));
+        assert_eq!(Action::Sacrifice(Resource::Special(SpecialResource::Corpses), 1).words(), ("sacrifice", Some("corpses".to_string())));
+
+        match state.clone().apply_action(Action::Sacrifice(Resource::Primary(PrimaryResource::Stone), 1)) {
+            Err(error) => assert_eq!(error.code(), "invalid_sacrifice"),
+            Ok(_) => panic!("the gods took stone")
+        }
+        match state.apply_action(Action::Sacrifice(Resource::Special(SpecialResource::Gold), 1)) {
+            Err(error) => assert_eq!(error.exit_code(), 10),
+            Ok(_) => panic!("sacrificed gold that was never earned")
+        }
+    }
+
+    #[test]
+    fn pity_ends_droughts_of_secondary_resources() {
+        let gather = |pity: bool| State::with_settings(1, Settings { pity, ..Default::default() })
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 150)}).unwrap();
+        let (plain, pitied) = (gather(false), gather(true));
+        let found = |state: &State| luck(state).unwrap()[0].found;
+
+        assert!(plain.resources.misses.is_empty());
+        assert!(found(&pitied) > found(&plain));
+        assert!(found(&pitied) >= 150 / PITY_DRAWS);
+        assert!(pitied.resources.misses(&Resource::Secondary(SecondaryResource::Skins)) < PITY_DRAWS);
+        assert!(pitied.resources.misses.iter().all(|(missed, _)| *missed == Resource::Secondary(SecondaryResource::Skins)));
+        assert_ne!(plain.hash(), pitied.hash());
+    }
+
+    #[test]
+    fn miners_may_strike_gold() {
+        let gold = |state: &State| state.resources.amount(&Resource::Special(SpecialResource::Gold));
+        let plan = vec!{
+            (Action::Collect(PrimaryResource::Food), 200),
+            (Action::Collect(PrimaryResource::Wood), 60),
+            (Action::Build(Buildings::WoodenHut), 3),
+            (Action::RecruitCitizen, 4),
+            (Action::AssignJob(Job::Farmer), 2),
+            (Action::AssignJob(Job::Miner), 2),
+            (Action::Idle, 80),
+        };
+        let plain = State::new(-141872649309347578469772012024767025949).apply_plan(plan.clone()).unwrap();
+        let lucky = State::with_settings(-141872649309347578469772012024767025949, Settings { gold_strikes: true, ..Default::default() })
+            .apply_plan(plan).unwrap();
+        let strikes = lucky.recent_events().iter().filter(|(_, event)| *event == Event::GoldStruck).count();
+
+        assert_eq!(gold(&plain), Quantity::ZERO);
+        assert!(strikes > 0);
+        assert_eq!(gold(&lucky), GOLD_STRIKE * strikes as u64);
+    }
+
+    #[test]
+    fn mining_turns_up_rare_gems() {
+        let gems = Resource::Special(SpecialResource::Gems);
+        let plan = vec!{(Action::Collect(PrimaryResource::Stone), 300)};
+        let plain = State::new(-141872649309347578469772012024767025949).apply_plan(plan.clone()).unwrap();
+        let rich = State::with_settings(-141872649309347578469772012024767025949, Settings { gems: true, ..Default::default() })
+            .apply_plan(plan).unwrap();
+        let found = rich.resources.amount(&gems);
+
+        assert_eq!(plain.resources.amount(&gems), Quantity::ZERO);
+        assert!(found > Quantity::ZERO && found <= MAX_GEMS);
+        assert_eq!("gems".parse::<Resource>(), Ok(gems.clone()));
+        assert!(luck(&rich).unwrap().iter().any(|luck| luck.resource == "gems" && Quantity::units(luck.found as i64) == found));
+
+        let offered = rich.clone().apply_action(Action::Sacrifice(gems.clone(), 1)).unwrap();
+        assert_eq!(offered.resources.amount(&gems), found - Quantity::ONE);
+        assert!(offered.resources.piety >= rich.resources.piety + Quantity::units(10));
+    }
+
+    #[test]
+    fn stone_is_capped_at_its_own_storage() {
+        let log = vec!{(Action::Collect(PrimaryResource::Stone), 250)};
+        let state = State::new(23757).apply_log(log).unwrap();
+
+        assert!(state.resources.max_wood > state.resources.max_stone);
+        assert_eq!(state.resources.stone, state.resources.max_stone);
+        assert!(state.check().is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+use super::state::State;
+use super::buildings::Buildings;
+use super::jobs::{Job, CitizenClass};
+use super::resources::{Resource, PrimaryResource, TertiaryResource, RECRUIT_COST};
+use super::quantity::Quantity;
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/**
+ * What a building or a job brings, what it costs, and how long the current income
+ * takes to pay for it.
+ */
+#[derive(Serialize)]
+pub struct Payback {
+    pub name: String,
+    pub benefits: Vec<String>,
+    pub costs: BTreeMap<&'static str, Quantity>,
+    // Iterations of income paying for every cost, none when some cost isn't earned at all.
+    pub iterations: Option<u64>
+}
+
+/**
+ * Paybacks of every building and of every producing job, given the production rates
+ * of the game. A job costs the recruit taking it, and the training on top for
+ * specialists.
+ */
+pub fn roi(state: &State) -> Vec<Payback> {
+    let buildings = Buildings::all().into_iter().map(|building| {
+        let mut benefits = vec!{};
+
+        if building.population_capacity_increase() > 0 {
+            benefits.push(format!("+{} max population", building.population_capacity_increase()));
+        }
+        if building.training_capacity_increase() > 0 {
+            benefits.push(format!("+{} training places", building.training_capacity_increase()));
+        }
+        if building.water_production_increase() > Quantity::ZERO {
+            benefits.push(format!("+{} water/i", building.water_production_increase()));
+        }
+        if building.water_storage_increase() > Quantity::ZERO {
+            benefits.push(format!("+{} max water", building.water_storage_increase()));
+        }
+        if building.food_spoilage_multiplier() != Quantity::ONE {
+            benefits.push(format!("x{} food spoilage", building.food_spoilage_multiplier()));
+        }
+        for (resource, amount) in building.resource_storage_increase() {
+            benefits.push(format!("+{} max {}", amount, resource.name()));
+        }
+
+        payback(state, format!("{:?}", building), benefits, building.costs())
+    });
+
+    let jobs = Job::all().into_iter().filter_map(|job| {
+        let resource = job.get_resource_production()?;
+        let benefits = vec!{format!("+{} {}/i per worker", job.get_production_rate(), resource.name())};
+        let mut costs = vec!{(Resource::Primary(PrimaryResource::Food), RECRUIT_COST)};
+
+        if job.get_class() == CitizenClass::Specialist {
+            costs.extend(job.get_specialization()?.training_costs());
+        }
+
+        Some(payback(state, format!("{:?}", job), benefits, costs))
+    });
+
+    buildings.chain(jobs).collect()
+}
+
+fn payback(state: &State, name: String, benefits: Vec<String>, costs: Vec<(Resource, Quantity)>) -> Payback {
+    let mut total: Vec<(Resource, Quantity)> = vec!{};
+
+    for (resource, cost) in costs {
+        match total.iter_mut().find(|(paid, _)| *paid == resource) {
+            Some(entry) => entry.1 += cost,
+            None => total.push((resource, cost))
+        }
+    }
+
+    let iterations = total.iter().try_fold(0, |iterations, (resource, cost)| {
+        let income = income(state, resource).as_hundredths();
+
+        match income > 0 {
+            true => Some(u64::max(iterations, ((cost.as_hundredths() + income - 1) / income) as u64)),
+            false => None
+        }
+    });
+
+    Payback {
+        name,
+        benefits,
+        costs: total.into_iter().map(|(resource, cost)| (resource.name(), cost)).collect(),
+        iterations
+    }
+}
+
+/**
+ * Amount of the resource the game gains on each iteration. Resources only found
+ * while gathering others have no steady income.
+ */
+fn income(state: &State, resource: &Resource) -> Quantity {
+    let resources = &state.resources;
+    let citizens = state.citizens();
+    let morale = citizens.morale(state.land());
+
+    match resource {
+        Resource::Primary(PrimaryResource::Food) => resources.food_net_rate(morale),
+        Resource::Primary(PrimaryResource::Wood) => resources.wood_prod_rate * resources.wood_prod_rate_multiplier * morale,
+        Resource::Primary(PrimaryResource::Stone) => resources.stone_prod_rate * resources.stone_prod_rate_multiplier * morale,
+        Resource::Tertiary(TertiaryResource::Metal) => Job::Blacksmith.get_production_rate() * citizens.blacksmiths,
+        Resource::Tertiary(TertiaryResource::Piety) => Job::Cleric.get_production_rate() * citizens.clerics,
+        _ => Quantity::ZERO
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::quantity::Quantity;
+    use crate::game::buildings::Buildings;
+    use crate::game::jobs::Job;
+    use crate::game::settings::Settings;
+    use super::roi;
+
+    #[test]
+    fn roi_pays_costs_with_current_income() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{(Action::Build(Buildings::Tent), 4), (Action::RecruitCitizen, 4), (Action::AssignJob(Job::Woodcutter), 4)}).unwrap();
+        let paybacks = roi(&state);
+        let payback = |name: &str| paybacks.iter().find(|payback| payback.name == name).unwrap();
+        let wood_income = state.clone().apply_action(Action::Idle).unwrap().resources.wood - state.resources.wood;
+
+        let barn = payback("Barn").iterations.unwrap();
+        assert!(wood_income * barn >= Quantity::units(100) && wood_income * (barn - 1) < Quantity::units(100));
+        assert_eq!(payback("Barn").benefits, vec!{"+100.00 max food".to_string()});
+        assert_eq!(payback("Tent").iterations, None);
+        assert_eq!(payback("Blacksmith").costs["food"], Quantity::units(40));
+        assert!(paybacks.iter().all(|payback| payback.name != "Scholar"));
+    }
+}
@@ -0,0 +1,222 @@
+use super::buildings::Buildings;
+use super::jobs::{CitizenClass, Specialization, Job};
+use super::resources::{Resources, Resource, PrimaryResource, SecondaryResource};
+use super::quantity::Quantity;
+use super::hashing::{Digest, StableHasher};
+
+use serde::{Serialize, Deserialize};
+use rand::distributions::Bernoulli;
+use std::hash::Hasher;
+
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/**
+ * Cost of each parcel of land added to the starting land, however it is added. The
+ * first one costs the base, and every one after costs a step more than the last, plus
+ * a surcharge growing with the square of the parcels added before it.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CostCurve {
+    pub base: Quantity,
+    pub step: Quantity,
+    pub surcharge: Quantity
+}
+
+impl CostCurve {
+    /**
+     * Cost of the n-th parcel added, counting from 1.
+     */
+    pub fn cost(&self, parcel: u64) -> Quantity {
+        let added = parcel.saturating_sub(1);
+        self.base + self.step * added + self.surcharge * (added * added)
+    }
+}
+
+pub const LAND_EXPANSION: CostCurve = CostCurve {
+    base: Quantity::ONE,
+    step: Quantity::ONE,
+    surcharge: Quantity::hundredths(1)
+};
+
+/**
+ * Resource that may turn up while gathering a primary one: it is rolled for with every
+ * unit gathered, and found with the chance in the given number of units.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Drop {
+    pub resource: Resource,
+    pub chance: Quantity,
+    pub units: u64
+}
+
+impl Drop {
+    pub fn distribution(&self) -> Bernoulli {
+        Bernoulli::from_ratio(self.chance.as_hundredths().clamp(0, 100) as u32, 100).unwrap()
+    }
+}
+
+/**
+ * Drops of each primary resource, rolled for in the order listed.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DropTable {
+    pub food: Vec<Drop>,
+    pub wood: Vec<Drop>,
+    pub stone: Vec<Drop>
+}
+
+impl DropTable {
+    /**
+     * Skins with food, herbs with wood and ore with stone, one unit in ten draws.
+     */
+    pub fn standard() -> Self {
+        let drop = |resource| vec!{Drop { resource: Resource::Secondary(resource), chance: Quantity::hundredths(10), units: 1 }};
+
+        Self { food: drop(SecondaryResource::Skins), wood: drop(SecondaryResource::Herbs), stone: drop(SecondaryResource::Ore) }
+    }
+
+    pub fn drops(&self, resource: &PrimaryResource) -> &[Drop] {
+        match resource {
+            PrimaryResource::Food => &self.food,
+            PrimaryResource::Wood => &self.wood,
+            PrimaryResource::Stone => &self.stone
+        }
+    }
+}
+
+/**
+ * Version of the engine a game was created with, along with a digest of the balance
+ * tables it was played under. A log only replays to the same hashes under the same
+ * tables. Games created before rules were stamped have none, and are left unchecked.
+ */
+#[derive(Serialize, Deserialize, Default, Clone, Debug, Eq, PartialEq)]
+pub struct Rules {
+    pub engine: String,
+    pub digest: Digest
+}
+
+impl Rules {
+    pub fn current() -> Self {
+        Self { engine: ENGINE_VERSION.to_string(), digest: Self::tables_digest() }
+    }
+
+    pub fn is_stamped(&self) -> bool {
+        !self.engine.is_empty()
+    }
+
+    /**
+     * Whether a log played under these rules replays the same now. Only the tables
+     * matter; a different engine version alone is worth a warning at most.
+     */
+    pub fn is_compatible(&self) -> bool {
+        !self.is_stamped() || self.digest == Self::tables_digest()
+    }
+
+    fn write_amounts(hasher: &mut StableHasher, amounts: Vec<(Resource, Quantity)>) {
+        hasher.write_usize(amounts.len());
+        for (resource, amount) in amounts {
+            hasher.write_sized(resource.name().as_bytes());
+            hasher.write(&amount.to_le_bytes());
+        }
+    }
+
+    /**
+     * Digest of every cost, rate and capacity the game is balanced with.
+     */
+    fn tables_digest() -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write(&Resources::default().hash().0);
+
+        for building in Buildings::all() {
+            Self::write_amounts(&mut hasher, building.costs());
+            hasher.write_u64(building.population_capacity_increase());
+            hasher.write(&building.housing_quality().to_le_bytes());
+            hasher.write_u64(building.training_capacity_increase());
+            hasher.write(&building.water_production_increase().to_le_bytes());
+            hasher.write(&building.water_storage_increase().to_le_bytes());
+            hasher.write(&building.food_spoilage_multiplier().to_le_bytes());
+            Self::write_amounts(&mut hasher, building.resource_storage_increase());
+        }
+
+        for class in CitizenClass::all() {
+            Self::write_amounts(&mut hasher, class.upkeep());
+        }
+
+        for specialization in Specialization::all() {
+            hasher.write_u64(specialization.training_iterations());
+            Self::write_amounts(&mut hasher, specialization.training_costs());
+        }
+
+        for job in Job::all() {
+            hasher.write(&job.get_production_rate().to_le_bytes());
+            hasher.write(&job.get_water_consumption().to_le_bytes());
+        }
+
+        let table = DropTable::standard();
+        for drops in [&table.food, &table.wood, &table.stone] {
+            hasher.write_usize(drops.len());
+            for drop in drops {
+                hasher.write_sized(drop.resource.name().as_bytes());
+                hasher.write(&drop.chance.to_le_bytes());
+                hasher.write_u64(drop.units);
+            }
+        }
+
+        hasher.write(&LAND_EXPANSION.base.to_le_bytes());
+        hasher.write(&LAND_EXPANSION.step.to_le_bytes());
+        hasher.write(&LAND_EXPANSION.surcharge.to_le_bytes());
+
+        hasher.digest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::{State, Context};
+    use crate::game::actions::Action;
+    use crate::game::resources::{Resource, PrimaryResource, SecondaryResource, TertiaryResource, Resources};
+    use crate::game::quantity::Quantity;
+    use crate::game::errors::CheckError;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use crate::game::hashing::Digest;
+    use super::{Drop, DropTable};
+
+    #[test]
+    fn drop_tables_can_drop_several_units_of_any_resource() {
+        let piety = Drop { resource: Resource::Tertiary(TertiaryResource::Piety), chance: Quantity::ONE, units: 2 };
+        let mut ctx = Context {
+            rng: ChaChaRng::seed_from_u64(7),
+            sandbox: false,
+            morale: Quantity::ONE,
+            birth_rate: Quantity::ZERO,
+            soft_cap: None,
+            fatigue: false,
+            construction: false,
+            pity: false,
+            gold_strikes: false,
+            events: vec!{},
+            drops: DropTable { food: vec!{piety.clone()}, ..DropTable::standard() },
+            draws: vec!{}
+        };
+        let resources = Resources::default().apply_action(&Action::Collect(PrimaryResource::Food), &mut ctx).unwrap();
+
+        assert_eq!(resources.food, Quantity::ONE);
+        assert_eq!(resources.piety, Quantity::units(2));
+        assert_eq!(resources.skins, Quantity::ZERO);
+        assert_eq!(ctx.draws, vec!{(piety, 1, 1)});
+        assert_eq!(DropTable::standard().drops(&PrimaryResource::Stone)[0].resource, Resource::Secondary(SecondaryResource::Ore));
+    }
+
+    #[test]
+    fn other_rules_are_refused() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        let save = serde_json::to_string(&state).unwrap()
+            .replace(&state.rules().digest.to_hex(), &Digest::default().to_hex());
+        let loaded: State = serde_json::from_str(&save).unwrap();
+
+        assert!(state.check().is_ok());
+        assert!(matches!(loaded.check(), Err(CheckError::RulesMismatch(_))));
+    }
+}
@@ -0,0 +1,97 @@
+use super::state::Commitment;
+use super::resources::Resources;
+use super::citizens::Citizens;
+use super::errors::CheckError;
+use super::hashing::{Digest, StableHasher};
+use super::signing::SaveSignature;
+use super::merkle::ActionProof;
+
+use serde::{Serialize, Deserialize};
+use std::hash::Hasher;
+
+// Actions of the log proven in every run proof.
+const SAMPLED_ACTIONS: u64 = 8;
+
+/**
+ * Compact proof of a run, small enough for leaderboard submissions. It holds what the
+ * final hash commits to, with the resources and citizens in full so the outcome can be
+ * read, and inclusion proofs of a few actions picked by the final hash so they can't be
+ * chosen. It shows the outcome belongs to the signed hash and the log root; replaying
+ * the log is still the only way to show the run was played by the rules.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct RunProof {
+    pub commitment: Commitment,
+    pub resources: Resources,
+    pub citizens: Citizens,
+    pub actions: u64,
+    pub hash: Digest,
+    pub signature: Option<SaveSignature>,
+    pub samples: Vec<ActionProof>
+}
+
+impl RunProof {
+    /**
+     * Positions of the actions to prove, derived from the final hash.
+     */
+    pub fn sampled_actions(hash: &Digest, actions: u64) -> Vec<u64> {
+        if actions == 0 {
+            return vec!{};
+        }
+
+        (0..SAMPLED_ACTIONS.min(actions)).map(|sample| {
+            let mut hasher = StableHasher::default();
+            hasher.write(&hash.0);
+            hasher.write_u64(sample);
+            hasher.finish() % actions
+        }).collect()
+    }
+
+    pub fn verify(&self) -> Result<(), CheckError> {
+        let hash = self.commitment.hash();
+
+        if hash != self.hash {
+            return Err(CheckError::HashMismatch { iteration: self.commitment.iterations, expected: self.hash, actual: hash, differences: vec!{} });
+        }
+
+        if self.resources.hash() != self.commitment.resources || self.citizens.hash() != self.commitment.citizens {
+            return Err(CheckError::InvalidProof);
+        }
+
+        if let Some(signature) = &self.signature {
+            if !signature.verify(&self.hash) {
+                return Err(CheckError::InvalidSignature);
+            }
+        }
+
+        let indices: Vec<u64> = self.samples.iter().map(|sample| sample.index).collect();
+        let proven = self.samples.iter().all(|sample| sample.verify(&self.commitment.log_root));
+
+        match proven && indices == Self::sampled_actions(&self.hash, self.actions) {
+            true => Ok(()),
+            false => Err(CheckError::InvalidProof)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::quantity::Quantity;
+    use crate::game::errors::CheckError;
+
+    #[test]
+    fn run_proof_verifies() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 30), (Action::Collect(PrimaryResource::Wood), 30)}).unwrap();
+        let mut proof = state.run_proof();
+
+        assert_eq!(proof.samples.len(), 8);
+        assert!(proof.verify().is_ok());
+
+        proof.resources.food += Quantity::ONE;
+        assert!(matches!(proof.verify(), Err(CheckError::InvalidProof)));
+    }
+}
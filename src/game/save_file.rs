@@ -0,0 +1,386 @@
+use super::state::State;
+use super::errors::Error;
+use super::log_file::LogFile;
+use super::database::Database;
+
+use std::io::{Read, Write};
+use std::str::FromStr;
+use argon2::Argon2;
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, aead::{Aead, KeyInit}};
+use rand::{thread_rng, RngCore};
+use serde_json::{from_slice, to_vec_pretty};
+use flate2::{read::GzDecoder, write::GzEncoder};
+
+// Prefix of encrypted saves, followed by the key salt, the nonce and the ciphertext.
+const ENCRYPTED_MAGIC: &[u8] = b"CLICIV-ENCRYPTED-1\n";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+// Headers written by the compressors themselves.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_LEVEL: i32 = 19;
+
+// Prefixes of the binary formats. JSON saves have none.
+const BINCODE_MAGIC: &[u8] = b"CLICIV-BINCODE-1\n";
+const MESSAGEPACK_MAGIC: &[u8] = b"CLICIV-MSGPACK-1\n";
+
+/**
+ * Encoding of the state inside a save. JSON is the default, as it can be inspected by
+ * hand, while the binary formats keep long games small.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SaveFormat {
+    #[default]
+    Json,
+    Bincode,
+    MessagePack
+}
+
+impl SaveFormat {
+    fn magic(&self) -> &'static [u8] {
+        match self {
+            Self::Json => b"",
+            Self::Bincode => BINCODE_MAGIC,
+            Self::MessagePack => MESSAGEPACK_MAGIC,
+        }
+    }
+
+    fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(BINCODE_MAGIC) {
+            Self::Bincode
+        } else if bytes.starts_with(MESSAGEPACK_MAGIC) {
+            Self::MessagePack
+        } else {
+            Self::Json
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<State, Error> {
+        let bytes = &bytes[self.magic().len()..];
+
+        match self {
+            Self::Json => from_slice(bytes).map_err(Error::InputParseError),
+            Self::Bincode => bincode::deserialize(bytes).map_err(|error| Error::SaveDecode(error.to_string())),
+            Self::MessagePack => rmp_serde::from_slice(bytes).map_err(|error| Error::SaveDecode(error.to_string())),
+        }
+    }
+
+    /**
+     * Decodes the state without its log. Bincode saves can't be skipped through, so
+     * they are decoded whole.
+     */
+    fn decode_without_log(&self, bytes: &[u8]) -> Result<State, Error> {
+        let body = &bytes[self.magic().len()..];
+
+        match self {
+            Self::Json => State::deserialize_without_log(&mut serde_json::Deserializer::from_slice(body))
+                .map_err(Error::InputParseError),
+            Self::Bincode => Ok(self.decode(bytes)?.with_log(vec!{})),
+            Self::MessagePack => State::deserialize_without_log(&mut rmp_serde::Deserializer::new(body))
+                .map_err(|error| Error::SaveDecode(error.to_string())),
+        }
+    }
+
+    fn encode(&self, state: &State) -> Result<Vec<u8>, Error> {
+        let body = match self {
+            Self::Json => to_vec_pretty(state).map(|mut json| { json.push(b'\n'); json })
+                .map_err(|error| Error::SaveEncode(error.to_string())),
+            Self::Bincode => bincode::serialize(state).map_err(|error| Error::SaveEncode(error.to_string())),
+            Self::MessagePack => rmp_serde::to_vec(state).map_err(|error| Error::SaveEncode(error.to_string())),
+        }?;
+
+        Ok([self.magic(), &body].concat())
+    }
+}
+
+impl FromStr for SaveFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "json" => Ok(Self::Json),
+            "bincode" => Ok(Self::Bincode),
+            "messagepack" => Ok(Self::MessagePack),
+            _ => Err(name.to_string()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    Zstd,
+    Gzip
+}
+
+impl Compression {
+    fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(ZSTD_MAGIC) {
+            Some(Self::Zstd)
+        } else if bytes.starts_with(GZIP_MAGIC) {
+            Some(Self::Gzip)
+        } else {
+            None
+        }
+    }
+
+    fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Zstd => zstd::encode_all(bytes, ZSTD_LEVEL).map_err(Error::Io),
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(vec!{}, flate2::Compression::best());
+                encoder.write_all(bytes).map_err(Error::Io)?;
+                encoder.finish().map_err(Error::Io)
+            }
+        }
+    }
+
+    fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Zstd => zstd::decode_all(bytes).map_err(Error::Io),
+            Self::Gzip => {
+                let mut decompressed = vec!{};
+                GzDecoder::new(bytes).read_to_end(&mut decompressed).map_err(Error::Io)?;
+                Ok(decompressed)
+            }
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "zstd" => Ok(Self::Zstd),
+            "gzip" => Ok(Self::Gzip),
+            _ => Err(name.to_string()),
+        }
+    }
+}
+
+/**
+ * Reads and writes saves, optionally compressed and encrypted with a key derived from
+ * a passphrase. The format, compression and encryption of a save are detected on
+ * read, and the save is written back the same way unless told otherwise. With a log
+ * file the action log is kept there instead of in the save. With a database the game
+ * is read from and written to it instead of the given reader and writer.
+ */
+#[derive(Clone, Default)]
+pub struct SaveFile {
+    pub encrypted: bool,
+    pub format: Option<SaveFormat>,
+    pub compression: Option<Compression>,
+    pub log_file: Option<LogFile>,
+    pub database: Option<Database>
+}
+
+impl SaveFile {
+    pub fn read<R: Read>(&mut self, reader: R) -> Result<State, Error> {
+        if let Some(database) = &self.database {
+            return database.load();
+        }
+
+        let (bytes, format) = self.read_bytes(reader)?;
+        let state = format.decode(&bytes)?;
+
+        match &self.log_file {
+            Some(log_file) => log_file.attach(state),
+            None => Ok(state)
+        }
+    }
+
+    /**
+     * Reads the state without materializing its log, for commands that only look at
+     * the latest state. The log file, if any, is left alone.
+     */
+    pub fn read_without_log<R: Read>(&mut self, reader: R) -> Result<State, Error> {
+        if let Some(database) = &self.database {
+            return database.load_without_log();
+        }
+
+        let (bytes, format) = self.read_bytes(reader)?;
+        format.decode_without_log(&bytes)
+    }
+
+    /**
+     * Decrypted and decompressed contents of the save, with the format they are in.
+     */
+    fn read_bytes<R: Read>(&mut self, mut reader: R) -> Result<(Vec<u8>, SaveFormat), Error> {
+        let mut bytes = vec!{};
+        reader.read_to_end(&mut bytes).map_err(Error::Io)?;
+
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return Err(Error::NoGame);
+        }
+
+        if bytes.starts_with(ENCRYPTED_MAGIC) {
+            self.encrypted = true;
+            bytes = decrypt(&bytes[ENCRYPTED_MAGIC.len()..], &passphrase()?)?;
+        }
+
+        if let Some(compression) = Compression::detect(&bytes) {
+            self.compression.get_or_insert(compression);
+            bytes = compression.decompress(&bytes)?;
+        }
+
+        let format = SaveFormat::detect(&bytes);
+        self.format.get_or_insert(format);
+
+        Ok((bytes, format))
+    }
+
+    pub fn write<W: Write>(&self, state: &State, mut writer: W) -> Result<(), Error> {
+        if let Some(database) = &self.database {
+            return database.store(state);
+        }
+
+        let mut bytes = match &self.log_file {
+            Some(log_file) => {
+                log_file.append(state)?;
+                self.format.unwrap_or_default().encode(&state.without_log())?
+            },
+            None => self.format.unwrap_or_default().encode(state)?
+        };
+
+        if let Some(compression) = self.compression {
+            bytes = compression.compress(&bytes)?;
+        }
+
+        if self.encrypted {
+            bytes = [ENCRYPTED_MAGIC, &encrypt(&bytes, &passphrase()?)?].concat();
+        }
+
+        writer.write_all(&bytes).map_err(Error::Io)
+    }
+}
+
+/**
+ * Passphrase taken from `CLICIV_PASSPHRASE` or, when it is not set, asked on the
+ * terminal, as the standard input holds the save.
+ */
+fn passphrase() -> Result<String, Error> {
+    match std::env::var("CLICIV_PASSPHRASE") {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => rpassword::prompt_password("Passphrase: ").map_err(Error::Io)
+    }
+}
+
+fn cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, Error> {
+    let mut key = [0u8; 32];
+    Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|_| Error::Encryption)?;
+
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    thread_rng().fill_bytes(&mut salt);
+    thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher(passphrase, &salt)?
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| Error::Encryption)?;
+
+    Ok([&salt[..], &nonce[..], &ciphertext].concat())
+}
+
+fn decrypt(bytes: &[u8], passphrase: &str) -> Result<Vec<u8>, Error> {
+    if bytes.len() < SALT_LENGTH + NONCE_LENGTH {
+        return Err(Error::Decryption);
+    }
+
+    let (salt, rest) = bytes.split_at(SALT_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+
+    cipher(passphrase, salt)?
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::Decryption)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::errors::Error;
+    use super::{SaveFile, SaveFormat, Compression};
+
+    #[test]
+    fn encrypted_save_round_trips() {
+        std::env::set_var("CLICIV_PASSPHRASE", "correct horse battery staple");
+
+        let state = State::new(43932030939219715774207308070970463251);
+        let mut bytes = vec!{};
+        SaveFile { encrypted: true, ..Default::default() }.write(&state, &mut bytes).unwrap();
+
+        let mut save_file = SaveFile::default();
+        let loaded = save_file.read(&bytes[..]).unwrap();
+
+        assert!(save_file.encrypted);
+        assert_eq!(loaded.hash(), state.hash());
+    }
+
+    #[test]
+    fn binary_saves_round_trip() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+
+        for format in [SaveFormat::Bincode, SaveFormat::MessagePack] {
+            let mut bytes = vec!{};
+            SaveFile { format: Some(format), ..Default::default() }.write(&state, &mut bytes).unwrap();
+
+            let mut save_file = SaveFile::default();
+            let loaded = save_file.read(&bytes[..]).unwrap();
+
+            assert_eq!(save_file.format, Some(format));
+            assert_eq!(loaded.hash(), state.hash());
+            assert!(loaded.check().is_ok());
+        }
+    }
+
+    #[test]
+    fn compressed_saves_round_trip() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+
+        for compression in [Compression::Zstd, Compression::Gzip] {
+            let mut bytes = vec!{};
+            SaveFile { compression: Some(compression), ..Default::default() }.write(&state, &mut bytes).unwrap();
+
+            let mut save_file = SaveFile::default();
+            let loaded = save_file.read(&bytes[..]).unwrap();
+
+            assert_eq!(save_file.compression, Some(compression));
+            assert_eq!(loaded.hash(), state.hash());
+        }
+    }
+
+    #[test]
+    fn saves_are_read_without_their_log() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 20), (Action::Collect(PrimaryResource::Wood), 5)}).unwrap();
+
+        for format in [SaveFormat::Json, SaveFormat::Bincode, SaveFormat::MessagePack] {
+            let mut save_file = SaveFile { format: Some(format), ..Default::default() };
+            let mut bytes = vec!{};
+            save_file.write(&state, &mut bytes).unwrap();
+            let peeked = save_file.read_without_log(&bytes[..]).unwrap();
+
+            assert!(peeked.log().is_empty());
+            assert_eq!(peeked.hash(), state.hash());
+            assert_eq!(peeked.recent_events().len(), state.recent_events().len());
+        }
+    }
+
+    #[test]
+    fn reading_without_a_save_is_an_error() {
+        let error = SaveFile::default().read(std::io::empty()).err().unwrap();
+
+        assert!(matches!(error, Error::NoGame));
+        assert_eq!(error.exit_code(), 3);
+        assert!(error.to_string().contains("cliciv create"));
+    }
+}
@@ -0,0 +1,172 @@
+use super::resources::Resources;
+use super::citizens::Citizens;
+use super::land::Land;
+use super::quantity::Quantity;
+use super::state::{Context, LogEntry};
+use super::rules::DropTable;
+
+use rand_chacha::ChaChaRng;
+
+use serde::{Serialize, Deserialize};
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub enum Scenario {
+    RiverValley,
+    Highlands,
+    Island,
+    Imported(Box<Import>),
+}
+
+/**
+ * Game brought over from another one, described by the actions that set it up. They
+ * are taken for free before the first iteration and are not logged, so the game can
+ * be rebuilt from its settings like any other. Actions that can't be taken, like
+ * buildings that don't fit in the land, are skipped.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct Import {
+    pub source: String,
+    pub setup: Vec<LogEntry>
+}
+
+impl Import {
+    pub fn set_up(&self, resources: Resources, citizens: Citizens, land: Land, rng: &ChaChaRng) -> (Resources, Citizens, Land) {
+        let mut ctx = Context {
+            rng: rng.clone(),
+            sandbox: true,
+            morale: Quantity::ONE,
+            birth_rate: Quantity::ZERO,
+            soft_cap: None,
+            fatigue: false,
+            construction: false,
+            pity: false,
+            gold_strikes: false,
+            events: vec!{},
+            drops: DropTable::standard(),
+            draws: vec!{}
+        };
+        let mut start = (resources, citizens, land);
+
+        for (action, count) in &self.setup {
+            for _ in 0..*count {
+                let (resources, citizens, land) = start.clone();
+                let taken = resources.apply_action(action, &mut ctx).and_then(|resources| {
+                    Ok((resources, citizens.apply_action(action, &mut ctx)?, land.apply_action(action, &mut ctx)?))
+                });
+
+                if let Ok(taken) = taken {
+                    start = taken;
+                }
+            }
+        }
+
+        start
+    }
+}
+
+impl Scenario {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RiverValley => "river-valley",
+            Self::Highlands => "highlands",
+            Self::Island => "island",
+            Self::Imported(_) => "imported",
+        }
+    }
+
+    /**
+     * River valleys are fertile and well watered, highlands are rich in stone but dry
+     * and islands start with some fish in store but very little land. Imported games
+     * start empty-handed, and are given what they had by their setup.
+     */
+    pub fn configure_resources(&self, resources: Resources) -> Resources {
+        match self {
+            Self::RiverValley => Resources {
+                food: Quantity::units(50),
+                water_prod_rate: Quantity::units(8),
+                max_water: Quantity::units(300),
+                ..resources
+            },
+            Self::Highlands => Resources {
+                stone: Quantity::units(50),
+                max_stone: resources.max_stone + Quantity::units(100),
+                ore: resources.ore + Quantity::units(10),
+                water_prod_rate: Quantity::units(3),
+                ..resources
+            },
+            Self::Island => Resources {
+                food: Quantity::units(30),
+                wood: Quantity::units(20),
+                water_prod_rate: Quantity::units(4),
+                ..resources
+            },
+            Self::Imported(_) => Resources {
+                food: Quantity::ZERO,
+                wood: Quantity::ZERO,
+                stone: Quantity::ZERO,
+                skins: Quantity::ZERO,
+                herbs: Quantity::ZERO,
+                ore: Quantity::ZERO,
+                leather: Quantity::ZERO,
+                piety: Quantity::ZERO,
+                metal: Quantity::ZERO,
+                gold: Quantity::ZERO,
+                corpses: Quantity::ZERO,
+                ..resources
+            },
+        }
+    }
+
+    /**
+     * What gathering turns up besides the resource gathered. Every scenario rolls the
+     * standard table for now, as changing the table of a scenario changes how the
+     * logs of its games replay.
+     */
+    pub fn drop_table(&self) -> DropTable {
+        DropTable::standard()
+    }
+
+    pub fn configure_land(&self, land: Land) -> Land {
+        match self {
+            Self::RiverValley => land,
+            Self::Highlands => Land { total_land: land.total_land - 200, ..land },
+            Self::Island => Land { total_land: land.total_land / 2, ..land },
+            Self::Imported(_) => land,
+        }
+    }
+}
+
+impl FromStr for Scenario {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "river-valley" => Ok(Self::RiverValley),
+            "highlands" => Ok(Self::Highlands),
+            "island" => Ok(Self::Island),
+            _ => Err(name.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::settings::Settings;
+    use super::Scenario;
+
+    #[test]
+    fn scenario_survives_check() {
+        let settings = Settings { scenario: Some(Scenario::Island), ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Wood), 20),
+                (Action::Idle, 10),
+            }).unwrap();
+
+        assert!(state.check().is_ok());
+    }
+}
@@ -0,0 +1,95 @@
+use super::state::State;
+use super::resources::{Resource, PrimaryResource};
+use super::quantity::Quantity;
+
+// Seconds since the save was last played after which a player is greeted back.
+pub const RESUME_GAP: u64 = 60 * 60;
+
+/**
+ * Short summary of the game for a player coming back to it, along with whatever needs
+ * attention. Nothing is said when the save was played within the last hour, or has
+ * never been played.
+ */
+pub fn resume_summary(state: &State, now: u64) -> Option<String> {
+    let gap = now.checked_sub(state.saved_at()).filter(|gap| state.saved_at() > 0 && *gap >= RESUME_GAP)?;
+    let citizens = state.citizens();
+    let food_rate = state.resources.food_net_rate(citizens.morale(state.land()));
+    let trend = match food_rate > Quantity::ZERO {
+        true => format!("+{}", food_rate),
+        false => food_rate.to_string()
+    };
+
+    let mut summary = format!("Welcome back after {}: iteration {}, {} citizens, food trending {}/i",
+        elapsed(gap), state.iterations(), state.population().0, trend);
+
+    for warning in warnings(state) {
+        summary.push_str("\n\t");
+        summary.push_str(&warning);
+    }
+
+    Some(summary)
+}
+
+/**
+ * What is going wrong, or about to, in the game.
+ */
+pub fn warnings(state: &State) -> Vec<String> {
+    let resources = &state.resources;
+    let mut warnings = vec!{};
+
+    if let Some(turns) = state.food_left() {
+        warnings.push(format!("food runs out in {} turns", turns));
+    }
+
+    if resources.water_cons_rate > resources.water_prod_rate {
+        warnings.push(format!("water is drunk at {}/i but only {}/i flows in", resources.water_cons_rate, resources.water_prod_rate));
+    }
+
+    for resource in [PrimaryResource::Food, PrimaryResource::Wood, PrimaryResource::Stone].iter().cloned().map(Resource::Primary) {
+        if resources.capacity(&resource).is_some_and(|capacity| resources.amount(&resource) >= capacity) {
+            warnings.push(format!("{} storage is full", resource.name()));
+        }
+    }
+
+    if state.citizens().idle > 0 {
+        warnings.push(format!("{} citizens are idle", state.citizens().idle));
+    }
+
+    warnings
+}
+
+/**
+ * Rough length of a stretch of time, in the largest unit that fits it twice.
+ */
+pub fn elapsed(seconds: u64) -> String {
+    match seconds {
+        seconds if seconds >= 2 * 24 * 60 * 60 => format!("{} days", seconds / (24 * 60 * 60)),
+        seconds if seconds >= 2 * 60 * 60 => format!("{} hours", seconds / (60 * 60)),
+        seconds => format!("{} minutes", seconds / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::buildings::Buildings;
+    use crate::game::settings::Settings;
+    use super::{resume_summary, warnings};
+
+    #[test]
+    fn resuming_after_a_while_summarizes_the_game() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{(Action::Build(Buildings::Tent), 2), (Action::RecruitCitizen, 2)}).unwrap()
+            .stamp(1_000_000);
+
+        assert!(resume_summary(&state, 1_000_000 + 60).is_none());
+        assert!(resume_summary(&state.clone().stamp(0), 1_000_000).is_none());
+
+        let summary = resume_summary(&state, 1_000_000 + 3 * 24 * 60 * 60).unwrap();
+        assert!(summary.starts_with("Welcome back after 3 days: iteration 4, 2 citizens, food trending -2.00/i"));
+        assert!(summary.contains("2 citizens are idle"));
+        assert_eq!(summary.lines().count(), 1 + warnings(&state).len());
+    }
+}
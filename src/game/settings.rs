@@ -0,0 +1,122 @@
+use super::scenarios::Scenario;
+use super::rules::{Drop, DropTable};
+use super::resources::{Resource, SpecialResource, GEM_CHANCE};
+use super::hashing::{Digest, StableHasher};
+
+use serde::{Serialize, Deserialize};
+use std::hash::Hasher;
+use std::convert::TryInto;
+
+// Iterations a daily challenge can be played for.
+pub const DAILY_TURNS: usize = 500;
+
+// Mixed into the date to derive the seed of the daily challenge.
+const DAILY_SALT: &[u8] = b"cliciv daily challenge";
+
+/**
+ * Options chosen when the game is created. They never change afterwards.
+ */
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Settings {
+    pub scenario: Option<Scenario>,
+    pub sandbox: bool,
+    pub ironman: bool,
+    pub free_management: bool,
+    // UTC date of the daily challenge the game plays, as `YYYY-MM-DD`.
+    #[serde(default)]
+    pub daily: Option<String>,
+    // Workers on a laborer job past which each new one produces less than half.
+    #[serde(default)]
+    pub soft_cap: Option<u64>,
+    // Whether workers tire during long stretches on the same job.
+    #[serde(default)]
+    pub fatigue: bool,
+    // Whether buildings take builders and time to put up instead of appearing at once.
+    #[serde(default)]
+    pub construction: bool,
+    // Whether a secondary resource missed for too many draws in a row is found for sure.
+    #[serde(default)]
+    pub pity: bool,
+    // Whether miners may strike gold.
+    #[serde(default)]
+    pub gold_strikes: bool,
+    // Whether gems may turn up while mining.
+    #[serde(default)]
+    pub gems: bool
+}
+
+impl Settings {
+    /**
+     * Daily challenge of the given date: an ironman game with a limited number of
+     * turns, on the same map for everyone, as given by `daily_seed`.
+     */
+    pub fn daily(date: &str) -> Self {
+        Self { ironman: true, daily: Some(date.to_string()), ..Default::default() }
+    }
+
+    /**
+     * Iterations the game can last, if limited.
+     */
+    pub fn turn_budget(&self) -> Option<usize> {
+        self.daily.as_ref().map(|_| DAILY_TURNS)
+    }
+
+    /**
+     * Sandbox games can't take part in challenges nor rankings.
+     */
+    pub fn is_ranked(&self) -> bool {
+        !self.sandbox
+    }
+
+    /**
+     * What gathering turns up besides the resource gathered: the drops of the
+     * scenario, and gems with stone when they are played with.
+     */
+    pub fn drop_table(&self) -> DropTable {
+        let mut drops = self.scenario.as_ref().map_or_else(DropTable::standard, Scenario::drop_table);
+
+        if self.gems {
+            drops.stone.push(Drop { resource: Resource::Special(SpecialResource::Gems), chance: GEM_CHANCE, units: 1 });
+        }
+
+        drops
+    }
+
+    pub fn hash(&self) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write_u8(self.scenario.is_some() as u8);
+        if let Some(scenario) = &self.scenario {
+            hasher.write_sized(scenario.name().as_bytes());
+        }
+        if let Some(Scenario::Imported(import)) = &self.scenario {
+            hasher.write_sized(&serde_json::to_vec(import).unwrap());
+        }
+        hasher.write_u8(self.sandbox as u8);
+        hasher.write_u8(self.ironman as u8);
+        hasher.write_u8(self.free_management as u8);
+        hasher.write_u8(self.daily.is_some() as u8);
+        if let Some(date) = &self.daily {
+            hasher.write_sized(date.as_bytes());
+            hasher.write_usize(DAILY_TURNS);
+        }
+        hasher.write_u8(self.soft_cap.is_some() as u8);
+        hasher.write_u64(self.soft_cap.unwrap_or_default());
+        hasher.write_u8(self.fatigue as u8);
+        hasher.write_u8(self.construction as u8);
+        hasher.write_u8(self.pity as u8);
+        hasher.write_u8(self.gold_strikes as u8);
+        hasher.write_u8(self.gems as u8);
+        hasher.digest()
+    }
+}
+
+/**
+ * Seed of the daily challenge of the given date, the same for every player.
+ */
+pub fn daily_seed(date: &str) -> i128 {
+    let mut hasher = StableHasher::default();
+    hasher.write(DAILY_SALT);
+    hasher.write(date.as_bytes());
+
+    i128::from_le_bytes(hasher.digest().0[..16].try_into().unwrap())
+}
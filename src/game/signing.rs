@@ -0,0 +1,84 @@
+use super::hashing::Digest;
+
+use serde::{Serialize, Deserialize};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::{thread_rng, RngCore};
+
+/**
+ * Ed25519 signature of a state hash, along with the public key of whoever signed it so
+ * shared saves can be attributed.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct SaveSignature {
+    pub public_key: String,
+    pub signature: String
+}
+
+impl SaveSignature {
+    pub fn sign(key: &SigningKey, digest: &Digest) -> Self {
+        Self {
+            public_key: hex::encode(key.verifying_key().as_bytes()),
+            signature: hex::encode(key.sign(&digest.0).to_bytes())
+        }
+    }
+
+    pub fn verify(&self, digest: &Digest) -> bool {
+        let public_key = match parse_public_key(&self.public_key) {
+            Some(public_key) => public_key,
+            None => return false
+        };
+
+        let mut signature = [0u8; 64];
+
+        if hex::decode_to_slice(&self.signature, &mut signature).is_err() {
+            return false;
+        }
+
+        public_key.verify(&digest.0, &Signature::from_bytes(&signature)).is_ok()
+    }
+}
+
+pub fn generate_key() -> SigningKey {
+    let mut secret = [0u8; 32];
+    thread_rng().fill_bytes(&mut secret);
+
+    SigningKey::from_bytes(&secret)
+}
+
+pub fn parse_signing_key(hex: &str) -> Option<SigningKey> {
+    let mut secret = [0u8; 32];
+    hex::decode_to_slice(hex.trim(), &mut secret).ok()?;
+
+    Some(SigningKey::from_bytes(&secret))
+}
+
+pub fn parse_public_key(hex: &str) -> Option<VerifyingKey> {
+    let mut public_key = [0u8; 32];
+    hex::decode_to_slice(hex.trim(), &mut public_key).ok()?;
+
+    VerifyingKey::from_bytes(&public_key).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::errors::CheckError;
+    use super::generate_key;
+
+    #[test]
+    fn signed_state_verifies() {
+        let key = generate_key();
+        let other_key = generate_key();
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap()
+            .sign(&key);
+
+        assert!(state.verify(&hex::encode(key.verifying_key().as_bytes())).is_ok());
+        assert!(matches!(
+            state.verify(&hex::encode(other_key.verifying_key().as_bytes())),
+            Err(CheckError::UnexpectedSigner)
+        ));
+    }
+}
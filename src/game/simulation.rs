@@ -0,0 +1,110 @@
+use super::state::{State, Checkpoint};
+use super::actions::Action;
+use super::resources::PrimaryResource;
+use super::buildings::Buildings;
+use super::jobs::{Job, Specialization};
+use super::invariants::Violation;
+use super::errors::CheckError;
+
+use std::collections::BTreeMap;
+use rand::{seq::SliceRandom, prelude::SeedableRng};
+use rand_chacha::ChaChaRng;
+
+// Turns between two checks of the simulated game.
+const CHECK_INTERVAL: usize = 1000;
+
+/**
+ * Outcome of a simulated game.
+ */
+pub struct Simulation {
+    pub state: State,
+    // How many times each action was taken, by its debug name.
+    pub actions: BTreeMap<String, u64>,
+    // Actions drawn but refused by the game.
+    pub rejected: u64,
+    // Invariants broken, with the iteration they were seen broken at after being kept.
+    pub violations: Vec<(usize, Violation)>,
+    pub check: Result<(), CheckError>
+}
+
+impl Simulation {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty() && self.check.is_ok()
+    }
+}
+
+/**
+ * Every action a player can take on their own, trades and sandbox grants aside.
+ */
+fn candidates() -> Vec<Action> {
+    let mut actions = vec!{Action::Idle, Action::RecruitCitizen, Action::Ennoble};
+    actions.extend(vec!{PrimaryResource::Food, PrimaryResource::Wood, PrimaryResource::Stone}.into_iter().map(Action::Collect));
+    actions.extend(Buildings::all().into_iter().map(Action::Build));
+    actions.extend(Job::all().into_iter().map(Action::AssignJob));
+    actions.extend(Job::all().into_iter().map(Action::DischargeJob));
+    actions.extend(Specialization::all().into_iter().map(Action::Train));
+    actions
+}
+
+/**
+ * Plays random actions on a new game for the given number of turns. Each turn the
+ * candidate actions are drawn in random order until the game takes one. The
+ * invariants are verified after every turn and the game is checked every
+ * `CHECK_INTERVAL` turns and at the end, stopping at the first failed check.
+ */
+pub fn simulate(seed: i128, turns: usize) -> Simulation {
+    let mut rng = ChaChaRng::seed_from_u64(seed as u64);
+    let mut candidates = candidates();
+    let mut simulation = Simulation { state: State::new(seed), actions: BTreeMap::new(), rejected: 0, violations: vec!{}, check: Ok(()) };
+    let mut broken: Vec<Violation> = vec!{};
+    let mut checkpoint: Option<Checkpoint> = None;
+
+    for turn in 1..=turns {
+        candidates.shuffle(&mut rng);
+
+        for action in candidates.iter() {
+            match simulation.state.clone().apply_action(action.clone()) {
+                Ok(state) => {
+                    simulation.state = state;
+                    *simulation.actions.entry(format!("{:?}", action)).or_default() += 1;
+                    break;
+                },
+                Err(_) => simulation.rejected += 1
+            }
+        }
+
+        let violations = simulation.state.violations();
+
+        for violation in violations.iter().filter(|violation| !broken.iter().any(|broken| broken.is_same_invariant(violation))) {
+            simulation.violations.push((simulation.state.commitment().iterations, violation.clone()));
+        }
+
+        broken = violations;
+
+        if turn % CHECK_INTERVAL == 0 || turn == turns {
+            match simulation.state.check_from(checkpoint.as_ref()) {
+                Ok(next_checkpoint) => checkpoint = next_checkpoint.or(checkpoint),
+                Err(error) => {
+                    simulation.check = Err(error);
+                    break;
+                }
+            }
+        }
+    }
+
+    simulation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::simulate;
+
+    #[test]
+    fn simulated_games_stay_valid() {
+        let simulation = simulate(43932030939219715774207308070970463251, 300);
+
+        assert_eq!(simulation.actions.values().sum::<u64>(), 300);
+        assert!(simulation.violations.is_empty());
+        assert!(simulation.check.is_ok());
+    }
+}
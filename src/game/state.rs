@@ -1,155 +1,1506 @@
-use super::resources::{Resource, Resources, PrimaryResource};
-use super::errors::{CheckError, Error, IterationError};
+use super::resources::{Resources, Resource, PrimaryResource, MAX_OMENS, PITY_DRAWS, MAX_GEMS};
+use super::errors::{CheckError, IterationError, RollbackError};
 use super::actions::Action;
 use super::land::Land;
 use super::citizens::Citizens;
+use super::scenarios::Scenario;
+use super::settings::Settings;
+use super::events::Event;
+use super::jobs::Job;
+use super::hashing::{Digest, StableHasher};
+use super::quantity::Quantity;
+use super::signing::{SaveSignature, parse_public_key};
+use super::merkle::{LogTree, ActionProof};
+use super::rules::{Rules, Drop, DropTable};
+use super::phrase::seed_phrase;
+use super::run_proof::RunProof;
+use super::observer::StateObserver;
+use super::invariants::Violation;
+use super::porcelain::{flatten, lines};
+use super::timeline::{LogTime, PlayTime};
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Deserializer, de::IgnoredAny};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 use rand::{thread_rng, Rng, prelude::SeedableRng};
 use rand_chacha::{ChaChaRng};
-use std::collections::hash_map::DefaultHasher;
+use ed25519_dalek::SigningKey;
+use rayon::prelude::*;
 use std::hash::Hasher;
+use std::collections::BTreeMap;
 
-type LogEntry = (Action, u64);
+pub type LogEntry = (Action, u64);
+
+// Real time it takes for an iteration to elapse while the game is not being played.
+const SECONDS_PER_IDLE_ITERATION: u64 = 60;
+
+// Most idle iterations a single catch up can grant.
+const MAX_CATCH_UP_ITERATIONS: u64 = 1000;
+
+// How many of the latest events are kept in the state.
+const RECENT_EVENTS_LIMIT: usize = 100;
+
+// Iterations between automatic snapshots, and how many of the latest ones are kept.
+const SNAPSHOT_INTERVAL: usize = 100;
+const SNAPSHOTS_LIMIT: usize = 10;
 
 // TODO: Hacer que las propedades sean privaadas.
 pub struct Context {
-    pub rng: ChaChaRng
+    pub rng: ChaChaRng,
+    pub sandbox: bool,
+    pub morale: Quantity,
+    pub birth_rate: Quantity,
+    pub soft_cap: Option<u64>,
+    pub fatigue: bool,
+    pub construction: bool,
+    pub pity: bool,
+    pub gold_strikes: bool,
+    pub events: Vec<Event>,
+    pub drops: DropTable,
+    // Drops rolled for while gathering: draws made and times found.
+    pub draws: Vec<(Drop, u64, u64)>
+}
+
+/**
+ * Copy of the state taken after a number of logged actions, without its log. Rolling
+ * back starts from it instead of replaying the whole log.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Snapshot {
+    actions: u64,
+    state: Box<State>
+}
+
+/**
+ * Snapshot known to be valid, as it was rebuilt while checking the state. Only digests
+ * are kept, to recognize the snapshot in later saves of the same game.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Checkpoint {
+    iterations: usize,
+    actions: u64,
+    hash: Digest,
+    state_digest: Digest,
+    log_digest: Digest
+}
+
+impl Checkpoint {
+    fn new(snapshot: &Snapshot, log: &[LogEntry]) -> Self {
+        Self {
+            iterations: snapshot.state.iterations,
+            actions: snapshot.actions,
+            hash: snapshot.state.hash(),
+            state_digest: Self::digest(&snapshot.state),
+            log_digest: Self::digest(&State::split_log(log, snapshot.actions).0)
+        }
+    }
+
+    fn matches(&self, snapshot: &Snapshot, log: &[LogEntry]) -> bool {
+        snapshot.actions == self.actions
+            && snapshot.state.iterations == self.iterations
+            && snapshot.state.hash() == self.hash
+            && Self::digest(&snapshot.state) == self.state_digest
+            && Self::digest(&State::split_log(log, snapshot.actions).0) == self.log_digest
+    }
+
+    fn digest<T: Serialize>(value: &T) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write(&serde_json::to_vec(value).unwrap());
+        hasher.digest()
+    }
+}
+
+/**
+ * Seed, settings and log of a game: all it takes to rebuild it. The hash it should end
+ * on is included when exported, so the game can be verified.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct LogDocument {
+    pub seed: i128,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub log: Vec<LogEntry>,
+    #[serde(default)]
+    pub hash: Option<Digest>
+}
+
+/**
+ * Everything a state hash is computed from, with the larger parts already digested, so
+ * the hash can be recomputed without the log.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Commitment {
+    pub seed: i128,
+    pub settings: Digest,
+    pub prev_hash: Digest,
+    pub iterations: usize,
+    pub log_root: Digest,
+    pub resources: Digest,
+    pub citizens: Digest,
+    pub land: Digest,
+    #[serde(default)]
+    pub rules: Rules,
+    #[serde(default)]
+    pub trades: Digest
+}
+
+impl Commitment {
+    pub fn hash(&self) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write_i128(self.seed);
+        hasher.write(&self.settings.0);
+        hasher.write(&self.prev_hash.0);
+        hasher.write_usize(self.iterations);
+        hasher.write(&self.log_root.0);
+        hasher.write(&self.resources.0);
+        hasher.write(&self.citizens.0);
+        hasher.write(&self.land.0);
+        hasher.write_sized(self.rules.engine.as_bytes());
+        hasher.write(&self.rules.digest.0);
+        hasher.write(&self.trades.0);
+        hasher.digest()
+    }
+}
+
+/**
+ * Resources and hash of the state at the end of an iteration.
+ */
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub iteration: usize,
+    pub hash: Digest,
+    pub resources: Resources
+}
+
+/**
+ * Field holding another value in a rebuilt state than in the recorded one.
+ */
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub struct Difference {
+    pub field: String,
+    pub recorded: String,
+    pub rebuilt: String
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{} {} vs {}", self.field, self.recorded, self.rebuilt)
+    }
+}
+
+/**
+ * What a single logged action did when the game is replayed.
+ */
+pub struct ReplayStep {
+    pub iteration: usize,
+    pub hash: Digest,
+    pub action: Action,
+    pub resource_deltas: Vec<(Resource, Quantity)>,
+    pub population_delta: i64,
+    pub events: Vec<Event>
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct State {
     seed: i128,
-    prev_hash: u64,
+    settings: Settings,
+    prev_hash: Digest,
     iterations: usize,
+    saved_at: u64,
     pub resources: Resources,
     citizens: Citizens,
     land: Land,
-    log: Vec<LogEntry>
+    log: Vec<LogEntry>,
+    #[serde(default)]
+    log_tree: LogTree,
+    events: Vec<(usize, Event)>,
+    signature: Option<SaveSignature>,
+    #[serde(default)]
+    snapshots: Vec<Snapshot>,
+    #[serde(default)]
+    tampered: bool,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    trades: Vec<Digest>,
+    #[serde(default)]
+    log_times: Vec<LogTime>,
+    #[serde(default)]
+    play_time: PlayTime,
+    // Events the clerics foresaw, by the iteration they are due.
+    #[serde(default)]
+    omens: Vec<(usize, Event)>
+}
+
+/**
+ * State read without its log, which is skipped over while decoding instead of being
+ * kept in memory. The fields must stay in the order of `State`, as MessagePack saves
+ * hold them by position.
+ */
+#[derive(Deserialize)]
+struct LoglessState {
+    seed: i128,
+    settings: Settings,
+    prev_hash: Digest,
+    iterations: usize,
+    saved_at: u64,
+    resources: Resources,
+    citizens: Citizens,
+    land: Land,
+    #[allow(dead_code)]
+    log: IgnoredAny,
+    #[serde(default)]
+    log_tree: LogTree,
+    events: Vec<(usize, Event)>,
+    signature: Option<SaveSignature>,
+    #[serde(default)]
+    snapshots: Vec<Snapshot>,
+    #[serde(default)]
+    tampered: bool,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    trades: Vec<Digest>,
+    #[serde(default)]
+    log_times: Vec<LogTime>,
+    #[serde(default)]
+    play_time: PlayTime,
+    // Events the clerics foresaw, by the iteration they are due.
+    #[serde(default)]
+    omens: Vec<(usize, Event)>
+}
+
+/**
+ * State as written by `debug dump`, to be edited by hand. The seed is kept as text, as
+ * TOML integers are only 64 bits wide. Snapshots and the signature are left out, as
+ * neither holds once the state is edited.
+ */
+#[derive(Serialize, Deserialize)]
+pub struct EditableState {
+    seed: String,
+    settings: Settings,
+    prev_hash: Digest,
+    iterations: usize,
+    saved_at: u64,
+    resources: Resources,
+    citizens: Citizens,
+    land: Land,
+    log: Vec<LogEntry>,
+    log_tree: LogTree,
+    events: Vec<(usize, Event)>,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    trades: Vec<Digest>,
+    #[serde(default)]
+    log_times: Vec<LogTime>,
+    #[serde(default)]
+    play_time: PlayTime,
+    // Events the clerics foresaw, by the iteration they are due.
+    #[serde(default)]
+    omens: Vec<(usize, Event)>
 }
 
 impl State {
+    /**
+     * Creates a new state. The starting land, storage and bonus resources are rolled
+     * from the seed, so the same seed always starts the same game.
+     */
     pub fn new(seed: i128) -> Self {
+        Self::with_settings(seed, Default::default())
+    }
+
+    /**
+     * Creates a new state with the given settings, which are kept in the state so the
+     * game can be rebuilt from scratch when checking it.
+     */
+    pub fn with_settings(seed: i128, settings: Settings) -> Self {
+        let mut rng = Self::get_genesis_rng(seed);
+        let resources = Resources::genesis(&mut rng);
+        let land = Land::genesis(&mut rng);
+
+        let (resources, land) = match &settings.scenario {
+            Some(scenario) => (scenario.configure_resources(resources), scenario.configure_land(land)),
+            None => (resources, land)
+        };
+        let (resources, citizens, land) = match &settings.scenario {
+            Some(Scenario::Imported(import)) => import.set_up(resources, Citizens::default(), land, &rng),
+            _ => (resources, Citizens::default(), land)
+        };
+
         Self {
             seed,
-            prev_hash: 0,
+            settings,
+            prev_hash: Default::default(),
             iterations: 0,
+            saved_at: 0,
             log: Default::default(),
-            resources: Default::default(),
-            citizens: Default::default(),
-            land: Default::default()
+            log_tree: Default::default(),
+            events: Default::default(),
+            signature: None,
+            snapshots: Default::default(),
+            tampered: false,
+            rules: Rules::current(),
+            trades: vec!{},
+            log_times: vec!{},
+            play_time: Default::default(),
+            omens: vec!{},
+            resources,
+            citizens,
+            land
+        }
+    }
+
+    /**
+     * Rebuilds the game from its seed, settings and log.
+     */
+    pub fn from_log_document(document: LogDocument) -> Result<Self, IterationError> {
+        Self::with_settings(document.seed, document.settings).apply_log(document.log)
+    }
+
+    pub fn log_document(&self) -> LogDocument {
+        LogDocument { seed: self.seed, settings: self.settings.clone(), log: self.log.clone(), hash: Some(self.hash()) }
+    }
+
+    /**
+     * Rebuilds the game from its seed, settings and log, checking it ends on the
+     * expected hash.
+     */
+    pub fn verify_log(seed: i128, settings: Settings, log: Vec<LogEntry>, expected_hash: Digest) -> Result<Self, CheckError> {
+        let state = Self::with_settings(seed, settings).recreate(log)?;
+        let hash = state.hash();
+
+        if hash == expected_hash {
+            Ok(state)
+        } else {
+            Err(CheckError::HashMismatch { iteration: state.iterations, expected: expected_hash, actual: hash, differences: vec!{} })
+        }
+    }
+
+    pub fn editable(&self) -> EditableState {
+        EditableState {
+            seed: self.seed.to_string(),
+            settings: self.settings.clone(),
+            prev_hash: self.prev_hash,
+            iterations: self.iterations,
+            saved_at: self.saved_at,
+            resources: self.resources.clone(),
+            citizens: self.citizens.clone(),
+            land: self.land.clone(),
+            log: self.log.clone(),
+            log_tree: self.log_tree.clone(),
+            events: self.events.clone(),
+            rules: self.rules.clone(),
+            trades: self.trades.clone(),
+            log_times: self.log_times.clone(),
+            play_time: self.play_time.clone(),
+            omens: self.omens.clone()
         }
     }
 
+    /**
+     * State edited by hand, marked as tampered since it no longer follows from its
+     * log. Fails with the seed when it isn't a number.
+     */
+    pub fn from_editable(editable: EditableState) -> Result<Self, String> {
+        Ok(Self {
+            seed: editable.seed.parse().map_err(|_| editable.seed.clone())?,
+            settings: editable.settings,
+            prev_hash: editable.prev_hash,
+            iterations: editable.iterations,
+            saved_at: editable.saved_at,
+            resources: editable.resources,
+            citizens: editable.citizens,
+            land: editable.land,
+            log: editable.log,
+            log_tree: editable.log_tree,
+            events: editable.events,
+            signature: None,
+            snapshots: vec!{},
+            tampered: true,
+            rules: editable.rules,
+            trades: editable.trades,
+            log_times: editable.log_times,
+            play_time: editable.play_time,
+            omens: editable.omens
+        })
+    }
+
+    /**
+     * Whether the state was edited by hand. Such a state never passes a check.
+     */
+    pub fn tampered(&self) -> bool {
+        self.tampered
+    }
+
+    pub fn seed(&self) -> i128 {
+        self.seed
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn log(&self) -> &[LogEntry] {
+        &self.log
+    }
+
+    /**
+     * First and last iteration each entry of the log was taken on. Actions taking no
+     * iteration are taken on the iteration before them.
+     */
+    pub fn log_spans(&self) -> Vec<(&LogEntry, usize, usize)> {
+        let mut iterations = 0;
+
+        self.log.iter().map(|log_entry| {
+            match Self::elapses(&self.settings, &log_entry.0) {
+                true => {
+                    iterations += log_entry.1 as usize;
+                    (log_entry, iterations + 1 - log_entry.1 as usize, iterations)
+                },
+                false => (log_entry, iterations, iterations)
+            }
+        }).collect()
+    }
+
+    pub fn with_log(self, log: Vec<LogEntry>) -> Self {
+        Self { log, ..self }
+    }
+
+    /**
+     * Decodes a state leaving its log out, for commands that never look at it. The hash
+     * still commits to the log, through the log tree.
+     */
+    pub fn deserialize_without_log<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let state = LoglessState::deserialize(deserializer)?;
+
+        Ok(Self {
+            seed: state.seed,
+            settings: state.settings,
+            prev_hash: state.prev_hash,
+            iterations: state.iterations,
+            saved_at: state.saved_at,
+            resources: state.resources,
+            citizens: state.citizens,
+            land: state.land,
+            log: vec!{},
+            log_tree: state.log_tree,
+            events: state.events,
+            signature: state.signature,
+            snapshots: state.snapshots,
+            tampered: state.tampered,
+            rules: state.rules,
+            trades: state.trades,
+            log_times: state.log_times,
+            play_time: state.play_time,
+            omens: state.omens
+        })
+    }
+
+    /**
+     * Same state with its log left out, for saves that keep it in a log file.
+     */
+    pub fn without_log(&self) -> Self {
+        Self { log: vec!{}, ..self.clone() }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /**
+     * State the game started from, under the rules it was created with.
+     */
     fn get_initial_state(&self) -> Self {
+        Self { rules: self.rules.clone(), ..Self::with_settings(self.seed, self.settings.clone()) }
+    }
+
+    /**
+     * Check if the current state is valid by rebuilding it step by step.
+     */
+    pub fn check(&self) -> Result<(), CheckError> {
+        self.check_from(None).map(|_| ())
+    }
+
+    /**
+     * Checks the state replaying only the log after a checkpoint verified by a previous
+     * check, when its snapshot is still in the state untouched. Returns the checkpoint
+     * to resume from next time.
+     *
+     * The log is split in segments at each later snapshot and the segments are replayed
+     * in parallel. Each one must end on the very state of the snapshot the next one
+     * starts from, not just its hash, as the snapshot is trusted from then on. The last
+     * one must end on the hash of the state.
+     */
+    pub fn check_from(&self, checkpoint: Option<&Checkpoint>) -> Result<Option<Checkpoint>, CheckError> {
+        if self.tampered {
+            return Err(CheckError::Tampered);
+        }
+
+        if !self.rules.is_compatible() {
+            return Err(CheckError::RulesMismatch(self.rules.clone()));
+        }
+
+        let checkpoint_snapshot = checkpoint.and_then(|checkpoint| self.snapshots.iter()
+            .find(|snapshot| checkpoint.matches(snapshot, &self.log)));
+
+        let mut starts: Vec<(Self, u64)> = vec!{match checkpoint_snapshot {
+            Some(snapshot) => (self.restore(snapshot), snapshot.actions),
+            None => (self.get_initial_state(), 0)
+        }};
+        let mut later_snapshots: Vec<&Snapshot> = self.snapshots.iter()
+            .filter(|snapshot| snapshot.actions > starts[0].1)
+            .collect();
+        later_snapshots.sort_by_key(|snapshot| snapshot.actions);
+        starts.extend(later_snapshots.into_iter().map(|snapshot| (self.restore(snapshot), snapshot.actions)));
+
+        let segments: Vec<(Self, Vec<LogEntry>, &Self, bool)> = starts.iter().enumerate()
+            .map(|(index, (state, from))| {
+                let (to, recorded, snapshot) = match starts.get(index + 1) {
+                    Some((next_state, to)) => (*to, next_state, true),
+                    None => (Self::count_actions(&self.log), self, false)
+                };
+                let log = Self::split_log(&Self::split_log(&self.log, to).0, *from).1;
+
+                (state.clone(), log, recorded, snapshot)
+            })
+            .collect();
+
+        // Collected in order first, so a mismatch is reported for the earliest segment.
+        let mut states = segments.into_par_iter()
+            .map(|(state, log, recorded, snapshot)| {
+                let state = state.recreate(log)?;
+                let hash = state.hash();
+                let expected = recorded.hash();
+
+                if hash == expected && snapshot && state.played_digest() != recorded.played_digest() {
+                    Err(CheckError::SnapshotMismatch(recorded.iterations))
+                } else if hash == expected {
+                    Ok(state)
+                } else {
+                    Err(CheckError::HashMismatch {
+                        iteration: state.iterations,
+                        expected,
+                        actual: hash,
+                        differences: state.differences(recorded)
+                    })
+                }
+            })
+            .collect::<Vec<Result<Self, CheckError>>>()
+            .into_iter()
+            .collect::<Result<Vec<Self>, CheckError>>()?;
+        let state = states.pop().unwrap();
+
+        let violations = self.violations();
+
+        if !violations.is_empty() {
+            return Err(CheckError::InvariantsViolated(violations));
+        }
+
+        if let Some(signature) = &self.signature {
+            if !signature.verify(&self.hash()) {
+                return Err(CheckError::InvalidSignature);
+            }
+        }
+
+        Ok(state.snapshots.last().map(|snapshot| Checkpoint::new(snapshot, &state.log)))
+    }
+
+    /**
+     * Digest of everything the log decides in the state: all but the log itself, the
+     * snapshots, the signature and the timestamps.
+     */
+    fn played_digest(&self) -> Digest {
+        Checkpoint::digest(&Self {
+            saved_at: 0,
+            log: vec!{},
+            signature: None,
+            snapshots: vec!{},
+            tampered: false,
+            log_times: vec!{},
+            play_time: PlayTime::default(),
+            ..self.clone()
+        })
+    }
+
+    /**
+     * Invariants between citizens, land and resources the state breaks.
+     */
+    pub fn violations(&self) -> Vec<Violation> {
+        let mut violations = vec!{};
+
+        if self.citizens.count() > self.citizens.max_population {
+            violations.push(Violation::Overpopulated { citizens: self.citizens.count(), max_population: self.citizens.max_population });
+        }
+
+        if self.land.land_use() > self.land.total_land {
+            violations.push(Violation::LandOverused { land_use: self.land.land_use(), total_land: self.land.total_land });
+        }
+
+        for resource in Resource::all() {
+            let amount = self.resources.amount(&resource);
+
+            // Food goes negative when citizens eat more than is produced, as nobody starves.
+            if amount < Quantity::ZERO && resource != Resource::Primary(PrimaryResource::Food) {
+                violations.push(Violation::NegativeResource(resource.clone(), amount));
+            }
+
+            if let Some(capacity) = self.resources.capacity(&resource) {
+                if amount > capacity {
+                    violations.push(Violation::OverCapacity { resource, amount, capacity });
+                }
+            }
+        }
+
+        let expected = Quantity::ONE * self.citizens.count();
+
+        if self.resources.food_cons_rate != expected {
+            violations.push(Violation::FoodConsumption { expected, actual: self.resources.food_cons_rate });
+        }
+
+        violations
+    }
+
+    /**
+     * Rebuilds the state from as much of its log as can still be applied, dropping the
+     * actions after the first one that fails. Returns the rebuilt state and how many
+     * actions were dropped.
+     */
+    pub fn repair(&self) -> Result<(Self, u64), IterationError> {
+        let actions = Self::count_actions(&self.log);
+        let mut valid_actions = 0;
+        let mut state = self.get_initial_state();
+
+        for action in self.log.iter().flat_map(|(action, count)| std::iter::repeat_n(action, *count as usize)) {
+            // Applied to a copy, as the state is lost along with the failing action.
+            match state.clone().apply_action(action.clone()) {
+                Ok(next_state) => state = next_state,
+                Err(_) => break
+            }
+
+            valid_actions += 1;
+        }
+
+        Ok((state.stamp(self.saved_at), actions - valid_actions))
+    }
+
+    /**
+     * Rebuilds the game from its seed and log, describing each action taken up to the
+     * given iteration.
+     */
+    pub fn replay<F: FnMut(ReplayStep)>(&self, until: usize, mut narrate: F) -> Result<(), IterationError> {
+        let mut state = self.get_initial_state();
+
+        for action in self.log.iter().flat_map(|(action, count)| std::iter::repeat_n(action, *count as usize)) {
+            if state.iterations >= until {
+                break;
+            }
+
+            let resources = state.resources.clone();
+            let population = state.citizens.count();
+            let iterations = state.iterations;
+
+            state = state.apply_action(action.clone())?;
+
+            let events = match state.iterations > iterations {
+                true => state.events.iter()
+                    .filter(|(iteration, _)| *iteration == state.iterations)
+                    .map(|(_, event)| event.clone())
+                    .collect(),
+                false => vec!{}
+            };
+
+            narrate(ReplayStep {
+                iteration: state.iterations,
+                hash: state.hash(),
+                action: action.clone(),
+                resource_deltas: state.resources.deltas(&resources),
+                population_delta: state.citizens.count() as i64 - population as i64,
+                events
+            });
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Rebuilds the game from its seed and log, letting the observer follow along.
+     */
+    pub fn replay_observed(&self, observer: &mut dyn StateObserver) -> Result<Self, IterationError> {
+        self.get_initial_state().apply_log_observed(self.log.clone(), observer)
+    }
+
+    /**
+     * Rebuilds the game from its seed and log, recording the state at the end of every
+     * iteration from the given one on.
+     */
+    pub fn history(&self, from: usize) -> Result<Vec<HistoryEntry>, IterationError> {
+        let mut state = self.get_initial_state();
+        let mut history: Vec<HistoryEntry> = vec!{};
+
+        let mut record = |state: &Self| if state.iterations >= from {
+            let entry = HistoryEntry { iteration: state.iterations, hash: state.hash(), resources: state.resources.clone() };
+
+            match history.last_mut() {
+                Some(last_entry) if last_entry.iteration == entry.iteration => *last_entry = entry,
+                _ => history.push(entry)
+            }
+        };
+
+        record(&state);
+
+        for action in self.log.iter().flat_map(|(action, count)| std::iter::repeat_n(action, *count as usize)) {
+            state = state.apply_action(action.clone())?;
+            record(&state);
+        }
+
+        Ok(history)
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    pub fn citizens(&self) -> &Citizens {
+        &self.citizens
+    }
+
+    pub fn land(&self) -> &Land {
+        &self.land
+    }
+
+    /**
+     * When the state was last saved, in seconds since the Unix epoch.
+     */
+    pub fn saved_at(&self) -> u64 {
+        self.saved_at
+    }
+
+    /**
+     * Citizens in the civilization, and how many it can house.
+     */
+    pub fn population(&self) -> (u64, u64) {
+        (self.citizens.count(), self.citizens.max_population)
+    }
+
+    /**
+     * Iterations until the food runs out at the current rate, or nothing while it
+     * isn't being lost.
+     */
+    pub fn food_left(&self) -> Option<u64> {
+        let rate = self.resources.food_net_rate(self.citizens.morale(&self.land));
+
+        match rate < Quantity::ZERO {
+            true => Some((Quantity::max(self.resources.food, Quantity::ZERO).as_hundredths() / -rate.as_hundredths()) as u64),
+            false => None
+        }
+    }
+
+    /**
+     * Latest events, along with the iteration each happened on.
+     */
+    pub fn recent_events(&self) -> &[(usize, Event)] {
+        &self.events
+    }
+
+    /**
+     * Events the clerics foresaw and are still to come, by the iteration they are due.
+     */
+    pub fn omens(&self) -> &[(usize, Event)] {
+        &self.omens
+    }
+
+    /**
+     * Recent events that happened after the given iteration.
+     */
+    pub fn events_since(&self, iteration: usize) -> Vec<Event> {
+        self.events.iter()
+            .filter(|(event_iteration, _)| *event_iteration > iteration)
+            .map(|(_, event)| event.clone())
+            .collect()
+    }
+
+    /**
+     * Root of the Merkle tree over every action in the log.
+     */
+    pub fn log_root(&self) -> Digest {
+        self.log_tree.root()
+    }
+
+    /**
+     * Inclusion proof of the action at the given position of the log, counting every
+     * repetition of merged log entries.
+     */
+    pub fn prove(&self, index: u64) -> Option<ActionProof> {
+        let mut iteration = 0;
+        let mut taken_actions = vec!{};
+        let mut leaves = vec!{};
+
+        for action in self.log.iter().flat_map(|(action, count)| std::iter::repeat_n(action, *count as usize)) {
+            if Self::elapses(&self.settings, action) {
+                iteration += 1;
+            }
+
+            leaves.push(LogTree::leaf(leaves.len() as u64, iteration, action));
+            taken_actions.push((iteration, action));
+        }
+
+        let (iteration, action) = taken_actions.get(index as usize)?;
+        let proof = LogTree::prove(&leaves, index as usize)?;
+
+        Some(ActionProof { root: self.log_root(), index, iteration: *iteration, action: (*action).clone(), proof })
+    }
+
+    /**
+     * Identifies the game, whatever iteration it is at.
+     */
+    pub fn game_id(&self) -> Digest {
+        self.get_initial_state().hash()
+    }
+
+    /**
+     * Whether the state continues the given one: both are the same game and its log
+     * starts with the whole log of the other, so they share its hash chain.
+     */
+    pub fn extends(&self, other: &Self) -> bool {
+        self.game_id() == other.game_id()
+            && self.iterations >= other.iterations
+            && Self::split_log(&self.log, Self::count_actions(&other.log)).0 == other.log
+    }
+
+    /**
+     * Checks the state and that it was signed by the owner of the given public key.
+     */
+    pub fn verify(&self, public_key: &str) -> Result<(), CheckError> {
+        self.check()?;
+
+        let expected = parse_public_key(public_key).ok_or(CheckError::InvalidPublicKey)?;
+
+        match &self.signature {
+            Some(signature) if parse_public_key(&signature.public_key) == Some(expected) => Ok(()),
+            Some(_) => Err(CheckError::UnexpectedSigner),
+            None => Err(CheckError::MissingSignature)
+        }
+    }
+
+    /**
+     * Get apply_action state from current state and an optional action. Unless the
+     * game treats management as free, every action takes a whole iteration.
+     */
+    pub fn apply_action(self, action: Action) -> Result<Self, IterationError> {
+        self.apply_action_observed(action, &mut ())
+    }
+
+    /**
+     * Applies an action like `apply_action`, telling the observer about the events it
+     * caused, the action itself and the iteration it completed, in that order.
+     */
+    pub fn apply_action_observed(self, action: Action, observer: &mut dyn StateObserver) -> Result<Self, IterationError> {
+        let prev_hash = self.hash();
+        self.apply_action_after(action, observer, prev_hash)
+    }
+
+    /**
+     * Lets iterations elapse idle, like applying `Idle` that many times. Each iteration
+     * draws from a generator seeded by the hash of the previous one, so a stretch can't
+     * be computed in closed form, but the settings don't change while idle and are
+     * hashed only once, as is the land unless it is being built on or surveyed.
+     */
+    pub fn fast_forward(self, iterations: u64) -> Result<Self, IterationError> {
+        let settings = self.settings.hash();
+        let mut land = self.land.hash();
+        let mut state = self;
+
+        for _ in 0..iterations {
+            let prev_hash = state.commitment_with(settings, land).hash();
+            let changing = !state.land.construction.is_empty() || state.citizens.surveyors > 0;
+            state = state.apply_action_after(Action::Idle, &mut (), prev_hash)?;
+
+            if changing {
+                land = state.land.hash();
+            }
+        }
+
+        Ok(state)
+    }
+
+    fn apply_action_after(self, action: Action, observer: &mut dyn StateObserver, prev_hash: Digest) -> Result<Self, IterationError> {
+        if !action.is_well_formed() {
+            return Err(IterationError::InvalidTurn);
+        }
+
+        let mut ctx = self.get_context();
+        let mut trades = self.trades.clone();
+        for step in action.steps() {
+            trades = self.record_trade(step, trades, &prev_hash)?;
+        }
+        let seed = self.seed;
+        let settings = self.settings.clone();
+        let elapses = Self::elapses(&self.settings, &action);
+        let foreseen = action.steps().into_iter().find_map(|step| match step {
+            Action::ReadOmens(iterations) => Some(*iterations),
+            _ => None
+        });
+        let iterations = if elapses { self.iterations + 1 } else { self.iterations };
+        if let Some(budget) = self.settings.turn_budget().filter(|budget| iterations > *budget) {
+            return Err(IterationError::TurnBudgetSpent(budget));
+        }
+        let saved_at = self.saved_at;
+        let previous_resources = self.resources.clone();
+        let (mut resources, mut citizens) = if elapses {
+            (
+                self.resources.govern(&self.citizens)?.craft(&self.citizens)?.work(&self.citizens, &mut ctx)?,
+                self.citizens.work(&ctx)?
+            )
+        } else {
+            (self.resources, self.citizens)
+        };
+        let mut land = self.land;
+
+        for step in action.steps() {
+            resources = resources.apply_action(step, &mut ctx)?;
+            if let Action::CancelConstruction(index) = step {
+                resources = resources.salvage(land.salvage(*index))?;
+            }
+            citizens = citizens.apply_action(step, &mut ctx)?;
+            land = land.apply_action(step, &mut ctx)?;
+        }
+
+        if elapses && citizens.surveyors > 0 {
+            land = land.explore(citizens.surveyors);
+        }
+
+        if elapses && ctx.construction {
+            let (constructed, finished) = land.construct(citizens.builders);
+            land = constructed;
+
+            for building in finished {
+                resources = resources.raise(&building)?;
+                citizens = citizens.house(&building);
+                land = land.raise(&building);
+                ctx.events.push(Event::BuildingBuilt(building));
+            }
+        }
+
+        let (resources, citizens) = if elapses {
+            let population = citizens.count();
+            let citizens = citizens.grow(&mut ctx)?;
+            let resources = resources.feed_births(citizens.count() - population)?;
+            Self::rebalance_jobs(resources, citizens, &mut ctx)?
+        } else {
+            (resources, citizens)
+        };
+
+        ctx.events.extend(resources.capped(&previous_resources).into_iter().map(Event::ResourceCapped));
+
+        let new_events: Vec<Event> = ctx.events.drain(..).collect();
+        let mut events = self.events;
+        events.extend(new_events.iter().map(|event| (iterations, event.clone())));
+        events.drain(..events.len().saturating_sub(RECENT_EVENTS_LIMIT));
+
+        let mut log_tree = self.log_tree;
+        log_tree.push(LogTree::leaf(log_tree.leaves(), iterations, &action));
+
+        // Appended in place, merging repeated actions into the last entry.
+        let mut log = self.log;
+
+        match log.last_mut() {
+            Some(last_entry) if last_entry.0 == action => last_entry.1 += 1,
+            _ => log.push((action, 1))
+        }
+
+        let state = Self {
+            prev_hash,
+            seed,
+            settings,
+            iterations,
+            saved_at,
+            resources,
+            citizens,
+            land,
+            log,
+            log_tree,
+            events,
+            signature: None,
+            snapshots: self.snapshots,
+            tampered: self.tampered,
+            rules: self.rules,
+            trades,
+            log_times: self.log_times,
+            play_time: self.play_time,
+            omens: self.omens.into_iter().filter(|(iteration, _)| *iteration > iterations).collect()
+        };
+
+        let state = match foreseen {
+            Some(ahead) => Self { omens: state.foresee(ahead), ..state },
+            None => state
+        };
+
+        let state = if elapses && iterations % SNAPSHOT_INTERVAL == 0 {
+            state.snapshot()
+        } else {
+            state
+        };
+
+        for event in new_events.iter() {
+            observer.on_event(&state, iterations, event);
+        }
+        for (drop, draws, found) in ctx.draws.iter() {
+            observer.on_draw(&state, drop, *draws, *found);
+        }
+        if let Some((action, _)) = state.log.last() {
+            observer.on_action(&state, action);
+        }
+        if elapses {
+            observer.on_iteration(&state);
+        }
+
+        Ok(state)
+    }
+
+    /**
+     * Events of the next iterations, up to the most clerics can read ahead, found by
+     * playing them out idle on a copy of the game. As everything is rolled from the
+     * seed they come to pass as long as the game is left idle, and stop short of an
+     * iteration that would fail.
+     */
+    fn foresee(&self, iterations: u64) -> Vec<(usize, Event)> {
+        let mut future = Self { log: vec!{}, snapshots: vec!{}, omens: vec!{}, ..self.clone() };
+        let mut omens = vec!{};
+
+        for _ in 0..u64::min(iterations, MAX_OMENS) {
+            future = match future.fast_forward(1) {
+                Ok(future) => future,
+                Err(_) => break
+            };
+            omens.extend(future.events.iter().filter(|(iteration, _)| *iteration == future.iterations).cloned());
+        }
+
+        omens
+    }
+
+    /**
+     * Keeps track of the offers the game is party to: those it made and are still open,
+     * and those it accepted. Forged trades, trades of other games and trades already
+     * logged are refused.
+     */
+    fn record_trade(&self, action: &Action, mut trades: Vec<Digest>, state: &Digest) -> Result<Vec<Digest>, IterationError> {
+        if action.is_trade() && (self.settings.is_ranked() || self.settings.daily.is_some()) {
+            return Err(IterationError::SandboxOnly);
+        }
+
+        match action {
+            Action::OfferTrade(offer) if offer.verify() && offer.game == self.game_id()
+                && offer.state == *state && !trades.contains(&offer.id()) => {
+                trades.push(offer.id());
+                Ok(trades)
+            },
+            Action::AcceptTrade(receipt) if receipt.verify() && receipt.game == self.game_id()
+                && !trades.contains(&receipt.offer.id()) => {
+                trades.push(receipt.offer.id());
+                Ok(trades)
+            },
+            Action::SettleTrade(receipt) if receipt.verify() && receipt.offer.game == self.game_id()
+                && trades.contains(&receipt.offer.id()) => {
+                trades.retain(|id| *id != receipt.offer.id());
+                Ok(trades)
+            },
+            Action::OfferTrade(_) | Action::AcceptTrade(_) | Action::SettleTrade(_) => Err(IterationError::InvalidTrade),
+            _ => Ok(trades)
+        }
+    }
+
+    /**
+     * Whether an iteration elapses when taking the action.
+     */
+    fn elapses(settings: &Settings, action: &Action) -> bool {
+        !(settings.free_management && action.is_administrative())
+    }
+
+    fn snapshot(mut self) -> Self {
+        // Taken out first, so the log and the older snapshots are not cloned along.
+        let log = std::mem::take(&mut self.log);
+        let mut snapshots = std::mem::take(&mut self.snapshots);
+        let signature = self.signature.take();
+
+        snapshots.push(Snapshot {
+            actions: Self::count_actions(&log),
+            state: Box::new(self.clone())
+        });
+        snapshots.drain(..snapshots.len().saturating_sub(SNAPSHOTS_LIMIT));
+
+        Self { log, snapshots, signature, ..self }
+    }
+
+    pub fn count_actions(log: &[LogEntry]) -> u64 {
+        log.iter().map(|log_entry| log_entry.1).sum()
+    }
+
+    /**
+     * Splits the log after the given count of actions.
+     */
+    pub fn split_log(log: &[LogEntry], actions: u64) -> (Vec<LogEntry>, Vec<LogEntry>) {
+        let mut prefix: Vec<LogEntry> = vec!{};
+        let mut suffix: Vec<LogEntry> = vec!{};
+        let mut remaining = actions;
+
+        for (action, count) in log {
+            let taken = u64::min(*count, remaining);
+            remaining -= taken;
+
+            if taken > 0 {
+                prefix.push((action.clone(), taken));
+            }
+            if taken < *count {
+                suffix.push((action.clone(), count - taken));
+            }
+        }
+
+        (prefix, suffix)
+    }
+
+    /**
+     * State the snapshot was taken from, along with its log and earlier snapshots.
+     */
+    fn restore(&self, snapshot: &Snapshot) -> Self {
         Self {
-            seed: self.seed,
-            prev_hash: 0,
-            iterations: 0,
-            log: Default::default(),
-            resources: Default::default(),
-            citizens: Default::default(),
-            land: Default::default()
+            log: Self::split_log(&self.log, snapshot.actions).0,
+            snapshots: self.snapshots.iter()
+                .filter(|kept| kept.actions <= snapshot.actions)
+                .cloned()
+                .collect(),
+            saved_at: self.saved_at,
+            ..*snapshot.state.clone()
+        }
+    }
+
+    /**
+     * Restores the game as it was at the given iteration. Ironman games can't go back.
+     */
+    pub fn rollback(&self, iteration: usize) -> Result<Self, RollbackError> {
+        if self.settings.ironman {
+            return Err(RollbackError::Ironman);
+        }
+
+        self.at(iteration)
+    }
+
+    /**
+     * Rebuilds the state as it was at the given iteration: the nearest earlier snapshot
+     * is restored and the log replayed forward from it.
+     */
+    pub fn at(&self, iteration: usize) -> Result<Self, RollbackError> {
+        if iteration > self.iterations {
+            return Err(RollbackError::FutureIteration(iteration));
+        }
+
+        let snapshot = self.snapshots.iter().rev()
+            .find(|snapshot| snapshot.state.iterations <= iteration);
+
+        let (mut state, actions) = match snapshot {
+            Some(snapshot) => (self.restore(snapshot), snapshot.actions),
+            None => (Self { saved_at: self.saved_at, ..self.get_initial_state() }, 0)
+        };
+
+        let remaining_actions = self.log.iter()
+            .flat_map(|(action, count)| std::iter::repeat_n(action, *count as usize))
+            .skip(actions as usize);
+
+        for action in remaining_actions {
+            if state.iterations >= iteration {
+                break;
+            }
+
+            state = state.apply_action(action.clone()).map_err(RollbackError::Iteration)?;
+        }
+
+        Ok(state)
+    }
+
+    /**
+     * While food is being lost, moves one worker per iteration from the least
+     * prioritized job into farming.
+     */
+    fn rebalance_jobs(resources: Resources, citizens: Citizens, ctx: &mut Context) -> Result<(Resources, Citizens), IterationError> {
+        if resources.food_net_rate(ctx.morale) >= Quantity::ZERO {
+            return Ok((resources, citizens));
+        }
+
+        match citizens.least_prioritized_job() {
+            Some(job) => {
+                let resources = resources
+                    .apply_action(&Action::DischargeJob(job.clone()), ctx)?
+                    .apply_action(&Action::AssignJob(Job::Farmer), ctx)?;
+                let citizens = citizens
+                    .apply_action(&Action::DischargeJob(job.clone()), ctx)?
+                    .apply_action(&Action::AssignJob(Job::Farmer), ctx)?;
+
+                ctx.events.push(Event::WorkerReassigned(job, Job::Farmer));
+
+                Ok((resources, citizens))
+            },
+            None => Ok((resources, citizens))
+        }
+    }
+
+    pub fn apply_log(self, log: Vec<LogEntry>) -> Result<Self, IterationError> {
+        let mut state = self;
+
+        for (action, count) in log {
+            state = match action {
+                Action::Idle => state.fast_forward(count)?,
+                action => state.apply_log_observed(vec!{(action, count)}, &mut ())?
+            };
+        }
+
+        Ok(state)
+    }
+
+    /**
+     * Applies every action in the log like `apply_action_observed`.
+     */
+    pub fn apply_log_observed(self, log: Vec<LogEntry>, observer: &mut dyn StateObserver) -> Result<Self, IterationError> {
+        let mut state = self;
+
+        for log_entry in log {
+            for _ in 0..log_entry.1 {
+                state = state.apply_action_observed(log_entry.0.clone(), observer)?;
+            }
         }
+
+        Ok(state)
     }
 
     /**
-     * Check if the current state is valid by rebuilding it step by step.
+     * Applies a plan as a whole: either every entry of it is applied, or the index of
+     * the entry that failed is returned with its error, leaving this state untouched.
      */
-    pub fn check(&self) -> Result<(), CheckError> {
-        let state = Self::get_initial_state(&self).apply_log(self.log.clone())
-            .or_else(|error| Err(CheckError::InvalidStateRecreation(error)))?;
+    pub fn apply_plan(&self, plan: Vec<LogEntry>) -> Result<Self, (usize, IterationError)> {
+        let mut state = self.clone();
 
-        if self.hash() == state.hash() {
-            Ok(())
-        } else {
-            Err(CheckError::HashMismatch)
+        for (index, log_entry) in plan.into_iter().enumerate() {
+            state = state.apply_log(vec!{log_entry}).map_err(|error| (index, error))?;
         }
+
+        Ok(state)
     }
 
     /**
-     * Get apply_action state from current state and an optional action.
+     * Applies a plan like `apply_plan`, telling the observer about every action and
+     * about the progress through the plan.
      */
-    pub fn apply_action(self, action: Action) -> Result<Self, IterationError> {
-        let mut ctx = self.get_context();
-        let prev_hash = self.hash();
-        let seed = self.seed;
-        let iterations = self.iterations + 1;
-        let resources = self.resources.work(&mut ctx)?.apply_action(&action, &mut ctx)?;
-        let citizens = self.citizens.apply_action(&action, &mut ctx)?;
-        let land = self.land.apply_action(&action, &mut ctx)?;
-
-        let log = {
-            let mut log: Vec<LogEntry> = vec!{};
-
-            for log_entry in [self.log, vec!{(action, 1)}].concat() {
-                if log.len() > 0 {
-                    let last_index = log.len() - 1;
-    
-                    if log[last_index].0 == log_entry.0 {
-                        log[last_index].1 += 1;
-                        continue;
-                    }
-                }
-
-                log.push(log_entry);
-            }
+    pub fn apply_plan_observed(&self, plan: Vec<LogEntry>, observer: &mut dyn StateObserver) -> Result<Self, (usize, IterationError)> {
+        let total = plan.len();
+        let mut state = self.clone();
 
-            log
-        };
+        for (index, log_entry) in plan.into_iter().enumerate() {
+            state = state.apply_log_observed(vec!{log_entry}, observer).map_err(|error| (index, error))?;
+            observer.on_plan_progress(&state, index + 1, total);
+        }
 
-        Ok(Self {
-            prev_hash,
-            seed,
-            iterations,
-            resources,
-            citizens,
-            land,
-            log
-        })
+        Ok(state)
     }
 
-    pub fn apply_log(self, log: Vec<LogEntry>) -> Result<Self, IterationError> {
-        let hash = self.hash();
+    #[allow(dead_code)]
+    pub fn apply_and_check_log(self, log: Vec<LogEntry>) -> Result<Self, CheckError> {
         let mut state = self;
 
         for log_entry in log {
             for _ in 0..log_entry.1 {
-                state = state.apply_action(log_entry.0.clone())?;
+                state.check()?;
+
+                state = state.recreate(vec!{(log_entry.0.clone(), 1)})?;
             }
         }
 
         Ok(state)
     }
 
-    pub fn apply_and_check_log(self, log: Vec<LogEntry>) -> Result<Self, CheckError> {
+    /**
+     * Applies the log while checking, telling which action failed and where.
+     */
+    fn recreate(self, log: Vec<LogEntry>) -> Result<Self, CheckError> {
         let mut state = self;
 
-        for log_entry in log {
-            for _ in 0..log_entry.1 {
-                state.check()?;
-
-                state = state.apply_action(log_entry.0.clone())
-                    .or_else(|error| Err(CheckError::InvalidStateRecreation(error)))?;
+        for (action, count) in log {
+            for _ in 0..count {
+                let iteration = state.iterations;
+                state = state.apply_action(action.clone())
+                    .map_err(|error| CheckError::InvalidStateRecreation { iteration, action: action.clone(), error })?;
             }
         }
 
         Ok(state)
     }
 
+    /**
+     * Records when the state was saved, as seconds since the unix epoch, and when the
+     * log was added to if it grew since the last stamp. The time since the previous
+     * save adds to the play time, unless a new session started. Timestamps are not part
+     * of the hash, as they have no effect on the game.
+     */
+    pub fn stamp(mut self, now: u64) -> Self {
+        let actions = self.log_tree.leaves();
+        self.log_times.retain(|log_time| log_time.actions <= actions);
+
+        if self.log_times.last().map_or(0, |log_time| log_time.actions) < actions {
+            self.log_times.push(LogTime { at: now, actions, iterations: self.iterations });
+        }
+
+        Self { saved_at: now, play_time: self.play_time.clone().record(self.saved_at, now), ..self }
+    }
+
+    pub fn play_time(&self) -> &PlayTime {
+        &self.play_time
+    }
+
+    /**
+     * Actions taken since the game was created, whether or not the log is loaded.
+     */
+    pub fn actions(&self) -> u64 {
+        self.log_tree.leaves()
+    }
+
+    /**
+     * When the log was added to, as recorded on every stamp that found new actions.
+     */
+    pub fn log_times(&self) -> &[LogTime] {
+        &self.log_times
+    }
+
+    /**
+     * Signs the state hash, unless it was already signed by whoever committed it. Like
+     * the timestamp, the signature is not part of the hash.
+     */
+    pub fn sign(self, key: &SigningKey) -> Self {
+        if self.signature.is_some() {
+            return self;
+        }
+
+        let signature = SaveSignature::sign(key, &self.hash());
+
+        Self { signature: Some(signature), ..self }
+    }
+
+    /**
+     * Idle iterations earned by the real time elapsed since the state was saved.
+     */
+    pub fn catch_up_iterations(&self, now: u64) -> u64 {
+        if self.saved_at == 0 {
+            return 0;
+        }
+
+        u64::min(now.saturating_sub(self.saved_at) / SECONDS_PER_IDLE_ITERATION, MAX_CATCH_UP_ITERATIONS)
+    }
+
+    /**
+     * Fields of the resources, citizens and land holding other values than in the
+     * recorded state, this state being the one rebuilt from the log.
+     */
+    pub fn differences(&self, recorded: &Self) -> Vec<Difference> {
+        let rebuilt_fields = self.fields();
+        let recorded_fields = recorded.fields();
+        let mut fields: Vec<&String> = recorded_fields.keys().chain(rebuilt_fields.keys()).collect();
+        fields.sort();
+        fields.dedup();
+
+        fields.into_iter()
+            .filter(|field| recorded_fields.get(*field) != rebuilt_fields.get(*field))
+            .map(|field| Difference {
+                field: field.clone(),
+                recorded: recorded_fields.get(field).cloned().unwrap_or_else(|| "none".to_string()),
+                rebuilt: rebuilt_fields.get(field).cloned().unwrap_or_else(|| "none".to_string())
+            })
+            .collect()
+    }
+
+    /**
+     * State in the porcelain format: its fields, along with the game, hash, seed,
+     * population and free land, which scripts would otherwise have to work out.
+     */
+    pub fn porcelain(&self) -> String {
+        let mut fields = self.fields();
+        fields.insert("game".to_string(), self.game_id().to_string());
+        fields.insert("hash".to_string(), self.hash().to_string());
+        fields.insert("seed".to_string(), self.seed.to_string());
+        fields.insert("citizens.count".to_string(), self.citizens.count().to_string());
+        fields.insert("land.free_land".to_string(), self.land.free_land().to_string());
+
+        lines(&fields)
+    }
+
+    fn fields(&self) -> BTreeMap<String, String> {
+        let mut fields = BTreeMap::new();
+        fields.insert("iterations".to_string(), self.iterations.to_string());
+        fields.insert("prev_hash".to_string(), self.prev_hash.to_string());
+
+        for (section, value) in [
+            ("resources", serde_json::to_value(&self.resources)),
+            ("citizens", serde_json::to_value(&self.citizens)),
+            ("land", serde_json::to_value(&self.land))
+        ] {
+            flatten(section.to_string(), &value.unwrap(), &mut fields);
+        }
+
+        fields
+    }
+
     /**
      * Get current state hash.
      */
-    pub fn hash(&self) -> u64 {
-        let mut hasher = DefaultHasher::default();
-        hasher.write_i128(self.seed);
-        hasher.write_u64(self.prev_hash);
-        hasher.write_usize(self.iterations);
-        hasher.write_u64(self.resources.hash());
-        hasher.write_u64(self.citizens.hash());
-        hasher.write_u64(self.land.hash());
-        hasher.finish()
+    pub fn hash(&self) -> Digest {
+        self.commitment().hash()
+    }
+
+    pub fn commitment(&self) -> Commitment {
+        self.commitment_with(self.settings.hash(), self.land.hash())
+    }
+
+    /**
+     * Commitment reusing the digests of the settings and the land, for stretches of
+     * iterations known to leave them alone.
+     */
+    fn commitment_with(&self, settings: Digest, land: Digest) -> Commitment {
+        Commitment {
+            seed: self.seed,
+            settings,
+            prev_hash: self.prev_hash,
+            iterations: self.iterations,
+            log_root: self.log_tree.root(),
+            resources: self.resources.hash(),
+            citizens: self.citizens.hash(),
+            land,
+            rules: self.rules.clone(),
+            trades: match self.trades.is_empty() {
+                true => Digest::default(),
+                false => {
+                    let mut hasher = StableHasher::default();
+                    for id in self.trades.iter() {
+                        hasher.write(&id.0);
+                    }
+                    hasher.digest()
+                }
+            }
+        }
+    }
+
+    /**
+     * Compact proof of the run, with inclusion proofs of the actions picked by its hash.
+     */
+    pub fn run_proof(&self) -> RunProof {
+        let samples = RunProof::sampled_actions(&self.hash(), self.log_tree.leaves()).into_iter()
+            .filter_map(|index| self.prove(index))
+            .collect();
+
+        RunProof {
+            commitment: self.commitment(),
+            resources: self.resources.clone(),
+            citizens: self.citizens.clone(),
+            actions: self.log_tree.leaves(),
+            hash: self.hash(),
+            signature: self.signature.clone(),
+            samples
+        }
     }
 
     /**
@@ -160,6 +1511,12 @@ impl State {
         Self::new(rng.gen())
     }
 
+    pub fn rand_with_settings(settings: Settings) -> Self {
+        let mut rng = thread_rng();
+        Self::with_settings(rng.gen(), settings)
+    }
+
+    #[allow(clippy::identity_op, clippy::erasing_op)]
     fn get_rng(&self) -> ChaChaRng {
         let seed: [u8; 32] = [
             // State seed
@@ -181,25 +1538,47 @@ impl State {
             ((self.seed >> (8 * 15)) & 0b11111111) as u8,
 
             // State previous hash
-            ((self.prev_hash >> (8 * 0)) & 0b11111111) as u8,
-            ((self.prev_hash >> (8 * 1)) & 0b11111111) as u8,
-            ((self.prev_hash >> (8 * 2)) & 0b11111111) as u8,
-            ((self.prev_hash >> (8 * 3)) & 0b11111111) as u8,
-            ((self.prev_hash >> (8 * 4)) & 0b11111111) as u8,
-            ((self.prev_hash >> (8 * 5)) & 0b11111111) as u8,
-            ((self.prev_hash >> (8 * 6)) & 0b11111111) as u8,
-            ((self.prev_hash >> (8 * 7)) & 0b11111111) as u8,
+            self.prev_hash.0[0],
+            self.prev_hash.0[1],
+            self.prev_hash.0[2],
+            self.prev_hash.0[3],
+            self.prev_hash.0[4],
+            self.prev_hash.0[5],
+            self.prev_hash.0[6],
+            self.prev_hash.0[7],
 
             // Padding
             0, 0, 0, 0, 0, 0, 0, 0
         ];
 
-        return ChaChaRng::from_seed(seed);
+        ChaChaRng::from_seed(seed)
+    }
+
+    /**
+     * Rng used only to roll the starting conditions. The padding keeps it apart from
+     * the first iteration rng, which shares the same seed and a zero previous hash.
+     */
+    fn get_genesis_rng(seed: i128) -> ChaChaRng {
+        let mut genesis_seed = [0xff_u8; 32];
+        genesis_seed[..16].copy_from_slice(&seed.to_le_bytes());
+
+        ChaChaRng::from_seed(genesis_seed)
     }
 
     fn get_context(&self) -> Context {
         Context {
-            rng: self.get_rng()
+            rng: self.get_rng(),
+            sandbox: self.settings.sandbox,
+            morale: self.citizens.morale(&self.land),
+            birth_rate: self.citizens.birth_rate(&self.land),
+            soft_cap: self.settings.soft_cap,
+            fatigue: self.settings.fatigue,
+            construction: self.settings.construction,
+            pity: self.settings.pity,
+            gold_strikes: self.settings.gold_strikes,
+            events: vec!{},
+            drops: self.settings.drop_table(),
+            draws: vec!{}
         }
     }
 }
@@ -207,48 +1586,148 @@ impl State {
 impl Display for State {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         let log_limit = 5;
+        let morale = self.citizens.morale(&self.land);
 
-        writeln!(f, "")?;
+        writeln!(f)?;
         writeln!(f, "Seed\t\t{:032x}", self.seed)?;
-        writeln!(f, "Previous Hash\t{:016x}", self.prev_hash)?;
+        writeln!(f, "Phrase\t\t{}", seed_phrase(self.seed))?;
+        if let Some(Scenario::Imported(import)) = &self.settings.scenario {
+            writeln!(f, "Imported from\t{}", import.source)?;
+        } else if let Some(scenario) = &self.settings.scenario {
+            writeln!(f, "Scenario\t{}", scenario.name())?;
+        }
+        if self.settings.sandbox {
+            writeln!(f, "Mode\t\tSandbox")?;
+        }
+        if self.settings.ironman {
+            writeln!(f, "Mode\t\tIronman")?;
+        }
+        if self.settings.free_management {
+            writeln!(f, "Rules\t\tFree management")?;
+        }
+        if let Some(soft_cap) = self.settings.soft_cap {
+            writeln!(f, "Rules\t\tSoft cap of {} workers per job", soft_cap)?;
+        }
+        if self.settings.fatigue {
+            writeln!(f, "Rules\t\tFatigue")?;
+        }
+        if self.settings.construction {
+            writeln!(f, "Rules\t\tConstruction time")?;
+        }
+        if self.settings.pity {
+            writeln!(f, "Rules\t\tPity after {} missed draws", PITY_DRAWS)?;
+        }
+        if self.settings.gold_strikes {
+            writeln!(f, "Rules\t\tGold strikes")?;
+        }
+        if self.settings.gems {
+            writeln!(f, "Rules\t\tGems")?;
+        }
+        if let (Some(date), Some(budget)) = (&self.settings.daily, self.settings.turn_budget()) {
+            writeln!(f, "Challenge\tDaily of {}\t({} of {} turns left)", date, budget.saturating_sub(self.iterations), budget)?;
+        }
+        if !self.settings.is_ranked() {
+            writeln!(f, "Ranked\t\tNo")?;
+        }
+        writeln!(f, "Previous Hash\t{}", self.prev_hash)?;
+        writeln!(f, "Log Root\t{}", self.log_root())?;
+        if let Some(signature) = &self.signature {
+            writeln!(f, "Signed by\t{}", signature.public_key)?;
+        }
+        if let Some(snapshot) = self.snapshots.last() {
+            writeln!(f, "Snapshots\t{}\t(latest #{})", self.snapshots.len(), snapshot.state.iterations)?;
+        }
         writeln!(f, "Resources:")?;
         writeln!(f, "\tPrimary:")?;
-        writeln!(f, "\t\tFood\t\t{:.2}\t{:.2}/i\t(max {})",
+        writeln!(f, "\t\tFood\t\t{}\t{}/i\t(max {}, spoils {}%/i)",
             self.resources.food,
-            self.resources.food_prod_rate * self.resources.food_prod_rate_multiplier - self.resources.food_cons_rate,
-            self.resources.max_food
+            self.resources.food_net_rate(morale),
+            self.resources.max_food,
+            self.resources.food_spoilage_percent
         )?;
-        writeln!(f, "\t\tWood\t\t{:.2}\t{:.2}/i\t(max {})",
+        writeln!(f, "\t\tWood\t\t{}\t{}/i\t(max {})",
             self.resources.wood,
-            self.resources.wood_prod_rate * self.resources.wood_prod_rate_multiplier,
+            self.resources.wood_prod_rate * self.resources.wood_prod_rate_multiplier * morale,
             self.resources.max_wood
         )?;
-        writeln!(f, "\t\tStone\t\t{:.2}\t{:.2}/i\t(max {})",
+        writeln!(f, "\t\tStone\t\t{}\t{}/i\t(max {})",
             self.resources.stone,
-            self.resources.stone_prod_rate * self.resources.stone_prod_rate_multiplier,
+            self.resources.stone_prod_rate * self.resources.stone_prod_rate_multiplier * morale,
             self.resources.max_stone
         )?;
+        writeln!(f, "\t\tWater\t\t{}\t{}/i\t(max {})",
+            self.resources.water,
+            self.resources.water_prod_rate - self.resources.water_cons_rate,
+            self.resources.max_water
+        )?;
         writeln!(f, "\tSecondary:")?;
-        writeln!(f, "\t\tSkins\t\t{}", self.resources.skins)?;
-        writeln!(f, "\t\tHerbs\t\t{}", self.resources.herbs)?;
-        writeln!(f, "\t\tOre\t\t{}", self.resources.ore)?;
+        writeln!(f, "\t\tSkins\t\t{}\t(max {})", self.resources.skins, self.resources.max_skins)?;
+        writeln!(f, "\t\tHerbs\t\t{}\t(max {})", self.resources.herbs, self.resources.max_herbs)?;
+        writeln!(f, "\t\tOre\t\t{}\t(max {})", self.resources.ore, self.resources.max_ore)?;
         writeln!(f, "\tTertiary:")?;
-        writeln!(f, "\t\tLeather\t\t{}", self.resources.leather)?;
+        writeln!(f, "\t\tLeather\t\t{}\t(max {})", self.resources.leather, self.resources.max_leather)?;
         writeln!(f, "\t\tPiety\t\t{}", self.resources.piety)?;
-        writeln!(f, "\t\tMetal\t\t{}", self.resources.metal)?;
+        writeln!(f, "\t\tMetal\t\t{}\t(max {})", self.resources.metal, self.resources.max_metal)?;
         writeln!(f, "\tSpetial:")?;
-        writeln!(f, "\t\tGold\t\t{}", self.resources.gold)?;
+        writeln!(f, "\t\tGold\t\t{}\t(max {})", self.resources.gold, self.resources.max_gold)?;
         writeln!(f, "\t\tCorpses\t\t{}", self.resources.corpses)?;
+        if self.settings.gems {
+            writeln!(f, "\t\tGems\t\t{}\t(max {})", self.resources.gems, MAX_GEMS)?;
+        }
         writeln!(f, "Citizens:")?;
         writeln!(f, "\tStats:")?;
         writeln!(f, "\t\tMax\t\t{}", self.citizens.max_population)?;
         writeln!(f, "\t\tIdle\t\t{}", self.citizens.idle)?;
         writeln!(f, "\t\tTotal\t\t{}", self.citizens.count())?;
+        writeln!(f, "\t\tMorale\t\t{}\t(housing quality {})", morale, self.land.housing_quality())?;
+        writeln!(f, "\tClasses:")?;
+        writeln!(f, "\t\tLaborers\t{}", self.citizens.laborers())?;
+        writeln!(f, "\t\tSpecialists\t{}\t(idle {})", self.citizens.specialists(), self.citizens.idle_specialists())?;
+        writeln!(f, "\t\tNobles\t\t{}", self.citizens.nobles)?;
+        writeln!(f, "\t\tTrainees\t{}\t(max {})", self.citizens.trainees.len(), self.citizens.max_trainees)?;
         writeln!(f, "\tWorkers:")?;
         writeln!(f, "\t\tFarmers\t\t{}", self.citizens.farmers)?;
         writeln!(f, "\t\tWoodcutters\t{}", self.citizens.woodcutters)?;
         writeln!(f, "\t\tMiners\t\t{}", self.citizens.miners)?;
+        writeln!(f, "\t\tScholars\t{}", self.citizens.scholars)?;
+        writeln!(f, "\t\tBlacksmiths\t{}", self.citizens.blacksmiths)?;
+        writeln!(f, "\t\tClerics\t\t{}", self.citizens.clerics)?;
+        if self.settings.construction {
+            writeln!(f, "\t\tBuilders\t{}", self.citizens.builders)?;
+        }
+        writeln!(f, "\t\tSurveyors\t{}", self.citizens.surveyors)?;
+        if !self.citizens.fatigue.is_empty() {
+            writeln!(f, "\tFatigue:")?;
+            for (job, _) in self.citizens.fatigue.iter() {
+                writeln!(f, "\t\t{:?}\t\t-{}%", job, self.citizens.fatigue(job).as_hundredths())?;
+            }
+        }
+        writeln!(f, "Land:")?;
+        writeln!(f, "\tFree\t\t{}\t(total {})", self.land.free_land(), self.land.total_land)?;
+        if self.land.surveyed > 0 || self.land.survey > Quantity::ZERO {
+            writeln!(f, "\tSurveyed\t{}\t({} of {} effort into the next)", self.land.surveyed, self.land.survey, self.land.parcel_effort())?;
+        }
+        for (building, remaining) in self.land.construction.iter() {
+            writeln!(f, "\tBuilding\t{:?}\t({} of {} work left)", building, remaining, building.construction_work())?;
+        }
+        if !self.omens.is_empty() {
+            writeln!(f, "Prophecies:")?;
+            for (iteration, event) in self.omens.iter() {
+                writeln!(f, "\t#{}\t{:?}", iteration, event)?;
+            }
+        }
+        if !self.events.is_empty() {
+            writeln!(f, "Events:")?;
+            for (iteration, event) in self.events.iter().rev().take(log_limit).rev() {
+                writeln!(f, "\t#{}\t{:?}", iteration, event)?;
+            }
+        }
         writeln!(f, "Log:")?;
+        if self.log.is_empty() && self.log_tree.leaves() > 1 {
+            writeln!(f, "\t{} actions, not loaded", self.log_tree.leaves())?;
+        } else if self.log.is_empty() && self.log_tree.leaves() == 1 {
+            writeln!(f, "\t1 action, not loaded")?;
+        }
         if self.log.len() > log_limit + 1 {
             writeln!(f, "\t... {} more entries ...", self.log.len() - log_limit)?;
         } else if self.log.len() == log_limit + 1 {
@@ -260,3 +1739,490 @@ impl Display for State {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::game::citizens::Citizens;
+    use crate::game::land::Land;
+    use crate::game::actions::Action;
+    use crate::game::resources::{Resource, PrimaryResource, TertiaryResource, SpecialResource, Resources};
+    use crate::game::quantity::Quantity;
+    use crate::game::buildings::Buildings;
+    use crate::game::jobs::{Specialization, Job};
+    use crate::game::errors::{IterationError, CheckError, RollbackError};
+    use crate::game::settings::Settings;
+    use crate::game::events::Event;
+    use crate::game::observer::StateObserver;
+    use super::State;
+
+    #[test]
+    fn farmer() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 200),
+                (Action::Collect(PrimaryResource::Stone), 200),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+                (Action::Idle, 100),
+            }).unwrap();
+
+        println!("{}", state);
+
+        assert!(state.check().is_ok());
+    }
+
+    #[test]
+    fn food_production() {
+        let state = State::new(-141872649309347578469772012024767025949)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 200),
+                (Action::Build(Buildings::WoodenHut), 3),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+                (Action::Idle, 101),
+            }).unwrap();
+
+        println!("{}", state);
+
+        assert_eq!(state.resources.food, Quantity::units(200));
+        assert!(state.check().is_ok());
+    }
+
+    #[test]
+    fn house_requires_metal() {
+        let result = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Wood), 30),
+                (Action::Collect(PrimaryResource::Stone), 70),
+                (Action::Build(Buildings::House), 1),
+            });
+
+        assert!(matches!(
+            result,
+            Err(IterationError::NotEnaughtResource(Resource::Tertiary(TertiaryResource::Metal)))
+        ));
+    }
+
+    #[test]
+    fn school_trains_specialists() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 100),
+                (Action::Collect(PrimaryResource::Wood), 100),
+                (Action::Collect(PrimaryResource::Stone), 150),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::Build(Buildings::School), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::Train(Specialization::Blacksmith), 1),
+            }).unwrap();
+
+        assert!(matches!(
+            state.clone().apply_action(Action::AssignJob(Job::Blacksmith)),
+            Err(IterationError::NotEnaughtTrainedSpecialists(Specialization::Blacksmith))
+        ));
+
+        let state = state
+            .apply_log(vec!{
+                (Action::Idle, 10),
+                (Action::AssignJob(Job::Blacksmith), 1),
+            }).unwrap();
+
+        println!("{}", state);
+
+        assert!(state.check().is_ok());
+    }
+
+    #[test]
+    fn sandbox_ignores_costs() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{
+                (Action::Build(Buildings::Mansion), 1),
+                (Action::Grant(Resource::Special(SpecialResource::Gold), 80), 1),
+            }).unwrap();
+
+        assert_eq!(state.resources.gold, Quantity::units(80));
+        assert!(state.check().is_ok());
+
+        let result = State::new(43932030939219715774207308070970463251)
+            .apply_action(Action::Grant(Resource::Special(SpecialResource::Gold), 80));
+
+        assert!(matches!(result, Err(IterationError::SandboxOnly)));
+    }
+
+    #[test]
+    fn shortage_reassigns_workers() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 200),
+                (Action::Collect(PrimaryResource::Wood), 100),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::RecruitCitizen, 2),
+                (Action::AssignJob(Job::Woodcutter), 2),
+                (Action::PrioritizeJobs(vec!{Job::Farmer, Job::Woodcutter}), 1),
+                (Action::Idle, 5),
+            }).unwrap();
+
+        println!("{}", state);
+
+        assert_eq!(state.resources.food_prod_rate, Quantity::hundredths(240));
+        assert_eq!(state.resources.wood_prod_rate, Quantity::ZERO);
+        assert!(state.check().is_ok());
+    }
+
+    #[test]
+    fn turns_are_atomic() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 100),
+                (Action::Collect(PrimaryResource::Wood), 100),
+                (Action::Turn(vec!{
+                    Action::Build(Buildings::WoodenHut),
+                    Action::RecruitCitizen,
+                    Action::AssignJob(Job::Farmer),
+                }), 1),
+            }).unwrap();
+
+        assert_eq!(state.resources.food_prod_rate, Quantity::hundredths(120));
+        assert!(state.check().is_ok());
+
+        let result = state.apply_action(Action::Turn(vec!{
+            Action::RecruitCitizen,
+            Action::AssignJob(Job::Miner),
+            Action::AssignJob(Job::Miner),
+        }));
+
+        assert!(matches!(result, Err(IterationError::NotEnaughtIdleWorkers)));
+    }
+
+    #[test]
+    fn turns_gather_at_most_once() {
+        let state = State::new(43932030939219715774207308070970463251);
+        let collect = Action::Collect(PrimaryResource::Wood);
+
+        for turn in [
+            Action::Turn(vec!{collect.clone(); 500}),
+            Action::Turn(vec!{collect.clone(), Action::Collect(PrimaryResource::Stone)}),
+            Action::Turn(vec!{Action::Turn(vec!{collect.clone()})}),
+            Action::Turn(vec!{})
+        ] {
+            assert!(matches!(state.clone().apply_action(turn), Err(IterationError::InvalidTurn)));
+        }
+
+        let state = state.apply_action(Action::Turn(vec!{collect, Action::Idle})).unwrap();
+        assert_eq!(state.iterations(), 1);
+    }
+
+    #[test]
+    fn free_management_takes_no_iteration() {
+        let settings = Settings { free_management: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 100),
+                (Action::Collect(PrimaryResource::Wood), 100),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::RecruitCitizen, 1),
+                (Action::AssignJob(Job::Farmer), 1),
+            }).unwrap();
+
+        let food = state.resources.food;
+        let state = state
+            .apply_log(vec!{
+                (Action::DischargeJob(Job::Farmer), 1),
+                (Action::AssignJob(Job::Farmer), 1),
+            }).unwrap();
+
+        assert_eq!(state.resources.food, food);
+        assert!(state.check().is_ok());
+    }
+
+    #[test]
+    fn rollback_restores_earlier_iteration() {
+        let seed = 43932030939219715774207308070970463251;
+        let log = vec!{
+            (Action::Collect(PrimaryResource::Food), 120),
+            (Action::Collect(PrimaryResource::Wood), 130),
+        };
+        let state = State::new(seed).apply_log(log.clone()).unwrap();
+        let expected = State::new(seed).apply_log(vec!{
+            (Action::Collect(PrimaryResource::Food), 120),
+            (Action::Collect(PrimaryResource::Wood), 30),
+        }).unwrap();
+
+        let rolled_back = state.rollback(150).unwrap();
+
+        assert_eq!(rolled_back.hash(), expected.hash());
+        assert!(rolled_back.check().is_ok());
+
+        let settings = Settings { ironman: true, ..Default::default() };
+        let ironman = State::with_settings(seed, settings).apply_log(log).unwrap();
+
+        assert!(matches!(ironman.rollback(150), Err(RollbackError::Ironman)));
+    }
+
+    #[test]
+    fn check_resumes_from_checkpoint() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 150)}).unwrap();
+        let checkpoint = state.check_from(None).unwrap().unwrap();
+
+        let state = state.apply_log(vec!{(Action::Collect(PrimaryResource::Wood), 20)}).unwrap();
+
+        assert!(state.check_from(Some(&checkpoint)).is_ok());
+    }
+
+    #[test]
+    fn check_refuses_edited_snapshots() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 250)}).unwrap();
+        // Omens are not hashed, and the first ones saved belong to the oldest snapshot.
+        let save = serde_json::to_string(&state).unwrap().replacen("\"omens\":[]", "\"omens\":[[150,\"GoldStruck\"]]", 1);
+        let edited: State = serde_json::from_str(&save).unwrap();
+
+        assert!(state.check().is_ok());
+        assert_eq!(edited.hash(), state.hash());
+        assert!(matches!(edited.check(), Err(CheckError::SnapshotMismatch(100))));
+    }
+
+    #[test]
+    fn repair_keeps_valid_state() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 120)}).unwrap();
+
+        let (repaired_state, dropped_actions) = state.repair().unwrap();
+
+        assert_eq!(dropped_actions, 0);
+        assert_eq!(repaired_state.hash(), state.hash());
+    }
+
+    #[test]
+    fn repair_drops_actions_after_the_first_failure() {
+        let log = vec!{(Action::Collect(PrimaryResource::Food), 10), (Action::Collect(PrimaryResource::Wood), 5)};
+        let state = State::new(43932030939219715774207308070970463251).apply_log(log).unwrap();
+        let broken: State = serde_json::from_str(&serde_json::to_string(&state).unwrap()
+            .replace(&serde_json::to_string(&Action::Collect(PrimaryResource::Wood)).unwrap(), &serde_json::to_string(&Action::Build(Buildings::Mansion)).unwrap()))
+            .unwrap();
+        let valid = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+
+        let (repaired_state, dropped_actions) = broken.repair().unwrap();
+
+        assert_eq!(dropped_actions, 5);
+        assert_eq!(repaired_state.hash(), valid.hash());
+        assert!(repaired_state.check().is_ok());
+    }
+
+    #[test]
+    fn replay_narrates_every_action() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 5),
+                (Action::Collect(PrimaryResource::Wood), 5),
+            }).unwrap();
+
+        let mut steps = 0;
+        let mut food = Quantity::ZERO;
+
+        state.replay(usize::MAX, |step| {
+            steps += 1;
+            food += step.resource_deltas.iter()
+                .filter(|(resource, _)| *resource == Resource::Primary(PrimaryResource::Food))
+                .fold(Quantity::ZERO, |total, (_, delta)| total + *delta);
+        }).unwrap();
+
+        assert_eq!(steps, 10);
+        assert_eq!(food, state.resources.food);
+    }
+
+    #[test]
+    fn at_rebuilds_past_ironman_state() {
+        let seed = 43932030939219715774207308070970463251;
+        let settings = Settings { ironman: true, ..Default::default() };
+        let state = State::with_settings(seed, settings.clone())
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 130)}).unwrap();
+        let expected = State::with_settings(seed, settings)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 110)}).unwrap();
+
+        assert_eq!(state.at(110).unwrap().hash(), expected.hash());
+    }
+
+    #[test]
+    fn exported_log_rebuilds_state() {
+        let seed = 43932030939219715774207308070970463251;
+        let state = State::new(seed)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 20), (Action::Collect(PrimaryResource::Wood), 25)}).unwrap();
+        let document = serde_json::to_string(&state.log_document()).unwrap();
+        let imported = State::from_log_document(serde_json::from_str(&document).unwrap()).unwrap();
+
+        assert_eq!(imported.hash(), state.hash());
+    }
+
+    #[test]
+    fn clerics_foresee_what_idling_brings() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{
+                (Action::Grant(Resource::Primary(PrimaryResource::Wood), 185), 1),
+                (Action::Grant(Resource::Primary(PrimaryResource::Food), 100), 1),
+                (Action::Build(Buildings::Tent), 2),
+                (Action::Build(Buildings::School), 1),
+                (Action::RecruitCitizen, 2),
+                (Action::Train(Specialization::Cleric), 1),
+                (Action::AssignJob(Job::Woodcutter), 1)
+            }).unwrap();
+
+        match state.clone().apply_action(Action::ReadOmens(10)) {
+            Err(error) => assert_eq!(error.code(), "not_enough_workers_in_job"),
+            Ok(_) => panic!("omens were read without clerics")
+        }
+        match State::new(43932030939219715774207308070970463251).apply_action(Action::ReadOmens(10)) {
+            Err(error) => assert_eq!(error.exit_code(), 10),
+            Ok(_) => panic!("omens were read without piety")
+        }
+
+        let state = state.apply_log(vec!{(Action::Idle, 15), (Action::AssignJob(Job::Cleric), 1), (Action::Idle, 20)}).unwrap();
+        let foreseen = state.clone().apply_action(Action::ReadOmens(30)).unwrap();
+        let idled = foreseen.clone().fast_forward(30).unwrap();
+        let came: Vec<(usize, Event)> = idled.recent_events().iter()
+            .filter(|(iteration, _)| *iteration > foreseen.iterations()).cloned().collect();
+
+        assert!(!foreseen.omens().is_empty());
+        assert_eq!(foreseen.omens(), came.as_slice());
+        assert!(foreseen.to_string().contains("Prophecies:"));
+        assert!(idled.omens().is_empty());
+        assert_eq!(Action::ReadOmens(30).words(), ("read", Some("omens".to_string())));
+    }
+
+    #[test]
+    fn fields_hash_apart() {
+        let builders = Citizens { builders: 5, ..Default::default() };
+        let surveyors = Citizens { surveyors: 5, ..Default::default() };
+        assert_ne!(builders.hash(), surveyors.hash());
+
+        let surveyed = Land { surveyed: 1, ..Default::default() };
+        let surveying = Land { survey: Quantity::ONE, ..Default::default() };
+        assert_ne!(surveyed.hash(), surveying.hash());
+
+        let gems = Resources { gems: Quantity::ONE, ..Default::default() };
+        assert_ne!(gems.hash(), Resources::default().hash());
+
+        let uncapped = Settings::default();
+        let capped = Settings { soft_cap: Some(0), ..Default::default() };
+        assert_ne!(uncapped.hash(), capped.hash());
+    }
+
+    #[test]
+    fn log_spans_cover_the_iterations_of_each_entry() {
+        let settings = Settings { sandbox: true, free_management: true, ..Default::default() };
+        let state = State::with_settings(43932030939219715774207308070970463251, settings)
+            .apply_log(vec!{
+                (Action::Collect(PrimaryResource::Food), 5),
+                (Action::Build(Buildings::WoodenHut), 1),
+                (Action::RecruitCitizen, 2),
+                (Action::AssignJob(Job::Farmer), 2),
+                (Action::Turn(vec!{Action::Build(Buildings::Barn), Action::Collect(PrimaryResource::Wood)}), 1)
+            }).unwrap();
+        let spans: Vec<(usize, usize)> = state.log_spans().into_iter().map(|(_, first, last)| (first, last)).collect();
+
+        assert_eq!(spans, vec!{(1, 5), (6, 6), (7, 8), (8, 8), (9, 9)});
+        assert_eq!(Action::AssignJob(Job::Farmer).words(), ("assign", Some("farmer".to_string())));
+        assert_eq!(Action::Build(Buildings::WoodenHut).words(), ("build", Some("woodenhut".to_string())));
+        assert!(state.log()[4].0.steps().iter().any(|step| step.words().0 == "build"));
+        assert!(state.log().iter().all(|(action, _)| Action::VERBS.contains(&action.words().0)));
+    }
+
+    #[test]
+    fn check_reports_where_state_diverges() {
+        let mut state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        let expected_hash = state.hash();
+        state.resources.food += Quantity::ONE;
+
+        match state.check() {
+            Err(CheckError::HashMismatch { iteration, expected, actual, differences }) => {
+                assert_eq!(iteration, 10);
+                assert_eq!(actual, expected_hash);
+                assert_eq!(expected, state.hash());
+                assert_eq!(differences.len(), 1);
+                assert_eq!(differences[0].to_string(), format!("resources.food {} vs {}", state.resources.food, state.resources.food - Quantity::ONE));
+            },
+            _ => panic!("tampered state passed the check")
+        }
+    }
+
+    #[test]
+    fn replay_chain_ends_on_state_hash() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10), (Action::Collect(PrimaryResource::Wood), 5)}).unwrap();
+        let mut hashes = vec!{};
+        state.replay(usize::MAX, |step| hashes.push(step.hash)).unwrap();
+
+        assert_eq!(hashes.len(), 15);
+        assert_eq!(hashes.last(), Some(&state.hash()));
+    }
+
+    #[test]
+    fn edited_state_is_tampered() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        let dump = toml::to_string_pretty(&state.editable()).unwrap();
+        let loaded = State::from_editable(toml::from_str(&dump).unwrap()).unwrap();
+
+        assert_eq!(loaded.hash(), state.hash());
+        assert!(loaded.tampered());
+        assert!(matches!(loaded.check(), Err(CheckError::Tampered)));
+    }
+
+    #[test]
+    fn fast_forward_matches_idling_one_iteration_at_a_time() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 30), (Action::Collect(PrimaryResource::Wood), 30)}).unwrap();
+        let mut idled = state.clone();
+
+        for _ in 0..500 {
+            idled = idled.apply_action(Action::Idle).unwrap();
+        }
+
+        let fast_forwarded = state.fast_forward(500).unwrap();
+
+        assert_eq!(fast_forwarded.hash(), idled.hash());
+        assert_eq!(fast_forwarded.log(), idled.log());
+    }
+
+    #[test]
+    fn plans_apply_as_a_whole() {
+        struct Progress(Vec<(usize, usize)>);
+
+        impl StateObserver for Progress {
+            fn on_plan_progress(&mut self, _state: &State, applied: usize, total: usize) {
+                self.0.push((applied, total));
+            }
+        }
+
+        let state = State::new(43932030939219715774207308070970463251);
+        let mut progress = Progress(vec!{});
+        let planned = state.apply_plan_observed(vec!{
+            (Action::Collect(PrimaryResource::Food), 20),
+            (Action::RecruitCitizen, 1)
+        }, &mut progress);
+
+        assert!(matches!(planned, Err((1, IterationError::PopulationLimitReached))));
+        assert_eq!(progress.0, vec!{(1, 2)});
+        assert_eq!(state.commitment().iterations, 0);
+
+        let planned = state.apply_plan(vec!{(Action::Collect(PrimaryResource::Food), 20), (Action::Idle, 5)}).unwrap();
+
+        assert_eq!(planned.commitment().iterations, 25);
+    }
+}
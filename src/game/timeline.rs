@@ -0,0 +1,137 @@
+use serde::{Serialize, Deserialize};
+
+// Longest pause, in seconds, within a single play session.
+pub const SESSION_GAP: u64 = 30 * 60;
+
+/**
+ * Wall-clock time the log was saved at, as seconds since the unix epoch, with the
+ * actions in the log and the iterations of the game by then.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct LogTime {
+    pub at: u64,
+    pub actions: u64,
+    pub iterations: usize
+}
+
+/**
+ * Real-world time spent playing, summed over the saves made within a session of the
+ * one before, and the number of sessions it was spread over.
+ */
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlayTime {
+    pub seconds: u64,
+    pub sessions: u64
+}
+
+impl PlayTime {
+    /**
+     * Counts a save made at `now`, the one before having been made at `previous`, or
+     * never if zero.
+     */
+    pub fn record(self, previous: u64, now: u64) -> Self {
+        match now.checked_sub(previous) {
+            Some(seconds) if previous > 0 && seconds <= SESSION_GAP => Self { seconds: self.seconds + seconds, ..self },
+            _ => Self { sessions: self.sessions + 1, ..self }
+        }
+    }
+}
+
+/**
+ * Stretch of real-world play, and the iterations it took the game through.
+ */
+#[derive(Serialize, Debug, Eq, PartialEq)]
+pub struct Session {
+    pub start: u64,
+    pub end: u64,
+    pub first_iteration: usize,
+    pub last_iteration: usize,
+    pub actions: u64
+}
+
+impl Session {
+    pub fn iterations(&self) -> usize {
+        (self.last_iteration + 1).saturating_sub(self.first_iteration)
+    }
+}
+
+/**
+ * Groups the times the log was saved into sessions, splitting them wherever the player
+ * was away for longer than the gap. Actions taken before times were recorded count
+ * towards the first session.
+ */
+pub fn sessions(log_times: &[LogTime], gap: u64) -> Vec<Session> {
+    let mut sessions: Vec<Session> = vec!{};
+    let (mut actions, mut iterations) = (0, 0);
+
+    for log_time in log_times {
+        match sessions.last_mut() {
+            Some(session) if log_time.at.saturating_sub(session.end) <= gap => {
+                session.end = log_time.at;
+                session.last_iteration = log_time.iterations;
+                session.actions += log_time.actions - actions;
+            },
+            _ => sessions.push(Session {
+                start: log_time.at,
+                end: log_time.at,
+                first_iteration: iterations + 1,
+                last_iteration: log_time.iterations,
+                actions: log_time.actions - actions
+            })
+        }
+
+        actions = log_time.actions;
+        iterations = log_time.iterations;
+    }
+
+    sessions
+}
+
+/**
+ * Seconds since the unix epoch as a UTC date and time, down to the minute.
+ */
+pub fn utc(seconds: u64) -> String {
+    let (days, time) = ((seconds / 86400) as i64, seconds % 86400);
+
+    // Days since the epoch to a civil date, counting eras of 400 years from March 1st.
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, time / 3600, time % 3600 / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use super::{sessions, utc};
+
+    #[test]
+    fn timeline_splits_play_into_sessions() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap().stamp(1_000_000)
+            .stamp(1_000_100)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Wood), 5)}).unwrap().stamp(1_000_600)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 20)}).unwrap().stamp(1_090_000);
+
+        assert_eq!(state.log_times().len(), 3);
+
+        let sessions = sessions(state.log_times(), 30 * 60);
+        assert_eq!(sessions.len(), 2);
+        assert_eq!((sessions[0].first_iteration, sessions[0].last_iteration, sessions[0].actions), (1, 15, 15));
+        assert_eq!((sessions[1].start, sessions[1].iterations()), (1_090_000, 20));
+        assert_eq!(utc(1_792_151_839), "2026-10-16 11:57 UTC");
+        assert_eq!(utc(951_782_400), "2000-02-29 00:00 UTC");
+
+        let hash = state.hash();
+        assert_eq!(state.stamp(2_000_000).hash(), hash);
+    }
+}
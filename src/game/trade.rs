@@ -0,0 +1,123 @@
+use super::resources::Resource;
+use super::quantity::Quantity;
+use super::hashing::{Digest, StableHasher};
+use super::signing::SaveSignature;
+
+use serde::{Serialize, Deserialize};
+use ed25519_dalek::SigningKey;
+use std::hash::Hasher;
+
+/**
+ * Offer to barter resources with another player, signed by whoever makes it. It is
+ * bound to the state it was made from, so it can only be logged once, and what it
+ * gives is set aside in the offering game until the offer is settled.
+ *
+ * Trades rest on trust. Signatures only prove who made an offer or accepted it, not
+ * that the other game exists or holds what it gives: anyone with two keys can sign an
+ * offer and its receipt to conjure resources. Trades are thus only allowed in sandbox
+ * games.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct TradeOffer {
+    pub game: Digest,
+    pub state: Digest,
+    pub give: (Resource, Quantity),
+    pub want: (Resource, Quantity),
+    pub signature: SaveSignature
+}
+
+impl TradeOffer {
+    pub fn new(key: &SigningKey, game: Digest, state: Digest, give: (Resource, Quantity), want: (Resource, Quantity)) -> Self {
+        let digest = Self::digest_of(&game, &state, &give, &want);
+        Self { game, state, give, want, signature: SaveSignature::sign(key, &digest) }
+    }
+
+    fn digest_of(game: &Digest, state: &Digest, give: &(Resource, Quantity), want: &(Resource, Quantity)) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write(&game.0);
+        hasher.write(&state.0);
+        for (resource, amount) in [give, want] {
+            hasher.write(resource.name().as_bytes());
+            hasher.write(&amount.to_le_bytes());
+        }
+        hasher.digest()
+    }
+
+    /**
+     * Identifies the offer, leaving its signature out.
+     */
+    pub fn id(&self) -> Digest {
+        Self::digest_of(&self.game, &self.state, &self.give, &self.want)
+    }
+
+    pub fn verify(&self) -> bool {
+        self.give.1 > Quantity::ZERO && self.want.1 > Quantity::ZERO && self.signature.verify(&self.id())
+    }
+}
+
+/**
+ * Acceptance of an offer by another game, signed by whoever accepts it. The accepting
+ * game logs it to make the exchange, and the offering game logs it to settle the offer.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct TradeReceipt {
+    pub offer: TradeOffer,
+    pub game: Digest,
+    pub signature: SaveSignature
+}
+
+impl TradeReceipt {
+    pub fn new(key: &SigningKey, offer: TradeOffer, game: Digest) -> Self {
+        let digest = Self::digest_of(&offer, &game);
+        Self { offer, game, signature: SaveSignature::sign(key, &digest) }
+    }
+
+    fn digest_of(offer: &TradeOffer, game: &Digest) -> Digest {
+        let mut hasher = StableHasher::default();
+        hasher.write(&offer.id().0);
+        hasher.write(&game.0);
+        hasher.digest()
+    }
+
+    pub fn verify(&self) -> bool {
+        self.offer.verify() && self.game != self.offer.game && self.signature.verify(&Self::digest_of(&self.offer, &self.game))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::{Resource, PrimaryResource};
+    use crate::game::quantity::Quantity;
+    use crate::game::errors::IterationError;
+    use crate::game::settings::Settings;
+    use crate::game::signing::generate_key;
+    use super::{TradeOffer, TradeReceipt};
+
+    #[test]
+    fn trades_move_resources_between_games() {
+        let settings = Settings { sandbox: true, ..Default::default() };
+        let wood = Resource::Primary(PrimaryResource::Wood);
+        let stone = Resource::Primary(PrimaryResource::Stone);
+        let offering = State::with_settings(1, settings.clone()).apply_action(Action::Grant(wood.clone(), 50)).unwrap();
+        let accepting = State::with_settings(2, settings).apply_action(Action::Grant(stone.clone(), 50)).unwrap();
+
+        let offer = TradeOffer::new(&generate_key(), offering.game_id(), offering.hash(), (wood.clone(), Quantity::units(20)), (stone.clone(), Quantity::units(10)));
+        let offering = offering.apply_action(Action::OfferTrade(Box::new(offer.clone()))).unwrap();
+        let receipt = TradeReceipt::new(&generate_key(), offer, accepting.game_id());
+        let accepting = accepting.apply_action(Action::AcceptTrade(Box::new(receipt.clone()))).unwrap();
+        let offering = offering.apply_action(Action::SettleTrade(Box::new(receipt.clone()))).unwrap();
+
+        assert_eq!((offering.resources.wood, offering.resources.stone), (Quantity::units(30), Quantity::units(10)));
+        assert_eq!((accepting.resources.wood, accepting.resources.stone), (Quantity::units(20), Quantity::units(40)));
+        assert!(offering.check().is_ok() && accepting.check().is_ok());
+        assert!(matches!(offering.apply_action(Action::SettleTrade(Box::new(receipt))), Err(IterationError::InvalidTrade)));
+
+        for settings in [Settings::default(), Settings { sandbox: true, ..Settings::daily("2026-10-16") }] {
+            let ranked = State::with_settings(3, settings);
+            let offer = TradeOffer::new(&generate_key(), ranked.game_id(), ranked.hash(), (wood.clone(), Quantity::units(1)), (stone.clone(), Quantity::units(1)));
+            assert!(matches!(ranked.apply_action(Action::OfferTrade(Box::new(offer))), Err(IterationError::SandboxOnly)));
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use super::errors::Error;
+use super::events::Event;
+use super::resources::Resource;
+use super::buildings::Buildings;
+use super::state::State;
+
+use serde::Serialize;
+use serde_json::json;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+// Iterations of food left below which starvation is announced.
+const STARVATION_WARNING: u64 = 20;
+
+/**
+ * Something notable that happened in a game, worth telling its player about.
+ */
+#[derive(Serialize, Clone, Debug, Eq, PartialEq)]
+pub enum Notification {
+    StorageFull(Resource),
+    BuildingBuilt(Buildings),
+    StarvationImminent { iterations_left: u64 }
+}
+
+impl Display for Notification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::StorageFull(resource) => write!(f, "{} storage is full", resource.name()),
+            Self::BuildingBuilt(building) => write!(f, "built a {:?}", building),
+            Self::StarvationImminent { iterations_left } => write!(f, "food runs out in {} iterations", iterations_left)
+        }
+    }
+}
+
+impl Notification {
+    /**
+     * Names of the kinds of notification, as they are enabled by.
+     */
+    pub fn kinds() -> &'static [&'static str] {
+        &["storage-full", "building-built", "starvation-imminent"]
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::StorageFull(_) => "storage-full",
+            Self::BuildingBuilt(_) => "building-built",
+            Self::StarvationImminent { .. } => "starvation-imminent"
+        }
+    }
+
+    /**
+     * Notifications for going from one state to the next: the notable events that
+     * happened in between, and starvation when the food left first falls below the
+     * warning.
+     */
+    pub fn between(previous: &State, state: &State) -> Vec<Self> {
+        let mut notifications: Vec<Self> = state.events_since(previous.iterations()).into_iter()
+            .filter_map(|event| match event {
+                Event::ResourceCapped(resource) => Some(Self::StorageFull(resource)),
+                Event::BuildingBuilt(building) => Some(Self::BuildingBuilt(building)),
+                _ => None
+            })
+            .collect();
+
+        let warned = |state: &State| state.food_left().is_some_and(|left| left <= STARVATION_WARNING);
+        if let (false, true, Some(iterations_left)) = (warned(previous), warned(state), state.food_left()) {
+            notifications.push(Self::StarvationImminent { iterations_left });
+        }
+
+        notifications
+    }
+}
+
+/**
+ * URL notifications are posted to. Discord and Slack webhooks get a message in the
+ * form they expect, with the notification as JSON in a code block. Other URLs get
+ * the save, the iteration, the message and the notification as a JSON object.
+ */
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    pub url: String
+}
+
+impl Webhook {
+    pub fn payload(&self, save: &str, iteration: usize, notification: &Notification) -> serde_json::Value {
+        let message = format!("{}: {}", save, notification);
+        let text = format!("{}\n```json\n{}\n```", message, serde_json::to_string_pretty(notification).unwrap());
+
+        if self.url.contains("discord.com/api/webhooks/") || self.url.contains("discordapp.com/api/webhooks/") {
+            json!({ "content": text })
+        } else if self.url.contains("hooks.slack.com/") {
+            json!({ "text": text })
+        } else {
+            json!({ "save": save, "iteration": iteration, "message": message, "event": notification })
+        }
+    }
+
+    pub fn notify(&self, save: &str, iteration: usize, notification: &Notification) -> Result<(), Error> {
+        ureq::post(&self.url).set("Content-Type", "application/json")
+            .send_string(&self.payload(save, iteration, notification).to_string())
+            .map_err(|error| Error::Remote(error.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::{Resource, PrimaryResource};
+    use super::{Notification, Webhook};
+
+    #[test]
+    fn notable_events_are_posted_to_webhooks() {
+        let previous = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 199)}).unwrap();
+        let state = previous.clone().apply_log(vec!{(Action::Collect(PrimaryResource::Food), 2)}).unwrap();
+        let notifications = Notification::between(&previous, &state);
+
+        assert_eq!(notifications, vec!{Notification::StorageFull(Resource::Primary(PrimaryResource::Food))});
+        assert!(Notification::between(&state, &state).is_empty());
+
+        let generic = Webhook { url: "http://localhost/hooks".to_string() }.payload("town", 201, &notifications[0]);
+        let discord = Webhook { url: "https://discord.com/api/webhooks/1/abc".to_string() }.payload("town", 201, &notifications[0]);
+        let slack = Webhook { url: "https://hooks.slack.com/services/T/B/x".to_string() }.payload("town", 201, &notifications[0]);
+
+        assert_eq!(generic["event"], serde_json::to_value(&notifications[0]).unwrap());
+        assert_eq!(generic["message"], "town: food storage is full");
+        assert!(discord["content"].as_str().unwrap().contains("StorageFull"));
+        assert!(slack["text"].as_str().unwrap().starts_with("town: food storage is full"));
+    }
+}
@@ -0,0 +1,137 @@
+pub(crate) mod game;
+pub mod cli;
+#[cfg(feature = "cliciv-ffi")]
+pub mod ffi;
+// The methods of pyo3 0.20 expand to impls nested in functions.
+#[cfg(feature = "python")]
+#[allow(non_local_definitions)]
+pub mod python;
+
+pub use game::state::{State, LogEntry, LogDocument, ReplayStep, HistoryEntry, Difference};
+pub use game::actions::Action;
+pub use game::settings::Settings;
+pub use game::scenarios::{Scenario, Import};
+pub use game::resources::{Resources, Resource, PrimaryResource, SecondaryResource, TertiaryResource, SpecialResource};
+pub use game::quantity::Quantity;
+pub use game::jobs::{Job, Specialization, CitizenClass};
+pub use game::buildings::Buildings;
+pub use game::trade::{TradeOffer, TradeReceipt};
+pub use game::rules::Drop;
+pub use game::events::Event;
+pub use game::hashing::Digest;
+pub use game::errors::{Error, CheckError, IterationError, RollbackError};
+pub use game::observer::StateObserver;
+
+use serde::Serialize;
+
+/**
+ * Replays a game from its seed, settings and log, and checks it ends on the expected
+ * hash. Tools that only hold a run, like bots or challenge servers, can validate it
+ * without going through a save.
+ */
+pub fn verify(seed: i128, settings: Settings, log: Vec<LogEntry>, expected_hash: Digest) -> Result<State, CheckError> {
+    State::verify_log(seed, settings, log, expected_hash)
+}
+
+/**
+ * Checks the previous state and applies the log entries on top of it, as the command
+ * line does with every action. Tampered states are played without a check, as they
+ * can't pass one.
+ */
+pub fn advance(previous_state: State, log: Vec<LogEntry>) -> Result<State, Error> {
+    if !previous_state.tampered() {
+        previous_state.check().map_err(Error::Check)?;
+    }
+
+    previous_state.apply_log(log).map_err(Error::Iteration)
+}
+
+/**
+ * Rebuilds the game from its seed and log, describing each action taken up to the
+ * given iteration.
+ */
+pub fn replay<F: FnMut(ReplayStep)>(state: &State, until: usize, narrate: F) -> Result<(), IterationError> {
+    state.replay(until, narrate)
+}
+
+/**
+ * Outcome of `check --json`, for scripts watching shared saves. Failures tell where
+ * the replay went wrong when that is known.
+ */
+#[derive(Serialize)]
+pub struct CheckReport {
+    pub valid: bool,
+    pub code: Option<&'static str>,
+    pub error: Option<String>,
+    pub iteration: Option<usize>,
+    pub expected_hash: Option<Digest>,
+    pub actual_hash: Option<Digest>,
+    pub action: Option<Action>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub differences: Vec<Difference>
+}
+
+impl CheckReport {
+    pub fn new(result: Result<(), CheckError>) -> Self {
+        let report = Self { valid: false, code: None, error: None, iteration: None, expected_hash: None, actual_hash: None, action: None, differences: vec!{} };
+
+        let error = match result {
+            Ok(()) => return Self { valid: true, ..report },
+            Err(error) => error
+        };
+        let report = Self { code: Some(error.code()), error: Some(error.to_string()), ..report };
+
+        match error {
+            CheckError::HashMismatch { iteration, expected, actual, differences } => Self {
+                iteration: Some(iteration),
+                expected_hash: Some(expected),
+                actual_hash: Some(actual),
+                differences,
+                ..report
+            },
+            CheckError::InvalidStateRecreation { iteration, action, .. } => Self {
+                iteration: Some(iteration),
+                action: Some(action),
+                ..report
+            },
+            _ => report
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::state::State;
+    use crate::game::actions::Action;
+    use crate::game::resources::PrimaryResource;
+    use crate::game::quantity::Quantity;
+    use crate::game::errors::CheckError;
+
+    #[test]
+    fn library_advances_only_valid_states() {
+        let state = State::new(43932030939219715774207308070970463251);
+        let advanced = crate::advance(state, vec!{(Action::Collect(PrimaryResource::Food), 10)}).unwrap();
+        let mut steps = 0;
+        crate::replay(&advanced, usize::MAX, |_| steps += 1).unwrap();
+
+        let mut forged = advanced.clone();
+        forged.resources.food += Quantity::ONE;
+
+        assert_eq!(steps, 10);
+        assert!(matches!(crate::advance(forged, vec!{(Action::Idle, 1)}), Err(crate::Error::Check(CheckError::HashMismatch { .. }))));
+    }
+
+    #[test]
+    fn exported_log_verifies() {
+        let state = State::new(43932030939219715774207308070970463251)
+            .apply_log(vec!{(Action::Collect(PrimaryResource::Food), 20)}).unwrap();
+        let document = state.log_document();
+        let hash = document.hash.unwrap();
+
+        assert!(crate::verify(document.seed, document.settings.clone(), document.log.clone(), hash).is_ok());
+        assert!(matches!(
+            crate::verify(document.seed + 1, document.settings, document.log, hash),
+            Err(CheckError::HashMismatch { iteration: 20, .. })
+        ));
+    }
+}
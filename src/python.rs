@@ -0,0 +1,245 @@
+use super::{State, Action, Settings, LogEntry, Digest, Error, ReplayStep};
+
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::types::PyDict;
+use serde::Serialize;
+
+/*
+ * Python module of the engine, built with the `python` feature:
+ *
+ *     cargo rustc --release --lib --features python,pyo3/extension-module --crate-type cdylib
+ *
+ * and the library renamed to `cliciv.so` (`cliciv.pyd` on Windows). States and actions
+ * are classes; settings, logs and saves go in as the JSON written on the command line.
+ * Calls that fail raise `cliciv.CivError`, with the code and the reason as arguments.
+ */
+
+create_exception!(cliciv, CivError, PyException);
+
+fn fail(error: Error) -> PyErr {
+    CivError::new_err((error.code(), error.to_string()))
+}
+
+/**
+ * Turns a serializable value into plain Python objects, through its JSON.
+ */
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let json = serde_json::to_string(value).map_err(|error| fail(Error::Output(error.to_string())))?;
+
+    Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+}
+
+/**
+ * A game. Actions are applied in place, and leave the game as it was when they can't
+ * be taken.
+ */
+#[pyclass(name = "State", module = "cliciv")]
+#[derive(Clone)]
+pub struct PyState(State);
+
+#[pymethods]
+impl PyState {
+    /**
+     * Creates a new game from the seed, or a random one, with the settings in the JSON.
+     */
+    #[new]
+    #[pyo3(signature = (seed = None, settings = None))]
+    fn new(seed: Option<i128>, settings: Option<&str>) -> PyResult<Self> {
+        let settings: Settings = match settings {
+            Some(json) => serde_json::from_str(json).map_err(|error| fail(Error::InputParseError(error)))?,
+            None => Default::default()
+        };
+
+        Ok(Self(match seed {
+            Some(seed) => State::with_settings(seed, settings),
+            None => State::rand_with_settings(settings)
+        }))
+    }
+
+    /**
+     * Reads a game from the JSON of a save.
+     */
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json).map(Self).map_err(|error| fail(Error::InputParseError(error)))
+    }
+
+    /**
+     * JSON of the game, as it is saved.
+     */
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(|error| fail(Error::SaveEncode(error.to_string())))
+    }
+
+    fn apply(&mut self, action: &PyAction) -> PyResult<()> {
+        self.0 = self.0.clone().apply_action(action.0.clone()).map_err(|error| fail(Error::Iteration(error)))?;
+
+        Ok(())
+    }
+
+    /**
+     * Applies the entries of a log, in the JSON of the `log` of a save.
+     */
+    fn apply_log(&mut self, json: &str) -> PyResult<()> {
+        let log: Vec<LogEntry> = serde_json::from_str(json).map_err(|error| fail(Error::InputParseError(error)))?;
+        self.0 = self.0.clone().apply_log(log).map_err(|error| fail(Error::Iteration(error)))?;
+
+        Ok(())
+    }
+
+    /**
+     * Replays the game to make sure it wasn't tampered with.
+     */
+    fn check(&self) -> PyResult<()> {
+        self.0.check().map_err(|error| fail(Error::Check(error)))
+    }
+
+    #[getter]
+    fn seed(&self) -> i128 {
+        self.0.seed()
+    }
+
+    #[getter]
+    fn iterations(&self) -> usize {
+        self.0.iterations()
+    }
+
+    #[getter]
+    fn hash(&self) -> String {
+        self.0.hash().to_hex()
+    }
+
+    #[getter]
+    fn resources(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &self.0.resources)
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("State(seed={}, iterations={}, hash={})", self.0.seed(), self.0.iterations(), self.0.hash())
+    }
+}
+
+/**
+ * An action, read from the JSON written on the command line.
+ */
+#[pyclass(name = "Action", module = "cliciv")]
+#[derive(Clone)]
+pub struct PyAction(Action);
+
+#[pymethods]
+impl PyAction {
+    #[new]
+    fn new(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json).map(Self).map_err(|error| fail(Error::ActionParseError(error)))
+    }
+
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(|error| fail(Error::Output(error.to_string())))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        Ok(format!("Action({})", self.to_json()?))
+    }
+}
+
+fn step(py: Python<'_>, step: ReplayStep) -> PyResult<PyObject> {
+    let resource_deltas = PyDict::new(py);
+    for (resource, delta) in step.resource_deltas {
+        resource_deltas.set_item(resource.name(), delta.as_hundredths() as f64 / 100.0)?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("iteration", step.iteration)?;
+    dict.set_item("hash", step.hash.to_hex())?;
+    dict.set_item("action", Py::new(py, PyAction(step.action))?)?;
+    dict.set_item("resource_deltas", resource_deltas)?;
+    dict.set_item("population_delta", step.population_delta)?;
+    dict.set_item("events", to_python(py, &step.events)?)?;
+
+    Ok(dict.into())
+}
+
+/**
+ * Rebuilds the game from its seed and log, describing each action taken up to the
+ * given iteration, or all of them.
+ */
+#[pyfunction]
+#[pyo3(signature = (state, until = None))]
+fn replay(py: Python<'_>, state: &PyState, until: Option<usize>) -> PyResult<Vec<PyObject>> {
+    let mut steps = vec!{};
+    crate::replay(&state.0, until.unwrap_or_else(|| state.0.iterations()), |replayed| steps.push(replayed))
+        .map_err(|error| fail(Error::Iteration(error)))?;
+
+    steps.into_iter().map(|replayed| step(py, replayed)).collect()
+}
+
+/**
+ * Replays a game from its seed, the JSON of its settings and log, and checks it ends
+ * on the expected hash.
+ */
+#[pyfunction]
+fn verify(seed: i128, settings: &str, log: &str, expected_hash: &str) -> PyResult<PyState> {
+    let settings: Settings = serde_json::from_str(settings).map_err(|error| fail(Error::InputParseError(error)))?;
+    let log: Vec<LogEntry> = serde_json::from_str(log).map_err(|error| fail(Error::InputParseError(error)))?;
+    let expected_hash = Digest::from_hex(expected_hash).ok_or_else(|| fail(Error::InvalidDigest(expected_hash.to_string())))?;
+
+    crate::verify(seed, settings, log, expected_hash)
+        .map(PyState)
+        .map_err(|error| fail(Error::Check(error)))
+}
+
+#[pymodule]
+pub fn cliciv(py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add("CivError", py.get_type::<CivError>())?;
+    module.add_class::<PyState>()?;
+    module.add_class::<PyAction>()?;
+    module.add_function(wrap_pyfunction!(replay, module)?)?;
+    module.add_function(wrap_pyfunction!(verify, module)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cliciv;
+    use pyo3::prelude::*;
+    use pyo3::types::{IntoPyDict, PyModule};
+
+    #[test]
+    fn engine_is_driven_from_python() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "cliciv").unwrap();
+            cliciv(py, module).unwrap();
+            let locals = [("cliciv", module)].into_py_dict(py);
+
+            py.run(r#"
+import json
+
+state = cliciv.State(43932030939219715774207308070970463251)
+state.apply(cliciv.Action('{"Collect":"Food"}'))
+state.check()
+try:
+    state.apply(cliciv.Action('{"Build":"Nope"}'))
+    raise AssertionError("built nothing")
+except cliciv.CivError as error:
+    assert error.args[0] == "invalid_action"
+assert state.iterations == 1
+
+steps = cliciv.replay(state)
+assert [step["iteration"] for step in steps] == [1]
+assert steps[0]["resource_deltas"]["food"] > 0
+
+save = json.loads(state.to_json())
+verified = cliciv.verify(save["seed"], json.dumps(save["settings"]), json.dumps(save["log"]), state.hash)
+assert verified.hash == cliciv.State.from_json(state.to_json()).hash == state.hash
+"#, None, Some(locals)).unwrap();
+        });
+    }
+}